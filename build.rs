@@ -0,0 +1,7 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    tonic_build::configure()
+        .build_server(false)
+        .compile(&["proto/superchain.proto"], &["proto"])
+        .expect("the gRPC protos compile");
+}
@@ -6,8 +6,6 @@ use superchain_client::{
     config::Config,
     ethers::types::H160,
     futures::{self, StreamExt},
-    reqwest::{header::HeaderMap, Client},
-    url::Url,
     HttpClient,
 };
 
@@ -16,24 +14,12 @@ use superchain_client::{
 const PAIR: &str = "0xb4e16d0168e52d35cacd2c6185b44281ec28c9dc";
 /// The block height we want to receive logs from
 const FROM_BLOCK: u64 = 15_569_717;
-/// The base url endpoint
-const BASE_URL: &str = "https://beta.superchain.app/";
 
 #[tokio::main]
 async fn main() {
     // First, we create a new client
-    let http = Client::new();
-    let base_url = Url::from_str(BASE_URL).unwrap();
-    let mut headers = HeaderMap::new();
     let config = Config::from_env();
-    headers.append(
-        "Authorization",
-        config
-            .get_basic_authorization_value()
-            .try_into()
-            .expect("invalid auth value"),
-    );
-    let client = HttpClient::new(http, base_url).with_default_headers(headers);
+    let client = HttpClient::from_config(&config).unwrap();
 
     // Then we tell the HttpClient that we want uniswap v2 prices
     let pair = H160::from_str(PAIR).unwrap();
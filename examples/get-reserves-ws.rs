@@ -6,8 +6,6 @@ use superchain_client::{
     config::Config,
     ethers::types::H160,
     futures::{self, StreamExt},
-    tokio_tungstenite::connect_async,
-    tungstenite::{client::IntoClientRequest, http::header::AUTHORIZATION},
     WsClient,
 };
 
@@ -19,25 +17,12 @@ const FROM_BLOCK: Option<u64> = Some(15_645_429);
 /// the block height we want to receive prices to (inclusive)
 /// `None` means continue streaming from head
 const TO_BLOCK_INC: Option<u64> = None;
-/// The websocket endpoint url
-const URL: &str = "wss://beta.superchain.app/websocket";
 
 #[tokio::main]
 async fn main() {
     // First, we create a new client
-    // TODO: set the basic auth token below to the one given to you
-    let mut req = URL.into_client_request().expect("invalid url");
     let config = Config::from_env();
-    req.headers_mut().append(
-        AUTHORIZATION,
-        config
-            .get_basic_authorization_value()
-            .try_into()
-            .expect("invalid auth value"),
-    );
-
-    let (websocket, _) = connect_async(req).await.unwrap();
-    let client = WsClient::new(websocket).await;
+    let client = WsClient::connect_with_config(&config).await.unwrap();
 
     // Then we tell the WsClient that we want uniswap v2 reserves
     let pairs = PAIRS_FILTER
@@ -0,0 +1,30 @@
+// Serve recorded price quotes and reserves on localhost, e.g.
+// `cargo run --example replay-server --features server -- prices.csv reserves.csv`
+// Then run the other examples against it, by setting `SC_HTTP_URL=http://127.0.0.1:8097/` and
+// `SC_WS_URL=ws://127.0.0.1:8097/websocket`, which `Config::from_env` reads.
+use superchain_client::{server::ReplayServer, tokio::net::TcpListener, MockClient};
+
+/// The address to serve on
+const ADDR: &str = "127.0.0.1:8097";
+
+#[tokio::main]
+async fn main() {
+    // First, we load the recorded data, as served by the gateway
+    let mut args = std::env::args().skip(1);
+    let mut data = MockClient::new();
+    if let Some(prices) = args.next() {
+        data = data
+            .with_prices_from_csv(prices)
+            .expect("invalid prices csv");
+    }
+    if let Some(reserves) = args.next() {
+        data = data
+            .with_reserves_from_csv(reserves)
+            .expect("invalid reserves csv");
+    }
+
+    // And serve it until the process is stopped
+    let listener = TcpListener::bind(ADDR).await.unwrap();
+    println!("serving on http://{ADDR}/ and ws://{ADDR}/websocket");
+    ReplayServer::new(data).serve(listener).await.unwrap();
+}
@@ -0,0 +1,123 @@
+use std::collections::{HashMap, VecDeque};
+
+use ethers::types::Address;
+use futures::Stream;
+
+use super::{window::Window, Process};
+use crate::{
+    types::{Interval, Price},
+    Result,
+};
+
+/// An alert raised by [`PriceAlerts`] for `price`
+#[derive(Clone, Debug)]
+pub struct Alert {
+    pub price: Price,
+    pub kind: AlertKind,
+}
+
+/// The reason of an [`Alert`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AlertKind {
+    /// The price moved by `change`, relative to the `reference` price at the window start
+    Move { reference: f64, change: f64 },
+    /// The price rose above the threshold
+    CrossedAbove(f64),
+    /// The price fell below the threshold
+    CrossedBelow(f64),
+}
+
+/// Watches the price quotes per pair and raises [`Alert`]s on large moves or threshold crossings
+///
+/// After a move alert the window of the pair starts over, so a move is only reported once.
+pub struct PriceAlerts {
+    change: Option<(f64, Interval)>,
+    thresholds: Vec<f64>,
+    pairs: HashMap<Address, (Option<Window>, f64)>,
+}
+
+impl PriceAlerts {
+    /// Create a new [`PriceAlerts`] without any alert conditions
+    pub fn new() -> Self {
+        Self {
+            change: None,
+            thresholds: Vec::new(),
+            pairs: HashMap::new(),
+        }
+    }
+
+    /// Alert if the price moves by more than `change`, e.g. `0.05` for 5%, within `window`
+    pub fn with_move(mut self, change: f64, window: Interval) -> Self {
+        self.change = Some((change, window));
+        self
+    }
+
+    /// Alert if the price crosses `threshold` in either direction
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.thresholds.push(threshold);
+        self
+    }
+
+    /// Watch the price quotes of `stream`
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<Alert>>
+    where
+        S: Stream<Item = Result<Price>>,
+    {
+        super::process(stream, self)
+    }
+}
+
+impl Default for PriceAlerts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Process for PriceAlerts {
+    type Input = Price;
+    type Output = Alert;
+
+    fn push(&mut self, price: Price, out: &mut VecDeque<Alert>) {
+        let mut alert = |kind| {
+            out.push_back(Alert {
+                price: price.clone(),
+                kind,
+            })
+        };
+
+        match self.pairs.get_mut(&price.pair) {
+            Some((_, previous)) => {
+                for &threshold in &self.thresholds {
+                    if *previous <= threshold && price.price > threshold {
+                        alert(AlertKind::CrossedAbove(threshold));
+                    } else if *previous >= threshold && price.price < threshold {
+                        alert(AlertKind::CrossedBelow(threshold));
+                    }
+                }
+                *previous = price.price;
+            }
+            None => {
+                let window = self.change.map(|(_, interval)| Window::new(interval));
+                self.pairs.insert(price.pair, (window, price.price));
+            }
+        }
+
+        let (window, _) = self.pairs.get_mut(&price.pair).expect("inserted above");
+        if let (Some((change, interval)), Some(window)) = (self.change, window) {
+            window.push(price.clone());
+            let reference = window
+                .items()
+                .front()
+                .map_or(price.price, |first| first.price);
+            let moved = price.price / reference - 1.0;
+            if moved.abs() > change {
+                alert(AlertKind::Move {
+                    reference,
+                    change: moved,
+                });
+                *window = Window::new(interval);
+                window.push(price.clone());
+            }
+        }
+    }
+}
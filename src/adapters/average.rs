@@ -0,0 +1,97 @@
+use std::collections::{HashMap, VecDeque};
+
+use ethers::types::Address;
+use futures::Stream;
+
+use super::{
+    window::{self, Window},
+    Process,
+};
+use crate::{
+    types::{Interval, Price},
+    Result,
+};
+
+/// Get the volume weighted average price of `prices`
+///
+/// The volume of a price quote is its `volume0`. Returns `None` if there is no volume at all.
+pub fn vwap<'a>(prices: impl IntoIterator<Item = &'a Price>) -> Option<f64> {
+    let (weighted, volume) = prices
+        .into_iter()
+        .fold((0.0, 0.0), |(weighted, volume), price| {
+            let quote_volume = price.volume0.abs();
+            (weighted + price.price * quote_volume, volume + quote_volume)
+        });
+    (volume > 0.0).then(|| weighted / volume)
+}
+
+/// Get the time weighted average price of `prices` until the unix timestamp `end`
+///
+/// `prices` must be ordered by time. Every price is weighted by the time until the next price
+/// quote (or `end`). Returns `None` if `prices` is empty.
+pub fn twap<'a>(prices: impl IntoIterator<Item = &'a Price>, end: i64) -> Option<f64> {
+    let points = prices
+        .into_iter()
+        .map(|price| (price.timestamp.max(0) as u64, price.price));
+    window::time_weighted(points, end.max(0) as u64)
+}
+
+/// The moving averages of a pair after a price quote
+#[derive(Clone, Debug)]
+pub struct Averages {
+    pub pair: Address,
+    pub block_number: u64,
+    pub timestamp: i64,
+    pub vwap: f64,
+    pub twap: f64,
+}
+
+/// Computes the volume and time weighted moving average price per pair
+///
+/// For every price quote the averages over the trailing `window` of that pair are yielded. With
+/// [`Interval::Blocks`] the time weighted average is weighted by blocks instead of seconds.
+pub struct MovingAverages {
+    window: Interval,
+    windows: HashMap<Address, Window>,
+}
+
+impl MovingAverages {
+    /// Create a new [`MovingAverages`] adapter averaging over the trailing `window`
+    pub fn new(window: Interval) -> Self {
+        Self {
+            window,
+            windows: HashMap::new(),
+        }
+    }
+
+    /// Compute the moving averages of the price quotes of `stream`
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<Averages>>
+    where
+        S: Stream<Item = Result<Price>>,
+    {
+        super::process(stream, self)
+    }
+}
+
+impl Process for MovingAverages {
+    type Input = Price;
+    type Output = Averages;
+
+    fn push(&mut self, price: Price, out: &mut VecDeque<Averages>) {
+        let (pair, block_number, timestamp, last) =
+            (price.pair, price.block_number, price.timestamp, price.price);
+        let window = self
+            .windows
+            .entry(pair)
+            .or_insert_with(|| Window::new(self.window));
+        window.push(price);
+
+        out.push_back(Averages {
+            pair,
+            block_number,
+            timestamp,
+            vwap: vwap(window.items()).unwrap_or(last),
+            twap: window.twap().unwrap_or(last),
+        });
+    }
+}
@@ -0,0 +1,94 @@
+use std::sync::{Arc, Mutex};
+
+use futures::{Stream, StreamExt};
+use tokio::sync::broadcast;
+
+use crate::{Error, Result};
+
+type Sender<T> = Arc<Mutex<Option<broadcast::Sender<Result<T, Arc<Error>>>>>>;
+
+/// Drives a single stream and broadcasts its items to many subscribers
+///
+/// Every subscriber has a buffer of `capacity` items. A subscriber that falls further behind
+/// skips the oldest items and receives an [`Error::Lagged`] with the number of missed items.
+/// Errors of the consumed stream are forwarded to all subscribers as [`Error::Shared`].
+///
+/// The stream is driven by a spawned task, until it ends or the [`Broadcast`] and all
+/// subscribers were dropped. Items are only received by subscribers, that subscribed before.
+pub struct Broadcast<T> {
+    sender: Sender<T>,
+}
+
+impl<T> Broadcast<T>
+where
+    T: Clone + Send + 'static,
+{
+    /// Start driving `stream`, buffering up to `capacity` items per subscriber
+    pub fn new<S>(stream: S, capacity: usize) -> Self
+    where
+        S: Stream<Item = Result<T>> + Send + 'static,
+    {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        let sender = Arc::new(Mutex::new(Some(sender)));
+
+        let shared = Arc::clone(&sender);
+        tokio::spawn(async move {
+            futures::pin_mut!(stream);
+            while let Some(item) = stream.next().await {
+                let item = item.map_err(Arc::new);
+                let unreceived = match &*shared.lock().expect("the lock is never poisoned") {
+                    Some(sender) => sender.send(item).is_err(),
+                    None => true,
+                };
+                if unreceived && Arc::strong_count(&shared) == 1 {
+                    break;
+                }
+            }
+            shared.lock().expect("the lock is never poisoned").take();
+        });
+
+        Self { sender }
+    }
+
+    /// Subscribe to the items of the broadcast stream from now on
+    ///
+    /// The subscription ends with the consumed stream.
+    pub fn subscribe(&self) -> impl Stream<Item = Result<T>> + Send {
+        let sender = self.sender.lock().expect("the lock is never poisoned");
+        let receiver = sender.as_ref().map(broadcast::Sender::subscribe);
+        futures::stream::unfold(receiver, |receiver| async move {
+            let mut receiver = receiver?;
+            let item = match receiver.recv().await {
+                Ok(item) => item.map_err(Error::Shared),
+                Err(broadcast::error::RecvError::Lagged(missed)) => Err(Error::Lagged(missed)),
+                Err(broadcast::error::RecvError::Closed) => return None,
+            };
+            Some((item, Some(receiver)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn forwards_errors_with_their_variant() {
+        let (trigger, triggered) = tokio::sync::oneshot::channel::<()>();
+        let stream = futures::stream::once(async move {
+            triggered.await.ok();
+            Err::<u64, _>(Error::ConnectionClosed)
+        });
+        let broadcast = Broadcast::new(stream, 4);
+        let first = broadcast.subscribe();
+        let second = broadcast.subscribe();
+        futures::pin_mut!(first, second);
+        trigger.send(()).unwrap();
+
+        for err in [first.next().await, second.next().await] {
+            let err = err.unwrap().unwrap_err();
+            assert!(err.is_retryable());
+            assert!(matches!(err.inner(), Error::ConnectionClosed));
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use ethers::types::Address;
+use futures::Stream;
+
+use super::Process;
+use crate::{
+    types::{Interval, Price},
+    Result,
+};
+
+/// An OHLCV candle of a pair
+///
+/// `start` is the first block number or unix timestamp of the candle, depending on the
+/// [`Interval`] it was aggregated with.
+#[derive(Clone, Debug)]
+pub struct Candle {
+    pub pair: Address,
+    pub start: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume0: f64,
+    pub volume1: f64,
+    pub trades: u64,
+}
+
+/// Aggregates price quotes into OHLCV candles per pair
+///
+/// Candles are yielded once they are complete, which is when a price quote of a later candle
+/// arrives. To handle slightly out of order price quotes, the most recent candles can be kept open
+/// with [`CandleAggregator::with_lateness`]. Price quotes for candles, that were already yielded,
+/// are dropped.
+pub struct CandleAggregator {
+    interval: Interval,
+    lateness: u64,
+    latest: Option<u64>,
+    open: BTreeMap<(u64, Address), OpenCandle>,
+}
+
+struct OpenCandle {
+    candle: Candle,
+    first: (u64, i64),
+    last: (u64, i64),
+}
+
+impl CandleAggregator {
+    /// Create a new [`CandleAggregator`] yielding one candle per pair and `interval`
+    pub fn new(interval: Interval) -> Self {
+        Self {
+            interval,
+            lateness: 0,
+            latest: None,
+            open: BTreeMap::new(),
+        }
+    }
+
+    /// Keep the `candles` candles before the most recent one open for out of order price quotes
+    pub fn with_lateness(mut self, candles: u64) -> Self {
+        self.lateness = candles;
+        self
+    }
+
+    /// Aggregate the price quotes of `stream` into candles
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<Candle>>
+    where
+        S: Stream<Item = Result<Price>>,
+    {
+        super::process(stream, self)
+    }
+
+    fn bucket(&self, price: &Price) -> (u64, u64) {
+        let (value, size) = match self.interval {
+            Interval::Blocks(size) => (price.block_number, size),
+            Interval::Seconds(size) => (price.timestamp.max(0) as u64, size),
+        };
+        let size = size.max(1);
+        (value / size, size)
+    }
+}
+
+impl Process for CandleAggregator {
+    type Input = Price;
+    type Output = Candle;
+
+    fn push(&mut self, price: Price, out: &mut VecDeque<Candle>) {
+        let (bucket, size) = self.bucket(&price);
+        let latest = self.latest.map_or(bucket, |latest| latest.max(bucket));
+        self.latest = Some(latest);
+
+        let oldest_open = latest.saturating_sub(self.lateness);
+        if bucket < oldest_open {
+            return;
+        }
+
+        let position = (price.block_number, price.transaction_index);
+        self.open
+            .entry((bucket, price.pair))
+            .and_modify(|open| {
+                let candle = &mut open.candle;
+                if position < open.first {
+                    open.first = position;
+                    candle.open = price.price;
+                }
+                if position >= open.last {
+                    open.last = position;
+                    candle.close = price.price;
+                }
+                candle.high = candle.high.max(price.price);
+                candle.low = candle.low.min(price.price);
+                candle.volume0 += price.volume0;
+                candle.volume1 += price.volume1;
+                candle.trades += 1;
+            })
+            .or_insert_with(|| OpenCandle {
+                candle: Candle {
+                    pair: price.pair,
+                    start: bucket * size,
+                    open: price.price,
+                    high: price.price,
+                    low: price.price,
+                    close: price.price,
+                    volume0: price.volume0,
+                    volume1: price.volume1,
+                    trades: 1,
+                },
+                first: position,
+                last: position,
+            });
+
+        let still_open = self.open.split_off(&(oldest_open, Address::zero()));
+        let complete = std::mem::replace(&mut self.open, still_open);
+        out.extend(complete.into_values().map(|open| open.candle));
+    }
+
+    fn finish(&mut self, out: &mut VecDeque<Candle>) {
+        let complete = std::mem::take(&mut self.open);
+        out.extend(complete.into_values().map(|open| open.candle));
+    }
+}
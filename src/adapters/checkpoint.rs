@@ -0,0 +1,158 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use futures::{Stream, TryStreamExt};
+
+use super::Process;
+use crate::{
+    store::CheckpointStore,
+    types::{Cursor, Positioned},
+    Result,
+};
+
+/// An item of a stream checkpointed by [`Checkpoints`]
+#[derive(Clone, Debug)]
+pub enum Checkpointed<T> {
+    /// An item of the checkpointed stream
+    Item(T),
+    /// A cursor, that all previous items are covered by
+    ///
+    /// Persist it once the previous items were processed and resume from it after a restart,
+    /// e.g. with [`WsClient::get_prices_after`](crate::WsClient::get_prices_after).
+    Cursor(Cursor),
+}
+
+/// Periodically yields resumable [`Cursor`]s alongside the items of a block ordered stream
+///
+/// A cursor is yielded once at least `every` items were yielded since the previous cursor and
+/// the transaction of the last item is complete, i.e. an item of a later transaction arrived. A
+/// final cursor is yielded after the consumed stream ended. Items only known to be in a block,
+/// like reserves without a transaction, advance the cursor to the end of their block once an item
+/// of a later block arrived. Items without a known block are yielded, but never advance the cursor.
+pub struct Checkpoints<T> {
+    every: u64,
+    since: u64,
+    last: Option<Cursor>,
+    store: Option<(Arc<dyn CheckpointStore>, String)>,
+    _item: std::marker::PhantomData<fn(T)>,
+}
+
+impl<T> Checkpoints<T>
+where
+    T: Positioned,
+{
+    /// Create a new [`Checkpoints`] yielding a cursor every `every` items
+    pub fn new(every: u64) -> Self {
+        Self {
+            every: every.max(1),
+            since: 0,
+            last: None,
+            store: None,
+            _item: std::marker::PhantomData,
+        }
+    }
+
+    /// Persist each cursor to `store` under `key`, once it is yielded
+    ///
+    /// A cursor is yielded after all previous items, so a consumer processing the items one by
+    /// one has processed them all by the time the cursor is persisted. Failing to persist a
+    /// cursor yields an error.
+    pub fn with_store(mut self, store: Arc<dyn CheckpointStore>, key: impl Into<String>) -> Self {
+        self.store = Some((store, key.into()));
+        self
+    }
+
+    /// Checkpoint the items of `stream`
+    pub fn apply<S>(mut self, stream: S) -> impl Stream<Item = Result<Checkpointed<T>>>
+    where
+        S: Stream<Item = Result<T>>,
+    {
+        let store = self.store.take();
+        super::process(stream, self).and_then(move |item| {
+            let persisted = match (&item, &store) {
+                (Checkpointed::Cursor(cursor), Some((store, key))) => store.save(key, *cursor),
+                _ => Ok(()),
+            };
+            futures::future::ready(persisted.map(|()| item))
+        })
+    }
+}
+
+impl<T> Process for Checkpoints<T>
+where
+    T: Positioned,
+{
+    type Input = T;
+    type Output = Checkpointed<T>;
+
+    fn push(&mut self, item: T, out: &mut VecDeque<Checkpointed<T>>) {
+        let cursor = match item.position() {
+            Some(position) => Cursor {
+                block: position.block_number,
+                tx_index: position.transaction_index,
+            },
+            None => match item.block_number() {
+                Some(block) => Cursor::block_end(block),
+                None => {
+                    self.since += 1;
+                    return out.push_back(Checkpointed::Item(item));
+                }
+            },
+        };
+        if let Some(last) = self.last {
+            if self.since >= self.every && last < cursor {
+                out.push_back(Checkpointed::Cursor(last));
+                self.since = 0;
+            }
+        }
+
+        self.last = Some(self.last.map_or(cursor, |last| last.max(cursor)));
+        self.since += 1;
+        out.push_back(Checkpointed::Item(item));
+    }
+
+    fn finish(&mut self, out: &mut VecDeque<Checkpointed<T>>) {
+        if let (Some(last), 1..) = (self.last, self.since) {
+            out.push_back(Checkpointed::Cursor(last));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{StreamExt, TryStreamExt};
+
+    use super::*;
+    use crate::types::{fixtures::gateway_reserves, Reserves};
+
+    fn reserves(block: u64) -> Reserves {
+        Reserves {
+            block_number: Some(block),
+            ..gateway_reserves(1, 2)
+        }
+    }
+
+    #[tokio::test]
+    async fn advances_by_block_without_transactions() {
+        let rows = vec![reserves(10), reserves(10), reserves(11)];
+        let items: Vec<_> = Checkpoints::new(1)
+            .apply(futures::stream::iter(rows).map(Ok))
+            .try_collect()
+            .await
+            .unwrap();
+
+        let cursors: Vec<_> = items
+            .iter()
+            .filter_map(|item| match item {
+                Checkpointed::Cursor(cursor) => Some(*cursor),
+                Checkpointed::Item(_) => None,
+            })
+            .collect();
+        assert_eq!(items.len(), 5);
+        assert!(matches!(items[2], Checkpointed::Cursor(_)));
+        assert_eq!(cursors, vec![Cursor::block_end(10), Cursor::block_end(11)]);
+
+        assert!(!cursors[0].precedes(&reserves(10)));
+        assert!(cursors[0].precedes(&reserves(11)));
+        assert!(cursors[0].precedes(&gateway_reserves(1, 2)));
+    }
+}
@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use tokio::time::Instant;
+
+use crate::Result;
+
+/// Batch the items of `stream` into chunks of up to `max_items` items
+///
+/// A chunk is yielded once it is full or `max_duration` passed since its first item, so slow
+/// streams don't delay items indefinitely. This is useful for bulk inserts into databases.
+pub fn chunked<S, T>(
+    stream: S,
+    max_items: usize,
+    max_duration: Duration,
+) -> impl Stream<Item = Result<Vec<T>>>
+where
+    S: Stream<Item = Result<T>>,
+{
+    let max_items = max_items.max(1);
+    let state = (Box::pin(stream), Vec::new(), None, false);
+    futures::stream::unfold(
+        state,
+        move |(mut stream, mut chunk, mut deadline, done)| async move {
+            if done {
+                return None;
+            }
+            loop {
+                let timeout = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now));
+                tokio::select! {
+                    item = stream.next() => match item {
+                        Some(Ok(item)) => {
+                            deadline.get_or_insert_with(|| Instant::now() + max_duration);
+                            chunk.push(item);
+                            if chunk.len() >= max_items {
+                                let full = std::mem::take(&mut chunk);
+                                return Some((Ok(full), (stream, chunk, None, false)));
+                            }
+                        }
+                        Some(Err(err)) => return Some((Err(err), (stream, chunk, deadline, false))),
+                        None if chunk.is_empty() => return None,
+                        None => return Some((Ok(chunk), (stream, Vec::new(), None, true))),
+                    },
+                    _ = timeout, if deadline.is_some() => {
+                        let due = std::mem::take(&mut chunk);
+                        return Some((Ok(due), (stream, chunk, None, false)));
+                    }
+                }
+            }
+        },
+    )
+}
@@ -0,0 +1,89 @@
+use std::collections::VecDeque;
+
+use futures::{Stream, StreamExt, TryStreamExt};
+
+use super::{EnrichedSwap, Process};
+use crate::Result;
+
+/// A composite price of a token pair across multiple pools
+///
+/// `liquidity` is the summed token1 reserve of the pools, in token1.
+#[derive(Clone, Debug)]
+pub struct CompositePrice {
+    pub block_number: u64,
+    pub timestamp: i64,
+    pub price: f64,
+    pub liquidity: f64,
+    pub pools: usize,
+}
+
+/// Combines the swaps of multiple pools of the same token pair into a liquidity weighted price
+///
+/// The streams are usually created with [`enrich`](super::enrich), one per pool, keeping only the
+/// joined swaps with [`Enriched::into_swap`](super::Enriched::into_swap). All pools have to quote
+/// the same token in the same token, i.e. have the same token order. On every swap the post-trade
+/// mid prices of all pools are weighted by their token1 reserves.
+pub struct LiquidityWeightedPrice {
+    latest: Vec<Option<(f64, f64)>>,
+}
+
+impl LiquidityWeightedPrice {
+    /// Create a new [`LiquidityWeightedPrice`]
+    pub fn new() -> Self {
+        Self { latest: Vec::new() }
+    }
+
+    /// Combine the swaps of `streams`, one stream per pool
+    pub fn apply<S>(
+        mut self,
+        streams: impl IntoIterator<Item = S>,
+    ) -> impl Stream<Item = Result<CompositePrice>>
+    where
+        S: Stream<Item = Result<EnrichedSwap>> + Send + 'static,
+    {
+        let streams: Vec<_> = streams
+            .into_iter()
+            .enumerate()
+            .map(|(pool, stream)| stream.map_ok(move |swap| (pool, swap)).boxed())
+            .collect();
+        self.latest = vec![None; streams.len()];
+        super::process(futures::stream::select_all(streams), self)
+    }
+}
+
+impl Default for LiquidityWeightedPrice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Process for LiquidityWeightedPrice {
+    type Input = (usize, EnrichedSwap);
+    type Output = CompositePrice;
+
+    fn push(&mut self, (pool, swap): (usize, EnrichedSwap), out: &mut VecDeque<CompositePrice>) {
+        let decimals1 = swap.price.decimals1 as i32;
+        let liquidity = swap.reserves.reserve1 as f64 / 10f64.powi(decimals1);
+        let mid_price = swap.mid_price();
+        if mid_price.is_finite() {
+            self.latest[pool] = Some((mid_price, liquidity));
+        }
+
+        let pools = self.latest.iter().flatten();
+        let liquidity: f64 = pools.clone().map(|(_, liquidity)| liquidity).sum();
+        if liquidity <= 0.0 {
+            return;
+        }
+        let weighted: f64 = pools
+            .clone()
+            .map(|(price, liquidity)| price * liquidity)
+            .sum();
+        out.push_back(CompositePrice {
+            block_number: swap.price.block_number,
+            timestamp: swap.price.timestamp,
+            price: weighted / liquidity,
+            liquidity,
+            pools: pools.count(),
+        });
+    }
+}
@@ -0,0 +1,80 @@
+use std::{collections::HashMap, time::Duration};
+
+use ethers::types::Address;
+use futures::{Stream, StreamExt};
+use tokio::time::Instant;
+
+use crate::{types::Price, Result};
+
+/// Conflates price quotes to the most recent price quote per pair, at a maximum rate per pair
+///
+/// The first price quote of a pair is yielded immediately. Afterwards at most one price quote per
+/// pair and interval is yielded, which is the most recent one received in the meantime. Once the
+/// consumed stream ended, the remaining price quotes are yielded without delay.
+pub struct Conflator {
+    interval: Duration,
+}
+
+#[derive(Default)]
+struct Conflation {
+    pairs: HashMap<Address, (Option<Instant>, Option<Price>)>,
+}
+
+impl Conflation {
+    fn insert(&mut self, price: Price) {
+        let pair = price.pair;
+        self.pairs.entry(pair).or_default().1 = Some(price);
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        let pending = self.pairs.values().filter(|(_, price)| price.is_some());
+        pending
+            .map(|(next, _)| next.unwrap_or_else(Instant::now))
+            .min()
+    }
+
+    fn pop(&mut self, interval: Duration, flush: bool) -> Option<Price> {
+        let now = Instant::now();
+        let (next, price) = self.pairs.values_mut().find(|(next, price)| {
+            price.is_some() && (flush || !matches!(next, Some(next) if *next > now))
+        })?;
+        *next = Some(now + interval);
+        price.take()
+    }
+}
+
+impl Conflator {
+    /// Create a new [`Conflator`] yielding at most one price quote per pair and `interval`
+    pub fn new(interval: Duration) -> Self {
+        Self { interval }
+    }
+
+    /// Conflate the price quotes of `stream`
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<Price>>
+    where
+        S: Stream<Item = Result<Price>>,
+    {
+        let state = (Box::pin(stream), Conflation::default(), false);
+        futures::stream::unfold(state, move |(mut stream, mut state, mut done)| async move {
+            loop {
+                if let Some(price) = state.pop(self.interval, done) {
+                    return Some((Ok(price), (stream, state, done)));
+                }
+                if done {
+                    return None;
+                }
+
+                let deadline = state.deadline();
+                tokio::select! {
+                    item = stream.next() => match item {
+                        Some(Ok(price)) => state.insert(price),
+                        Some(Err(err)) => return Some((Err(err), (stream, state, done))),
+                        None => done = true,
+                    },
+                    _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)),
+                        if deadline.is_some() => {}
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::{HashMap, VecDeque};
+
+use ethers::types::Address;
+use futures::Stream;
+
+use super::Process;
+use crate::{
+    types::{Interval, Price},
+    Result,
+};
+
+/// The running volume totals of a pair
+///
+/// The volumes are the summed absolute volumes of all trades.
+#[derive(Clone, Debug, Default)]
+pub struct VolumeTotal {
+    pub pair: Address,
+    pub volume0: f64,
+    pub volume1: f64,
+    pub trades: u64,
+}
+
+/// The running volume totals of all pairs at the end of an interval
+///
+/// `block_number` and `timestamp` are the ones of the last price quote within the interval.
+#[derive(Clone, Debug)]
+pub struct VolumeSnapshot {
+    pub block_number: u64,
+    pub timestamp: i64,
+    pub totals: Vec<VolumeTotal>,
+}
+
+/// Maintains running volume totals per pair and periodically yields a [`VolumeSnapshot`]
+///
+/// A snapshot is yielded once a price quote of a later interval arrives and after the consumed
+/// stream ended.
+pub struct CumulativeVolume {
+    interval: Interval,
+    totals: HashMap<Address, VolumeTotal>,
+    last: Option<(u64, u64, i64)>,
+}
+
+impl CumulativeVolume {
+    /// Create a new [`CumulativeVolume`] yielding a snapshot per `interval`
+    pub fn new(interval: Interval) -> Self {
+        Self {
+            interval,
+            totals: HashMap::new(),
+            last: None,
+        }
+    }
+
+    /// Accumulate the volumes of the price quotes of `stream`
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<VolumeSnapshot>>
+    where
+        S: Stream<Item = Result<Price>>,
+    {
+        super::process(stream, self)
+    }
+
+    fn bucket(&self, price: &Price) -> u64 {
+        match self.interval {
+            Interval::Blocks(size) => price.block_number / size.max(1),
+            Interval::Seconds(size) => price.timestamp.max(0) as u64 / size.max(1),
+        }
+    }
+
+    fn snapshot(&self, out: &mut VecDeque<VolumeSnapshot>) {
+        if let Some((_, block_number, timestamp)) = self.last {
+            out.push_back(VolumeSnapshot {
+                block_number,
+                timestamp,
+                totals: self.totals.values().cloned().collect(),
+            });
+        }
+    }
+}
+
+impl Process for CumulativeVolume {
+    type Input = Price;
+    type Output = VolumeSnapshot;
+
+    fn push(&mut self, price: Price, out: &mut VecDeque<VolumeSnapshot>) {
+        let bucket = self.bucket(&price);
+        if matches!(self.last, Some((last, _, _)) if last < bucket) {
+            self.snapshot(out);
+        }
+        self.last = Some((bucket, price.block_number, price.timestamp));
+
+        let total = self
+            .totals
+            .entry(price.pair)
+            .or_insert_with(|| VolumeTotal {
+                pair: price.pair,
+                ..VolumeTotal::default()
+            });
+        total.volume0 += price.volume0.abs();
+        total.volume1 += price.volume1.abs();
+        total.trades += 1;
+    }
+
+    fn finish(&mut self, out: &mut VecDeque<VolumeSnapshot>) {
+        self.snapshot(out);
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet, VecDeque},
+    hash::{Hash, Hasher},
+};
+
+use ethers::types::{Address, H256};
+use futures::Stream;
+use serde::Serialize;
+
+use super::Process;
+use crate::{
+    types::{PairCreated, Price, Reserves, Type},
+    Result,
+};
+
+/// Rows with a key identifying them uniquely, used by the [`Deduplicator`]
+pub trait Unique {
+    type Key: Clone + Eq + Hash;
+
+    fn key(&self) -> Self::Key;
+}
+
+/// Tells the rows of one transaction apart
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RowId {
+    /// The index of the log of the row
+    LogIndex(u64),
+    /// A hash of all fields of the row, used if the gateway didn't provide the log index
+    Fingerprint(u64),
+}
+
+impl RowId {
+    fn of<T: Serialize>(row: &T, log_index: Option<u64>) -> Self {
+        match log_index {
+            Some(log_index) => Self::LogIndex(log_index),
+            None => {
+                let mut hasher = DefaultHasher::new();
+                serde_json::to_vec(row)
+                    .expect("rows always serialize")
+                    .hash(&mut hasher);
+                Self::Fingerprint(hasher.finish())
+            }
+        }
+    }
+}
+
+impl Unique for PairCreated {
+    type Key = (H256, Address);
+
+    fn key(&self) -> Self::Key {
+        (self.transaction_hash, self.pair)
+    }
+}
+
+impl Unique for Price {
+    type Key = (H256, Address, RowId);
+
+    fn key(&self) -> Self::Key {
+        (
+            self.transaction_hash,
+            self.pair,
+            RowId::of(self, self.log_index),
+        )
+    }
+}
+
+impl Unique for Reserves {
+    type Key = (Option<H256>, Option<Address>, Type, RowId);
+
+    fn key(&self) -> Self::Key {
+        (
+            self.transaction_hash,
+            self.pair,
+            self.event,
+            RowId::of(self, self.log_index),
+        )
+    }
+}
+
+/// Drops rows, that were already yielded, e.g. the overlapping rows after resuming a stream
+///
+/// To bound the memory usage only the keys of the most recent `capacity` rows are remembered.
+///
+/// Rows are identified by their transaction, pair and log index. The gateway doesn't send the log
+/// index yet, so rows without one are identified by all of their fields instead. Thus distinct
+/// rows, that are equal in every field, e.g. two identical swaps in one transaction, are
+/// collapsed into one.
+pub struct Deduplicator<T>
+where
+    T: Unique,
+{
+    capacity: usize,
+    seen: HashSet<T::Key>,
+    order: VecDeque<T::Key>,
+}
+
+impl<T> Deduplicator<T>
+where
+    T: Unique,
+{
+    /// Create a new [`Deduplicator`] remembering the most recent `capacity` rows
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Deduplicate the rows of `stream`
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<T>>
+    where
+        S: Stream<Item = Result<T>>,
+    {
+        super::process(stream, self)
+    }
+}
+
+impl<T> Process for Deduplicator<T>
+where
+    T: Unique,
+{
+    type Input = T;
+    type Output = T;
+
+    fn push(&mut self, item: T, out: &mut VecDeque<T>) {
+        let key = item.key();
+        if !self.seen.insert(key.clone()) {
+            return;
+        }
+
+        self.order.push_back(key);
+        while self.order.len() > self.capacity {
+            if let Some(key) = self.order.pop_front() {
+                self.seen.remove(&key);
+            }
+        }
+        out.push_back(item);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{StreamExt, TryStreamExt};
+
+    use super::*;
+    use crate::types::fixtures::{gateway_price, gateway_reserves, price};
+
+    async fn dedup<T: Unique>(rows: Vec<T>) -> Vec<T> {
+        let rows = futures::stream::iter(rows.into_iter().map(Ok));
+        Deduplicator::new(16)
+            .apply(rows)
+            .try_collect()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn drops_repeated_rows() {
+        let prices = vec![
+            price(1, 10, 0, Some(0)),
+            price(1, 10, 0, Some(1)),
+            price(1, 10, 0, Some(0)),
+        ];
+        let logs: Vec<_> = dedup(prices).await.iter().map(|p| p.log_index).collect();
+        assert_eq!(logs, [Some(0), Some(1)]);
+    }
+
+    #[tokio::test]
+    async fn drops_repeated_gateway_rows() {
+        let mut second = gateway_price(1, 10, 0);
+        second.volume0 = 3.0;
+        let prices = vec![
+            gateway_price(1, 10, 0),
+            second.clone(),
+            gateway_price(1, 10, 0),
+            second,
+        ];
+        let volumes: Vec<_> = dedup(prices).await.iter().map(|p| p.volume0).collect();
+        assert_eq!(volumes, [1.0, 3.0]);
+
+        let reserves = vec![
+            gateway_reserves(1, 2),
+            gateway_reserves(2, 3),
+            gateway_reserves(1, 2),
+        ];
+        assert_eq!(dedup(reserves).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn passes_errors() {
+        let rows = futures::stream::iter(vec![
+            Ok(price(1, 10, 0, Some(0))),
+            Err(crate::Error::Custom("broken".to_owned())),
+            Ok(price(1, 10, 0, Some(0))),
+        ]);
+        let rows: Vec<_> = Deduplicator::new(16).apply(rows).collect().await;
+        assert_eq!(rows.len(), 2);
+        assert!(rows[1].is_err());
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::{HashMap, VecDeque};
+
+use ethers::types::{Address, H256};
+use futures::{future::Either, Stream, TryStreamExt};
+
+use super::Process;
+use crate::{
+    types::{Price, Reserves, Type},
+    Result,
+};
+
+/// A swap joined with the reserves of its pair right after the swap
+#[derive(Clone, Debug)]
+pub struct EnrichedSwap {
+    pub price: Price,
+    pub reserves: Reserves,
+}
+
+impl EnrichedSwap {
+    /// The price of token0 in token1 implied by the post-trade reserves, adjusted by decimals
+    pub fn mid_price(&self) -> f64 {
+        let reserve0 = self.reserves.reserve0 as f64 / 10f64.powi(self.price.decimals0 as i32);
+        let reserve1 = self.reserves.reserve1 as f64 / 10f64.powi(self.price.decimals1 as i32);
+        reserve1 / reserve0
+    }
+
+    /// The relative difference between the execution price and the post-trade mid price
+    pub fn slippage(&self) -> f64 {
+        self.price.price / self.mid_price() - 1.0
+    }
+
+    /// The share of the post-trade token0 reserve, that was traded by this swap
+    pub fn pool_impact(&self) -> f64 {
+        let reserve0 = self.reserves.reserve0 as f64 / 10f64.powi(self.price.decimals0 as i32);
+        self.price.volume0.abs() / reserve0
+    }
+}
+
+/// An item of an [`enrich`]ed stream
+#[derive(Clone, Debug)]
+pub enum Enriched {
+    /// A swap joined with the reserves right after it
+    Swap(EnrichedSwap),
+    /// A price quote without matching reserves
+    Price(Price),
+    /// Reserves without a known pair, transaction or block, which can't be matched to a swap
+    Reserves(Reserves),
+}
+
+impl Enriched {
+    /// The joined swap, `None` for items that couldn't be joined
+    pub fn into_swap(self) -> Option<EnrichedSwap> {
+        match self {
+            Self::Swap(swap) => Some(swap),
+            _ => None,
+        }
+    }
+}
+
+/// Joins the price quotes of `prices` with the post-trade reserves of `reserves`
+///
+/// Price quotes and reserves are matched by pair and transaction, using the `Sync` event of the
+/// swap. Both streams have to cover the same pairs and block range. Price quotes without matching
+/// reserves are passed through unenriched, once the reserves stream moved past their block.
+///
+/// Reserves without a known pair, transaction and block, as the gateway currently sends them,
+/// can't be matched and are passed through as well. Once such reserves arrive, all price quotes
+/// are passed through right away, since the reserves stream can't tell how far it progressed.
+pub fn enrich<P, R>(prices: P, reserves: R) -> impl Stream<Item = Result<Enriched>>
+where
+    P: Stream<Item = Result<Price>>,
+    R: Stream<Item = Result<Reserves>>,
+{
+    let merged =
+        futures::stream::select(prices.map_ok(Either::Left), reserves.map_ok(Either::Right));
+    super::process(merged, Joiner::default())
+}
+
+#[derive(Default)]
+struct Joiner {
+    prices: HashMap<(Address, H256), VecDeque<Price>>,
+    reserves: HashMap<(Address, H256), VecDeque<(u64, Reserves)>>,
+    prices_block: u64,
+    reserves_block: u64,
+    unpositioned: bool,
+}
+
+impl Joiner {
+    fn evict(&mut self, out: &mut VecDeque<Enriched>) {
+        let (prices_block, reserves_block) = (self.prices_block, self.reserves_block);
+        self.prices.retain(|_, pending| {
+            while matches!(pending.front(), Some(price) if price.block_number < reserves_block) {
+                out.extend(pending.pop_front().map(Enriched::Price));
+            }
+            !pending.is_empty()
+        });
+        self.reserves.retain(|_, pending| {
+            pending.retain(|(block, _)| *block >= prices_block);
+            !pending.is_empty()
+        });
+    }
+
+    fn flush_prices(&mut self, out: &mut VecDeque<Enriched>) {
+        let mut prices: Vec<_> = self
+            .prices
+            .drain()
+            .flat_map(|(_, pending)| pending)
+            .collect();
+        prices.sort_by_key(|price| (price.block_number, price.transaction_index));
+        out.extend(prices.into_iter().map(Enriched::Price));
+    }
+}
+
+impl Process for Joiner {
+    type Input = Either<Price, Reserves>;
+    type Output = Enriched;
+
+    fn push(&mut self, item: Self::Input, out: &mut VecDeque<Enriched>) {
+        match item {
+            Either::Left(price) => {
+                let key = (price.pair, price.transaction_hash);
+                let new_block = price.block_number > self.prices_block;
+                self.prices_block = self.prices_block.max(price.block_number);
+
+                match self.reserves.get_mut(&key).and_then(VecDeque::pop_front) {
+                    Some((_, reserves)) => {
+                        out.push_back(Enriched::Swap(EnrichedSwap { price, reserves }))
+                    }
+                    None if self.unpositioned => out.push_back(Enriched::Price(price)),
+                    None => self.prices.entry(key).or_default().push_back(price),
+                }
+                if new_block {
+                    self.evict(out);
+                }
+            }
+            Either::Right(reserves) => {
+                let (key, block) = match (
+                    reserves.pair,
+                    reserves.transaction_hash,
+                    reserves.block_number,
+                ) {
+                    (Some(pair), Some(transaction_hash), Some(block)) => {
+                        ((pair, transaction_hash), block)
+                    }
+                    _ => {
+                        self.unpositioned = true;
+                        self.flush_prices(out);
+                        return out.push_back(Enriched::Reserves(reserves));
+                    }
+                };
+                let new_block = block > self.reserves_block;
+                self.reserves_block = self.reserves_block.max(block);
+                if reserves.event != Type::Sync {
+                    return;
+                }
+
+                match self.prices.get_mut(&key).and_then(VecDeque::pop_front) {
+                    Some(price) => out.push_back(Enriched::Swap(EnrichedSwap { price, reserves })),
+                    None => self
+                        .reserves
+                        .entry(key)
+                        .or_default()
+                        .push_back((block, reserves)),
+                }
+                if new_block {
+                    self.evict(out);
+                }
+            }
+        }
+    }
+
+    fn finish(&mut self, out: &mut VecDeque<Enriched>) {
+        self.flush_prices(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::fixtures::{gateway_price, gateway_reserves, price, reserves};
+
+    async fn joined(prices: Vec<Price>, reserves: Vec<Reserves>) -> Vec<Enriched> {
+        let prices = futures::stream::iter(prices.into_iter().map(Ok));
+        let reserves = futures::stream::iter(reserves.into_iter().map(Ok));
+        enrich(prices, reserves).try_collect().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn joins_swaps_with_their_sync() {
+        let items = joined(
+            vec![price(1, 10, 0, Some(0)), price(1, 11, 0, Some(0))],
+            vec![reserves(1, 10, 0, Some(1)), reserves(1, 12, 0, Some(1))],
+        )
+        .await;
+        assert!(
+            matches!(&items[..], [Enriched::Swap(swap), Enriched::Price(price)]
+            if swap.price.block_number == 10 && price.block_number == 11)
+        );
+    }
+
+    #[tokio::test]
+    async fn passes_through_gateway_rows() {
+        let items = joined(
+            vec![gateway_price(1, 10, 0), gateway_price(1, 10, 1)],
+            vec![gateway_reserves(1, 2)],
+        )
+        .await;
+        let prices = items
+            .iter()
+            .filter(|item| matches!(item, Enriched::Price(_)))
+            .count();
+        let reserves = items
+            .iter()
+            .filter(|item| matches!(item, Enriched::Reserves(_)))
+            .count();
+        assert_eq!((prices, reserves), (2, 1));
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use futures::{future::Either, Stream, TryStreamExt};
+
+use super::Process;
+use crate::{
+    types::{Position, Positioned, Reorg},
+    Result,
+};
+
+/// An item of a stream buffered by a [`FinalityBuffer`]
+#[derive(Clone, Debug)]
+pub enum Finalized<T> {
+    /// An item, that is at least the configured number of blocks deep
+    Item(T),
+    /// A reorg deeper than the configured number of blocks
+    ///
+    /// All previously yielded items of the blocks in [`Reorg::affected_blocks`] are invalid.
+    Retracted(Reorg),
+}
+
+/// Withholds the items of a head following stream until they are `confirmations` blocks deep
+///
+/// The depth is measured against the latest block seen in either the items or the reorgs. Items
+/// orphaned by a reorg while withheld are dropped, items are yielded in the order of their
+/// [`Position`]. Items without a known block are withheld as if they were in the latest block.
+pub struct FinalityBuffer<T> {
+    confirmations: u64,
+    head: u64,
+    yielded_block: Option<u64>,
+    buffer: BTreeMap<(Position, u64), T>,
+    sequence: u64,
+}
+
+impl<T> FinalityBuffer<T>
+where
+    T: Positioned,
+{
+    /// Create a new [`FinalityBuffer`] yielding items once they are `confirmations` blocks deep
+    pub fn new(confirmations: u64) -> Self {
+        Self {
+            confirmations,
+            head: 0,
+            yielded_block: None,
+            buffer: BTreeMap::new(),
+            sequence: 0,
+        }
+    }
+
+    /// Buffer the items of `stream`, using `reorgs` to drop and retract orphaned items
+    ///
+    /// `reorgs` is usually the stream returned by
+    /// [`WsClient::subscribe_reorgs`](crate::WsClient::subscribe_reorgs).
+    pub fn apply<S, R>(self, stream: S, reorgs: R) -> impl Stream<Item = Result<Finalized<T>>>
+    where
+        S: Stream<Item = Result<T>>,
+        R: Stream<Item = Result<Reorg>>,
+    {
+        let merged =
+            futures::stream::select(stream.map_ok(Either::Left), reorgs.map_ok(Either::Right));
+        super::process(merged, self)
+    }
+
+    fn yield_confirmed(&mut self, out: &mut VecDeque<Finalized<T>>) {
+        let block = match self.head.checked_sub(self.confirmations) {
+            Some(block) => block,
+            None => return,
+        };
+        let withheld = self
+            .buffer
+            .split_off(&(Position::block_start(block + 1), 0));
+        let confirmed = std::mem::replace(&mut self.buffer, withheld);
+        if let Some(((position, _), _)) = confirmed.iter().next_back() {
+            self.yielded_block = Some(position.block_number);
+        }
+        out.extend(confirmed.into_values().map(Finalized::Item));
+    }
+}
+
+impl<T> Process for FinalityBuffer<T>
+where
+    T: Positioned,
+{
+    type Input = Either<T, Reorg>;
+    type Output = Finalized<T>;
+
+    fn push(&mut self, item: Self::Input, out: &mut VecDeque<Finalized<T>>) {
+        match item {
+            Either::Left(item) => {
+                let block = item.block_number().unwrap_or(self.head);
+                self.head = self.head.max(block);
+                let position = item
+                    .position()
+                    .unwrap_or_else(|| Position::block_end(block));
+                self.buffer.insert((position, self.sequence), item);
+                self.sequence += 1;
+            }
+            Either::Right(reorg) => {
+                let orphaned = *reorg.affected_blocks().start();
+                self.buffer
+                    .retain(|(position, _), _| position.block_number < orphaned);
+                self.head = reorg.new_head_number;
+                if matches!(self.yielded_block, Some(block) if block >= orphaned) {
+                    self.yielded_block = orphaned.checked_sub(1);
+                    out.push_back(Finalized::Retracted(reorg));
+                }
+            }
+        }
+        self.yield_confirmed(out);
+    }
+}
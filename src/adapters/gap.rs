@@ -0,0 +1,106 @@
+use std::collections::VecDeque;
+
+use futures::Stream;
+
+use super::Process;
+use crate::{types::Positioned, Result};
+
+/// A range of blocks, that were skipped by a stream
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Gap {
+    /// The first skipped block
+    pub from: u64,
+    /// The last skipped block, inclusive
+    pub to: u64,
+}
+
+/// An item of a stream checked by a [`GapDetector`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Checked<T> {
+    /// An item of the checked stream
+    Item(T),
+    /// Emitted before the first item after the gap
+    Gap(Gap),
+}
+
+/// Tracks the block continuity of a stream and reports skipped blocks as [`Gap`]s
+///
+/// Streams of events naturally skip blocks without any events, so by default only gaps of more
+/// than one block are reported. Items of earlier blocks than the latest one and items without a
+/// known block are passed through.
+pub struct GapDetector<T> {
+    max_gap: u64,
+    latest_block: Option<u64>,
+    _item: std::marker::PhantomData<fn(T)>,
+}
+
+impl<T> GapDetector<T>
+where
+    T: Positioned,
+{
+    /// Create a new [`GapDetector`] reporting gaps of more than one block
+    pub fn new() -> Self {
+        Self {
+            max_gap: 1,
+            latest_block: None,
+            _item: std::marker::PhantomData,
+        }
+    }
+
+    /// Set the number of blocks, that may be skipped without reporting a gap
+    ///
+    /// A `max_gap` of `0` reports every skipped block, e.g. for per block sampled reserves.
+    pub fn with_max_gap(mut self, max_gap: u64) -> Self {
+        self.max_gap = max_gap;
+        self
+    }
+
+    /// Start checking from `block`, so a gap right after it is reported as well
+    pub fn with_start_block(mut self, block: u64) -> Self {
+        self.latest_block = Some(block);
+        self
+    }
+
+    /// Check the block continuity of `stream`
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<Checked<T>>>
+    where
+        S: Stream<Item = Result<T>>,
+    {
+        super::process(stream, self)
+    }
+}
+
+impl<T> Default for GapDetector<T>
+where
+    T: Positioned,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Process for GapDetector<T>
+where
+    T: Positioned,
+{
+    type Input = T;
+    type Output = Checked<T>;
+
+    fn push(&mut self, item: T, out: &mut VecDeque<Checked<T>>) {
+        let block = match item.block_number() {
+            Some(block) => block,
+            None => return out.push_back(Checked::Item(item)),
+        };
+        if let Some(latest) = self.latest_block {
+            if block > latest + 1 + self.max_gap {
+                out.push_back(Checked::Gap(Gap {
+                    from: latest + 1,
+                    to: block - 1,
+                }));
+            }
+        }
+
+        self.latest_block = Some(self.latest_block.map_or(block, |latest| latest.max(block)));
+        out.push_back(Checked::Item(item));
+    }
+}
@@ -0,0 +1,303 @@
+//! Technical indicators over price quotes and candles
+//!
+//! Every indicator is computed per pair and yields the consumed item together with the indicator
+//! value. [`Ema`] and [`Macd`] are seeded with the first value of a pair and yield a value for
+//! every item, so their first values are still converging. [`Rsi`] skips the first `period`
+//! items of a pair, which it needs for its first value.
+
+use std::collections::{HashMap, VecDeque};
+
+use ethers::types::Address;
+use futures::Stream;
+
+use super::{Candle, Process};
+use crate::{types::Price, Result};
+
+/// Items an indicator can be computed over
+pub trait Quote {
+    fn pair(&self) -> Address;
+
+    /// The price the indicator is computed over
+    fn value(&self) -> f64;
+}
+
+impl Quote for Price {
+    fn pair(&self) -> Address {
+        self.pair
+    }
+
+    fn value(&self) -> f64 {
+        self.price
+    }
+}
+
+impl Quote for Candle {
+    fn pair(&self) -> Address {
+        self.pair
+    }
+
+    /// The close price of the candle
+    fn value(&self) -> f64 {
+        self.close
+    }
+}
+
+/// An item with the value of an indicator after it
+#[derive(Clone, Debug)]
+pub struct Indicated<T, V> {
+    pub item: T,
+    pub value: V,
+}
+
+/// The exponential moving average over `period` items, seeded with the first value
+#[derive(Clone, Copy, Debug)]
+struct Smoothing {
+    alpha: f64,
+    value: Option<f64>,
+}
+
+impl Smoothing {
+    fn new(period: u32) -> Self {
+        Self {
+            alpha: 2.0 / (period.max(1) as f64 + 1.0),
+            value: None,
+        }
+    }
+
+    fn push(&mut self, value: f64) -> f64 {
+        let smoothed = match self.value {
+            Some(previous) => previous + self.alpha * (value - previous),
+            None => value,
+        };
+        self.value = Some(smoothed);
+        smoothed
+    }
+}
+
+/// Computes the exponential moving average per pair
+pub struct Ema<T> {
+    period: u32,
+    pairs: HashMap<Address, Smoothing>,
+    _item: std::marker::PhantomData<fn(T)>,
+}
+
+impl<T> Ema<T>
+where
+    T: Quote,
+{
+    /// Create a new [`Ema`] over `period` items
+    pub fn new(period: u32) -> Self {
+        Self {
+            period,
+            pairs: HashMap::new(),
+            _item: std::marker::PhantomData,
+        }
+    }
+
+    /// Compute the exponential moving average of the items of `stream`
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<Indicated<T, f64>>>
+    where
+        S: Stream<Item = Result<T>>,
+    {
+        super::process(stream, self)
+    }
+}
+
+impl<T> Process for Ema<T>
+where
+    T: Quote,
+{
+    type Input = T;
+    type Output = Indicated<T, f64>;
+
+    fn push(&mut self, item: T, out: &mut VecDeque<Self::Output>) {
+        let period = self.period;
+        let ema = self
+            .pairs
+            .entry(item.pair())
+            .or_insert_with(|| Smoothing::new(period));
+        let value = ema.push(item.value());
+        out.push_back(Indicated { item, value });
+    }
+}
+
+#[derive(Default)]
+struct RsiState {
+    previous: Option<f64>,
+    changes: u32,
+    gain: f64,
+    loss: f64,
+}
+
+/// Computes the relative strength index per pair, using Wilder's smoothing
+///
+/// The first `period` price changes of a pair are its warm up period.
+pub struct Rsi<T> {
+    period: u32,
+    pairs: HashMap<Address, RsiState>,
+    _item: std::marker::PhantomData<fn(T)>,
+}
+
+impl<T> Rsi<T>
+where
+    T: Quote,
+{
+    /// Create a new [`Rsi`] over `period` items, commonly 14
+    pub fn new(period: u32) -> Self {
+        Self {
+            period: period.max(1),
+            pairs: HashMap::new(),
+            _item: std::marker::PhantomData,
+        }
+    }
+
+    /// Compute the relative strength index, between 0 and 100, of the items of `stream`
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<Indicated<T, f64>>>
+    where
+        S: Stream<Item = Result<T>>,
+    {
+        super::process(stream, self)
+    }
+}
+
+impl<T> Process for Rsi<T>
+where
+    T: Quote,
+{
+    type Input = T;
+    type Output = Indicated<T, f64>;
+
+    fn push(&mut self, item: T, out: &mut VecDeque<Self::Output>) {
+        let period = self.period as f64;
+        let state = self.pairs.entry(item.pair()).or_default();
+        let value = item.value();
+        let previous = match state.previous.replace(value) {
+            Some(previous) => previous,
+            None => return,
+        };
+
+        let change = value - previous;
+        let (gain, loss) = (change.max(0.0), (-change).max(0.0));
+        state.changes += 1;
+        if state.changes <= self.period {
+            state.gain += gain / period;
+            state.loss += loss / period;
+        } else {
+            state.gain = (state.gain * (period - 1.0) + gain) / period;
+            state.loss = (state.loss * (period - 1.0) + loss) / period;
+        }
+        if state.changes < self.period {
+            return;
+        }
+
+        let value = if state.loss == 0.0 {
+            100.0
+        } else {
+            100.0 - 100.0 / (1.0 + state.gain / state.loss)
+        };
+        out.push_back(Indicated { item, value });
+    }
+}
+
+/// The values of the moving average convergence divergence indicator
+#[derive(Clone, Copy, Debug)]
+pub struct MacdValue {
+    /// The difference between the fast and the slow exponential moving average
+    pub macd: f64,
+    /// The exponential moving average of `macd`
+    pub signal: f64,
+    /// The difference between `macd` and `signal`
+    pub histogram: f64,
+}
+
+/// Computes the moving average convergence divergence per pair
+pub struct Macd<T> {
+    periods: (u32, u32, u32),
+    pairs: HashMap<Address, (Smoothing, Smoothing, Smoothing)>,
+    _item: std::marker::PhantomData<fn(T)>,
+}
+
+impl<T> Macd<T>
+where
+    T: Quote,
+{
+    /// Create a new [`Macd`] with the `fast`, `slow` and `signal` periods, commonly 12, 26 and 9
+    pub fn new(fast: u32, slow: u32, signal: u32) -> Self {
+        Self {
+            periods: (fast, slow, signal),
+            pairs: HashMap::new(),
+            _item: std::marker::PhantomData,
+        }
+    }
+
+    /// Compute the moving average convergence divergence of the items of `stream`
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<Indicated<T, MacdValue>>>
+    where
+        S: Stream<Item = Result<T>>,
+    {
+        super::process(stream, self)
+    }
+}
+
+impl<T> Process for Macd<T>
+where
+    T: Quote,
+{
+    type Input = T;
+    type Output = Indicated<T, MacdValue>;
+
+    fn push(&mut self, item: T, out: &mut VecDeque<Self::Output>) {
+        let (fast, slow, signal) = self.periods;
+        let (fast, slow, signal) = self.pairs.entry(item.pair()).or_insert_with(|| {
+            (
+                Smoothing::new(fast),
+                Smoothing::new(slow),
+                Smoothing::new(signal),
+            )
+        });
+        let value = item.value();
+        let macd = fast.push(value) - slow.push(value);
+        let signal = signal.push(macd);
+        let value = MacdValue {
+            macd,
+            signal,
+            histogram: macd - signal,
+        };
+        out.push_back(Indicated { item, value });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{StreamExt, TryStreamExt};
+
+    use super::*;
+    use crate::types::fixtures::price;
+
+    fn prices() -> impl Stream<Item = Result<Price>> {
+        let prices = [1.0, 2.0, 1.5, 3.0].into_iter().enumerate();
+        futures::stream::iter(prices).map(|(block, value)| {
+            Ok(Price {
+                price: value,
+                ..price(1, block as u64, 0, None)
+            })
+        })
+    }
+
+    #[tokio::test]
+    async fn only_rsi_skips_a_warm_up_period() {
+        let ema: Vec<_> = Ema::new(3).apply(prices()).try_collect().await.unwrap();
+        let macd: Vec<_> = Macd::new(2, 3, 2)
+            .apply(prices())
+            .try_collect()
+            .await
+            .unwrap();
+        let rsi: Vec<_> = Rsi::new(2).apply(prices()).try_collect().await.unwrap();
+
+        assert_eq!(ema.len(), 4);
+        assert_eq!(ema[0].value, 1.0);
+        assert_eq!(macd.len(), 4);
+        assert_eq!(rsi.len(), 2);
+        assert_eq!(rsi[0].item.block_number, 2);
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use ethers::types::Address;
+use futures::{Stream, TryStreamExt};
+
+use crate::{
+    types::{PairCreated, Price},
+    Result,
+};
+
+/// The activity status of a pair
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PairStatus {
+    /// The pair was created, but not traded yet
+    Created,
+    /// The pair was traded within the configured inactivity period
+    Active,
+    /// The pair was not traded within the configured inactivity period
+    Inactive,
+}
+
+/// The lifecycle of a pair, as observed by a [`PairTracker`]
+///
+/// `created` is `None` if the pair was traded before its [`PairCreated`] event was observed.
+#[derive(Clone, Debug)]
+pub struct PairActivity {
+    pub pair: Address,
+    pub created: Option<PairCreated>,
+    /// The unix timestamp of the first observed trade
+    pub first_trade: Option<i64>,
+    /// The unix timestamp of the last observed trade
+    pub last_trade: Option<i64>,
+    pub trades: u64,
+    pub status: PairStatus,
+}
+
+#[derive(Default)]
+struct Registry {
+    pairs: HashMap<Address, PairActivity>,
+    /// The most recent timestamp of any observed row, used as the current time
+    now: i64,
+}
+
+/// Maintains a registry of pairs and their activity, fed by pair created and price streams
+///
+/// The streams are passed through unchanged while they are consumed. The status of a pair is
+/// relative to the most recent observed timestamp, so it is consistent for historical streams.
+#[derive(Clone)]
+pub struct PairTracker {
+    inactive_after: i64,
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl PairTracker {
+    /// Create a new [`PairTracker`] considering pairs inactive after `inactive_after` seconds
+    /// without a trade
+    pub fn new(inactive_after: i64) -> Self {
+        Self {
+            inactive_after,
+            registry: Arc::default(),
+        }
+    }
+
+    /// Track the pairs created in `stream`
+    pub fn track_pairs<S>(&self, stream: S) -> impl Stream<Item = Result<PairCreated>>
+    where
+        S: Stream<Item = Result<PairCreated>>,
+    {
+        let registry = Arc::clone(&self.registry);
+        stream.inspect_ok(move |created| {
+            let mut registry = registry.lock().expect("the lock is never poisoned");
+            registry.now = registry.now.max(created.timestamp);
+            let activity = registry
+                .pairs
+                .entry(created.pair)
+                .or_insert_with(|| PairActivity::new(created.pair));
+            activity.created = Some(created.clone());
+        })
+    }
+
+    /// Track the trades of `stream`
+    pub fn track_prices<S>(&self, stream: S) -> impl Stream<Item = Result<Price>>
+    where
+        S: Stream<Item = Result<Price>>,
+    {
+        let registry = Arc::clone(&self.registry);
+        stream.inspect_ok(move |price| {
+            let mut registry = registry.lock().expect("the lock is never poisoned");
+            registry.now = registry.now.max(price.timestamp);
+            let activity = registry
+                .pairs
+                .entry(price.pair)
+                .or_insert_with(|| PairActivity::new(price.pair));
+            activity.first_trade.get_or_insert(price.timestamp);
+            activity.last_trade = Some(
+                activity
+                    .last_trade
+                    .map_or(price.timestamp, |last| last.max(price.timestamp)),
+            );
+            activity.trades += 1;
+        })
+    }
+
+    /// Get the lifecycle of `pair`, if it was observed
+    pub fn get(&self, pair: Address) -> Option<PairActivity> {
+        let registry = self.registry.lock().expect("the lock is never poisoned");
+        let activity = registry.pairs.get(&pair)?;
+        Some(self.with_status(activity, registry.now))
+    }
+
+    /// Get the lifecycles of all observed pairs with the provided `status`
+    pub fn with(&self, status: PairStatus) -> Vec<PairActivity> {
+        self.pairs()
+            .into_iter()
+            .filter(|activity| activity.status == status)
+            .collect()
+    }
+
+    /// Get the lifecycles of all observed pairs
+    pub fn pairs(&self) -> Vec<PairActivity> {
+        let registry = self.registry.lock().expect("the lock is never poisoned");
+        registry
+            .pairs
+            .values()
+            .map(|activity| self.with_status(activity, registry.now))
+            .collect()
+    }
+
+    fn with_status(&self, activity: &PairActivity, now: i64) -> PairActivity {
+        let status = match activity.last_trade {
+            None => PairStatus::Created,
+            Some(last) if now - last > self.inactive_after => PairStatus::Inactive,
+            Some(_) => PairStatus::Active,
+        };
+        PairActivity {
+            status,
+            ..activity.clone()
+        }
+    }
+}
+
+impl PairActivity {
+    fn new(pair: Address) -> Self {
+        Self {
+            pair,
+            created: None,
+            first_trade: None,
+            last_trade: None,
+            trades: 0,
+            status: PairStatus::Created,
+        }
+    }
+}
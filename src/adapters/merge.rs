@@ -0,0 +1,148 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use futures::{Stream, StreamExt, TryStreamExt};
+
+use super::Process;
+use crate::{
+    types::{PairCreated, Position, Positioned, Price, Reserves},
+    Result,
+};
+
+/// A row of any of the typed streams, as yielded by a [`BlockMerge`]
+#[derive(Clone, Debug)]
+pub enum Event {
+    PairCreated(PairCreated),
+    Price(Price),
+    Reserves(Reserves),
+}
+
+impl Positioned for Event {
+    fn position(&self) -> Option<Position> {
+        match self {
+            Self::PairCreated(pair) => pair.position(),
+            Self::Price(price) => price.position(),
+            Self::Reserves(reserves) => reserves.position(),
+        }
+    }
+
+    fn block_number(&self) -> Option<u64> {
+        match self {
+            Self::PairCreated(pair) => pair.block_number(),
+            Self::Price(price) => price.block_number(),
+            Self::Reserves(reserves) => reserves.block_number(),
+        }
+    }
+}
+
+impl From<PairCreated> for Event {
+    fn from(pair: PairCreated) -> Self {
+        Self::PairCreated(pair)
+    }
+}
+
+impl From<Price> for Event {
+    fn from(price: Price) -> Self {
+        Self::Price(price)
+    }
+}
+
+impl From<Reserves> for Event {
+    fn from(reserves: Reserves) -> Self {
+        Self::Reserves(reserves)
+    }
+}
+
+/// Merges multiple block ordered streams into a single stream of [`Event`]s ordered by
+/// [`Position`]
+///
+/// An event is yielded once every other stream moved past its block or ended. To not stall on
+/// quiet streams, events are yielded regardless once any stream is more than `max_skew` blocks
+/// ahead of them. Events arriving after events of a later position were yielded, are yielded
+/// immediately and thus out of order. So are events without a known block, events only known to
+/// be in a block are ordered after the other events of it.
+pub struct BlockMerge {
+    max_skew: u64,
+    latest: Vec<Option<u64>>,
+    ended: Vec<bool>,
+    buffer: BTreeMap<(Position, u64), Event>,
+    sequence: u64,
+}
+
+impl BlockMerge {
+    /// Create a new [`BlockMerge`] tolerating a skew of `max_skew` blocks between the streams
+    pub fn new(max_skew: u64) -> Self {
+        Self {
+            max_skew,
+            latest: Vec::new(),
+            ended: Vec::new(),
+            buffer: BTreeMap::new(),
+            sequence: 0,
+        }
+    }
+
+    /// Merge `streams`, e.g. `prices.map_ok(Event::from).boxed()`
+    pub fn apply<S>(
+        mut self,
+        streams: impl IntoIterator<Item = S>,
+    ) -> impl Stream<Item = Result<Event>>
+    where
+        S: Stream<Item = Result<Event>> + Send + 'static,
+    {
+        let streams: Vec<_> = streams
+            .into_iter()
+            .enumerate()
+            .map(|(source, stream)| {
+                let items = stream.map_ok(move |event| (source, Some(event)));
+                let end = futures::stream::once(async move { Ok((source, None)) });
+                items.chain(end).boxed()
+            })
+            .collect();
+        self.latest = vec![None; streams.len()];
+        self.ended = vec![false; streams.len()];
+        super::process(futures::stream::select_all(streams), self)
+    }
+
+    fn watermark(&self) -> u64 {
+        let lagging = self
+            .latest
+            .iter()
+            .zip(&self.ended)
+            .filter(|(_, ended)| !**ended)
+            .map(|(latest, _)| latest.unwrap_or(0))
+            .min()
+            .unwrap_or(u64::MAX);
+        let head = self.latest.iter().flatten().max().copied().unwrap_or(0);
+        lagging.max(head.saturating_sub(self.max_skew))
+    }
+}
+
+impl Process for BlockMerge {
+    type Input = (usize, Option<Event>);
+    type Output = Event;
+
+    fn push(&mut self, (source, event): Self::Input, out: &mut VecDeque<Event>) {
+        match event {
+            Some(event) => match event.block_number() {
+                Some(block) => {
+                    let latest = &mut self.latest[source];
+                    *latest = Some(latest.map_or(block, |latest| latest.max(block)));
+                    let position = event
+                        .position()
+                        .unwrap_or_else(|| Position::block_end(block));
+                    self.buffer.insert((position, self.sequence), event);
+                    self.sequence += 1;
+                }
+                None => out.push_back(event),
+            },
+            None => self.ended[source] = true,
+        }
+
+        let first_kept = Position::block_start(self.watermark());
+        let kept = self.buffer.split_off(&(first_kept, 0));
+        out.extend(std::mem::replace(&mut self.buffer, kept).into_values());
+    }
+
+    fn finish(&mut self, out: &mut VecDeque<Event>) {
+        out.extend(std::mem::take(&mut self.buffer).into_values());
+    }
+}
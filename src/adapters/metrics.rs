@@ -0,0 +1,102 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::{Stream, TryStreamExt};
+
+use crate::{types::Positioned, Result};
+
+/// The throughput and lag of a stream since the previous report of a [`ThroughputMeter`]
+#[derive(Clone, Debug)]
+pub struct StreamMetrics {
+    pub items: u64,
+    /// The in-memory size of the items, as an estimate of the transferred bytes
+    pub bytes: u64,
+    pub elapsed: Duration,
+    pub items_per_second: f64,
+    pub bytes_per_second: f64,
+    /// The block of the most recent item
+    pub latest_block: Option<u64>,
+    /// The number of blocks the most recent item is behind the chain head
+    pub lag: Option<u64>,
+}
+
+struct State {
+    items: u64,
+    bytes: u64,
+    since: Instant,
+    latest_block: Option<u64>,
+}
+
+/// Measures the throughput of a stream and its lag behind the chain head
+///
+/// This tells apart a slow gateway, with a low throughput and a high lag, from a slow consumer,
+/// with a high lag although the throughput is high. The stream is measured as it is consumed,
+/// the metrics are taken with [`ThroughputMeter::report`].
+#[derive(Clone)]
+pub struct ThroughputMeter {
+    state: Arc<Mutex<State>>,
+}
+
+impl ThroughputMeter {
+    /// Create a new [`ThroughputMeter`]
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                items: 0,
+                bytes: 0,
+                since: Instant::now(),
+                latest_block: None,
+            })),
+        }
+    }
+
+    /// Measure the items of `stream`, which are passed through unchanged
+    pub fn apply<S, T>(&self, stream: S) -> impl Stream<Item = Result<T>>
+    where
+        S: Stream<Item = Result<T>>,
+        T: Positioned,
+    {
+        let state = Arc::clone(&self.state);
+        stream.inspect_ok(move |item| {
+            let mut state = state.lock().expect("the lock is never poisoned");
+            state.items += 1;
+            state.bytes += std::mem::size_of_val(item) as u64;
+            if let Some(block) = item.block_number() {
+                state.latest_block = Some(state.latest_block.map_or(block, |l| l.max(block)));
+            }
+        })
+    }
+
+    /// Take the metrics since the previous report and reset the counters
+    ///
+    /// `head` is the current chain height, e.g. from
+    /// [`WsClient::get_height`](crate::WsClient::get_height), used to compute the lag.
+    pub fn report(&self, head: Option<u64>) -> StreamMetrics {
+        let mut state = self.state.lock().expect("the lock is never poisoned");
+        let elapsed = state.since.elapsed();
+        let seconds = elapsed.as_secs_f64().max(f64::EPSILON);
+        let metrics = StreamMetrics {
+            items: state.items,
+            bytes: state.bytes,
+            elapsed,
+            items_per_second: state.items as f64 / seconds,
+            bytes_per_second: state.bytes as f64 / seconds,
+            latest_block: state.latest_block,
+            lag: head
+                .zip(state.latest_block)
+                .map(|(head, latest)| head.saturating_sub(latest)),
+        };
+        state.items = 0;
+        state.bytes = 0;
+        state.since = Instant::now();
+        metrics
+    }
+}
+
+impl Default for ThroughputMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
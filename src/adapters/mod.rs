@@ -0,0 +1,115 @@
+//! Stream adapters for post-processing the typed streams of the clients
+//!
+//! Every adapter is configured upfront and then consumes a stream with its `apply` method, e.g.
+//! a `Stream<Item = Result<Price>>` as returned by [`WsClient::get_prices`](crate::WsClient::get_prices).
+//! Errors of the consumed stream are passed through unchanged.
+
+use std::collections::VecDeque;
+
+use futures::{Stream, StreamExt};
+
+use crate::Result;
+
+pub use self::{
+    alert::{Alert, AlertKind, PriceAlerts},
+    average::{twap, vwap, Averages, MovingAverages},
+    broadcast::Broadcast,
+    candles::{Candle, CandleAggregator},
+    checkpoint::{Checkpointed, Checkpoints},
+    chunk::chunked,
+    composite::{CompositePrice, LiquidityWeightedPrice},
+    conflate::Conflator,
+    cumulative::{CumulativeVolume, VolumeSnapshot, VolumeTotal},
+    dedup::{Deduplicator, RowId, Unique},
+    enrich::{enrich, Enriched, EnrichedSwap},
+    finality::{FinalityBuffer, Finalized},
+    gap::{Checked, Gap, GapDetector},
+    lifecycle::{PairActivity, PairStatus, PairTracker},
+    merge::{BlockMerge, Event},
+    metrics::{StreamMetrics, ThroughputMeter},
+    order::{Ordered, OrderingBuffer},
+    outlier::{OutlierFilter, OutlierRule},
+    replay::{Clock, Replay, Speed, Timestamped},
+    resample::{resample, Representative, Resampler},
+    returns::{align, Return, ReturnKind, ReturnSeries},
+    spike::{VolumeSpike, VolumeSpikes},
+    spread::{Spread, SpreadMonitor},
+    stats::{RollingStats, RollingStatsAdapter},
+    tee::{Format, Tee},
+    tvl::TvlCalculator,
+    usd::{UsdPricer, UsdSwap},
+};
+
+mod alert;
+mod average;
+mod broadcast;
+mod candles;
+mod checkpoint;
+mod chunk;
+mod composite;
+mod conflate;
+mod cumulative;
+mod dedup;
+mod enrich;
+mod finality;
+mod gap;
+#[cfg(feature = "indicators")]
+pub mod indicators;
+mod lifecycle;
+mod merge;
+mod metrics;
+mod order;
+mod outlier;
+mod replay;
+mod resample;
+mod returns;
+mod spike;
+mod spread;
+mod stats;
+mod tee;
+mod tvl;
+mod usd;
+mod window;
+
+/// A stateful processor turning input items into zero or more output items
+pub(crate) trait Process {
+    type Input;
+    type Output;
+
+    /// Process the next input `item`, pushing all resulting items to `out`
+    fn push(&mut self, item: Self::Input, out: &mut VecDeque<Self::Output>);
+
+    /// Flush the remaining state after the input ended
+    fn finish(&mut self, _out: &mut VecDeque<Self::Output>) {}
+}
+
+/// Drive `stream` through the `processor`, yielding all items it produces
+pub(crate) fn process<S, P>(stream: S, processor: P) -> impl Stream<Item = Result<P::Output>>
+where
+    S: Stream<Item = Result<P::Input>>,
+    P: Process,
+{
+    let state = (Box::pin(stream), processor, VecDeque::new(), false);
+    futures::stream::unfold(
+        state,
+        |(mut stream, mut processor, mut out, mut done)| async move {
+            loop {
+                if let Some(item) = out.pop_front() {
+                    return Some((Ok(item), (stream, processor, out, done)));
+                }
+                if done {
+                    return None;
+                }
+
+                match stream.next().await {
+                    Some(Ok(item)) => processor.push(item, &mut out),
+                    Some(Err(err)) => return Some((Err(err), (stream, processor, out, done))),
+                    None => {
+                        processor.finish(&mut out);
+                        done = true;
+                    }
+                }
+            }
+        },
+    )
+}
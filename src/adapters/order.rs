@@ -0,0 +1,149 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use futures::Stream;
+
+use super::Process;
+use crate::{
+    types::{Position, Positioned},
+    Result,
+};
+
+/// An item of a stream re-ordered by an [`OrderingBuffer`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Ordered<T> {
+    /// An item in the order of its position
+    Item(T),
+    /// An item, that arrived after items of a later position were already yielded
+    ///
+    /// Late items are yielded as soon as they arrive, so they are out of order.
+    Late(T),
+}
+
+impl<T> Ordered<T> {
+    /// The item, regardless of whether it arrived late
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Item(item) | Self::Late(item) => item,
+        }
+    }
+}
+
+/// Re-orders items strictly by their [`Position`]
+///
+/// Live data of different subscriptions can arrive slightly out of order. This buffers the items
+/// of the most recent `blocks` blocks and yields an item once an item of a block more than
+/// `blocks` blocks later arrived. Items arriving after items of a later position were already
+/// yielded are yielded immediately as [`Ordered::Late`], so no item is lost. Items without a known
+/// position can't be ordered and are yielded immediately, items only known to be in a block are
+/// ordered after the other items of it.
+pub struct OrderingBuffer<T> {
+    blocks: u64,
+    latest_block: u64,
+    yielded: Option<Position>,
+    buffer: BTreeMap<(Position, u64), T>,
+    sequence: u64,
+}
+
+impl<T> OrderingBuffer<T>
+where
+    T: Positioned,
+{
+    /// Create a new [`OrderingBuffer`] buffering the items of the most recent `blocks` blocks
+    pub fn new(blocks: u64) -> Self {
+        Self {
+            blocks,
+            latest_block: 0,
+            yielded: None,
+            buffer: BTreeMap::new(),
+            sequence: 0,
+        }
+    }
+
+    /// Re-order the items of `stream`
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<Ordered<T>>>
+    where
+        S: Stream<Item = Result<T>>,
+    {
+        super::process(stream, self)
+    }
+
+    fn yield_until(&mut self, block: u64, out: &mut VecDeque<Ordered<T>>) {
+        let kept = self.buffer.split_off(&(Position::block_start(block), 0));
+        let ready = std::mem::replace(&mut self.buffer, kept);
+        if let Some(((position, _), _)) = ready.iter().next_back() {
+            self.yielded = Some(*position);
+        }
+        out.extend(ready.into_values().map(Ordered::Item));
+    }
+}
+
+impl<T> Process for OrderingBuffer<T>
+where
+    T: Positioned,
+{
+    type Input = T;
+    type Output = Ordered<T>;
+
+    fn push(&mut self, item: T, out: &mut VecDeque<Ordered<T>>) {
+        let position = match item.position() {
+            Some(position) => position,
+            None => match item.block_number() {
+                Some(block) => Position::block_end(block),
+                None => return out.push_back(Ordered::Item(item)),
+            },
+        };
+        if matches!(self.yielded, Some(yielded) if position < yielded) {
+            return out.push_back(Ordered::Late(item));
+        }
+
+        self.latest_block = self.latest_block.max(position.block_number);
+        self.buffer.insert((position, self.sequence), item);
+        self.sequence += 1;
+
+        let block = self.latest_block.saturating_sub(self.blocks);
+        self.yield_until(block, out);
+    }
+
+    fn finish(&mut self, out: &mut VecDeque<Ordered<T>>) {
+        out.extend(
+            std::mem::take(&mut self.buffer)
+                .into_values()
+                .map(Ordered::Item),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{StreamExt, TryStreamExt};
+
+    use super::*;
+    use crate::types::fixtures::price;
+
+    #[tokio::test]
+    async fn yields_late_items_instead_of_dropping_them() {
+        let prices = vec![
+            price(1, 10, 0, Some(0)),
+            price(1, 12, 0, Some(0)),
+            price(1, 11, 0, Some(0)),
+            price(1, 9, 0, Some(0)),
+        ];
+        let ordered: Vec<_> = OrderingBuffer::new(1)
+            .apply(futures::stream::iter(prices).map(Ok))
+            .try_collect()
+            .await
+            .unwrap();
+
+        let blocks: Vec<_> = ordered
+            .iter()
+            .map(|item| match item {
+                Ordered::Item(price) => (price.block_number, false),
+                Ordered::Late(price) => (price.block_number, true),
+            })
+            .collect();
+        assert_eq!(
+            blocks,
+            vec![(10, false), (9, true), (11, false), (12, false)]
+        );
+    }
+}
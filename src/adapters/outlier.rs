@@ -0,0 +1,96 @@
+use std::collections::{HashMap, VecDeque};
+
+use ethers::types::Address;
+use futures::Stream;
+
+use super::Process;
+use crate::{types::Price, Result};
+
+/// The rule deciding whether a price is an outlier, relative to the rolling median
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutlierRule {
+    /// Prices deviating by more than the fraction from the median, e.g. `0.5` for 50%
+    MaxDeviation(f64),
+    /// Prices deviating by more than the multiple of the median absolute deviation
+    MedianAbsoluteDeviation(f64),
+}
+
+/// Drops outlier price quotes, as produced by single trades in thin pools
+///
+/// Every price quote is compared to the median of the trailing price quotes of its pair. All
+/// price quotes, including dropped ones, enter the window, so a lasting price shift passes the
+/// filter once it makes up half the window.
+pub struct OutlierFilter {
+    window: usize,
+    min_samples: usize,
+    rule: OutlierRule,
+    pairs: HashMap<Address, VecDeque<f64>>,
+}
+
+impl OutlierFilter {
+    /// Create a new [`OutlierFilter`] applying `rule` over the trailing `window` price quotes
+    pub fn new(window: usize, rule: OutlierRule) -> Self {
+        Self {
+            window: window.max(1),
+            min_samples: 3,
+            rule,
+            pairs: HashMap::new(),
+        }
+    }
+
+    /// Set the number of trailing price quotes required before filtering, defaults to 3
+    pub fn with_min_samples(mut self, min_samples: usize) -> Self {
+        self.min_samples = min_samples;
+        self
+    }
+
+    /// Filter the price quotes of `stream`
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<Price>>
+    where
+        S: Stream<Item = Result<Price>>,
+    {
+        super::process(stream, self)
+    }
+
+    fn is_outlier(&self, window: &VecDeque<f64>, price: f64) -> bool {
+        if window.len() < self.min_samples.max(1) {
+            return false;
+        }
+        let center = median(window.iter().copied().collect());
+        let deviation = (price - center).abs();
+        match self.rule {
+            OutlierRule::MaxDeviation(max) => deviation > max * center.abs(),
+            OutlierRule::MedianAbsoluteDeviation(max) => {
+                let mad = median(window.iter().map(|value| (value - center).abs()).collect());
+                deviation > max * mad && deviation > 0.0
+            }
+        }
+    }
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    match values.len() % 2 {
+        0 => (values[mid - 1] + values[mid]) / 2.0,
+        _ => values[mid],
+    }
+}
+
+impl Process for OutlierFilter {
+    type Input = Price;
+    type Output = Price;
+
+    fn push(&mut self, price: Price, out: &mut VecDeque<Price>) {
+        let mut window = self.pairs.remove(&price.pair).unwrap_or_default();
+        if !self.is_outlier(&window, price.price) {
+            out.push_back(price.clone());
+        }
+
+        window.push_back(price.price);
+        if window.len() > self.window {
+            window.pop_front();
+        }
+        self.pairs.insert(price.pair, window);
+    }
+}
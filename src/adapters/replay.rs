@@ -0,0 +1,211 @@
+use std::{
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures::{Stream, StreamExt};
+use tokio::time::Instant;
+
+use super::Event;
+use crate::{
+    types::{PairCreated, Price, Reserves},
+    Error, Result,
+};
+
+/// Rows with a unix timestamp, that can be replayed by a [`Replay`]
+pub trait Timestamped {
+    /// The timestamp of the row, `None` if unknown
+    fn timestamp(&self) -> Option<i64>;
+}
+
+impl Timestamped for PairCreated {
+    fn timestamp(&self) -> Option<i64> {
+        Some(self.timestamp)
+    }
+}
+
+impl Timestamped for Price {
+    fn timestamp(&self) -> Option<i64> {
+        Some(self.timestamp)
+    }
+}
+
+impl Timestamped for Reserves {
+    fn timestamp(&self) -> Option<i64> {
+        self.timestamp
+    }
+}
+
+impl Timestamped for Event {
+    fn timestamp(&self) -> Option<i64> {
+        match self {
+            Self::PairCreated(pair) => Some(pair.timestamp),
+            Self::Price(price) => Some(price.timestamp),
+            Self::Reserves(reserves) => reserves.timestamp,
+        }
+    }
+}
+
+/// The speed at which a [`Replay`] yields the historical rows
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Speed {
+    /// Yield the rows as fast as they are consumed
+    Unbounded,
+    /// Yield the rows at their original pace, sped up by the factor, e.g. `60.0` for a minute per
+    /// second
+    ///
+    /// Prefer [`Speed::scaled`], which checks the factor.
+    Scaled(f64),
+}
+
+impl Speed {
+    /// Yield the rows at their original pace, sped up by `factor`
+    ///
+    /// Fails with [`Error::InvalidConfig`] unless the factor is positive and finite.
+    pub fn scaled(factor: f64) -> Result<Self> {
+        Self::Scaled(factor).validate()
+    }
+
+    /// Check the factor of [`Speed::Scaled`], see [`Speed::scaled`]
+    pub(crate) fn validate(self) -> Result<Self> {
+        match self {
+            Self::Scaled(factor) if !factor.is_finite() || factor <= 0.0 => {
+                Err(Error::InvalidConfig(format!(
+                    "the speed has to be scaled by a positive factor, got {factor}"
+                )))
+            }
+            speed => Ok(speed),
+        }
+    }
+
+    /// Wait until `elapsed` seconds of the original pace passed since `start`
+    ///
+    /// Negative delays, e.g. of rows out of order, don't wait at all. Delays beyond the range of
+    /// the clock wait forever.
+    pub(crate) async fn wait(self, start: Instant, elapsed: f64) {
+        let delay = match self {
+            Self::Unbounded => return,
+            Self::Scaled(factor) => elapsed / factor,
+        };
+        let delay = Duration::try_from_secs_f64(delay).unwrap_or(if delay > 0.0 {
+            Duration::MAX
+        } else {
+            Duration::ZERO
+        });
+        match start.checked_add(delay) {
+            Some(deadline) => tokio::time::sleep_until(deadline).await,
+            None => futures::future::pending().await,
+        }
+    }
+}
+
+/// A simulated clock, that is advanced by a [`Replay`]
+///
+/// The clock is at the unix timestamp of the most recently yielded row, which strategies should
+/// use in place of the wall clock.
+#[derive(Clone, Debug, Default)]
+pub struct Clock(Arc<AtomicI64>);
+
+impl Clock {
+    /// The current unix timestamp of the replay
+    pub fn now(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Replays historical rows, e.g. from [`HttpClient::get_prices_in_range`], for backtesting
+///
+/// The replayed stream has the same interface as the live streams, so strategies can be
+/// developed against a replay and run live unchanged.
+///
+/// [`HttpClient::get_prices_in_range`]: crate::HttpClient::get_prices_in_range
+pub struct Replay {
+    speed: Speed,
+    clock: Clock,
+}
+
+impl Replay {
+    /// Create a new [`Replay`] at `speed`
+    pub fn new(speed: Speed) -> Self {
+        Self {
+            speed,
+            clock: Clock::default(),
+        }
+    }
+
+    /// The simulated clock of this replay
+    pub fn clock(&self) -> Clock {
+        self.clock.clone()
+    }
+
+    /// Replay the rows of `stream`, which have to be ordered by their timestamp
+    ///
+    /// Rows without a timestamp are yielded right away, without advancing the clock.
+    pub fn apply<S, T>(self, stream: S) -> impl Stream<Item = Result<T>>
+    where
+        S: Stream<Item = Result<T>>,
+        T: Timestamped,
+    {
+        let state = (Box::pin(stream), self, None);
+        futures::stream::unfold(state, |(mut stream, replay, mut start)| async move {
+            let item = stream.next().await?;
+            if let Some(timestamp) = item.as_ref().ok().and_then(Timestamped::timestamp) {
+                let (start_timestamp, start_instant) =
+                    *start.get_or_insert((timestamp, Instant::now()));
+                let elapsed = timestamp.saturating_sub(start_timestamp) as f64;
+                replay.speed.wait(start_instant, elapsed).await;
+                replay.clock.0.store(timestamp, Ordering::Relaxed);
+            }
+            Some((item, (stream, replay, start)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+
+    use super::*;
+    use crate::types::fixtures::price;
+
+    #[test]
+    fn rejects_invalid_factors() {
+        for factor in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                Speed::scaled(factor),
+                Err(Error::InvalidConfig(_))
+            ));
+        }
+        assert_eq!(Speed::scaled(60.0).unwrap(), Speed::Scaled(60.0));
+    }
+
+    #[tokio::test]
+    async fn replays_rows_out_of_order_right_away() {
+        let rows = [price(1, 10, 0, None), price(1, 5, 0, None)];
+        let replay = Replay::new(Speed::scaled(1.0).unwrap());
+        let clock = replay.clock();
+        let replayed: Vec<_> = replay
+            .apply(futures::stream::iter(rows).map(Ok))
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(replayed.len(), 2);
+        assert_eq!(clock.now(), 60);
+    }
+
+    #[tokio::test]
+    async fn saturates_the_delay_of_unchecked_factors() {
+        let rows = [price(1, 10, 0, None), price(1, 10, 1, None)];
+        let replayed: Vec<_> = Replay::new(Speed::Scaled(f64::NAN))
+            .apply(futures::stream::iter(rows).map(Ok))
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(replayed.len(), 2);
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::{HashMap, VecDeque};
+
+use ethers::types::Address;
+use futures::Stream;
+
+use super::Process;
+use crate::{
+    types::{Interval, Price},
+    Result,
+};
+
+/// Resample the price quotes of `stream` to at most one price quote per pair and `interval`
+///
+/// This is a shorthand for [`Resampler::new`] with the default [`Representative::Last`].
+pub fn resample<S>(stream: S, interval: Interval) -> impl Stream<Item = Result<Price>>
+where
+    S: Stream<Item = Result<Price>>,
+{
+    Resampler::new(interval).apply(stream)
+}
+
+/// How the price quotes within one interval are represented
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Representative {
+    /// The last price quote
+    #[default]
+    Last,
+    /// The last price quote, with the mean price of the interval
+    Mean,
+    /// The last price quote, with the median price of the interval
+    Median,
+}
+
+/// Resamples price quotes to at most one representative price quote per pair and interval
+///
+/// An interval is yielded once a price quote of a later interval of the same pair arrives.
+pub struct Resampler {
+    interval: Interval,
+    representative: Representative,
+    buckets: HashMap<Address, (u64, Vec<Price>)>,
+}
+
+impl Resampler {
+    /// Create a new [`Resampler`] yielding the last price quote per pair and `interval`
+    pub fn new(interval: Interval) -> Self {
+        Self {
+            interval,
+            representative: Representative::default(),
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Set how the price quotes within one interval are represented
+    pub fn with_representative(mut self, representative: Representative) -> Self {
+        self.representative = representative;
+        self
+    }
+
+    /// Resample the price quotes of `stream`
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<Price>>
+    where
+        S: Stream<Item = Result<Price>>,
+    {
+        super::process(stream, self)
+    }
+
+    fn bucket(&self, price: &Price) -> u64 {
+        match self.interval {
+            Interval::Blocks(size) => price.block_number / size.max(1),
+            Interval::Seconds(size) => price.timestamp.max(0) as u64 / size.max(1),
+        }
+    }
+
+    fn represent(&self, mut prices: Vec<Price>) -> Option<Price> {
+        let mut values: Vec<_> = prices.iter().map(|price| price.price).collect();
+        let mut last = prices.pop()?;
+        match self.representative {
+            Representative::Last => {}
+            Representative::Mean => {
+                last.price = values.iter().sum::<f64>() / values.len() as f64;
+            }
+            Representative::Median => {
+                values.sort_by(f64::total_cmp);
+                let mid = values.len() / 2;
+                last.price = match values.len() % 2 {
+                    0 => (values[mid - 1] + values[mid]) / 2.0,
+                    _ => values[mid],
+                };
+            }
+        }
+        Some(last)
+    }
+}
+
+impl Process for Resampler {
+    type Input = Price;
+    type Output = Price;
+
+    fn push(&mut self, price: Price, out: &mut VecDeque<Price>) {
+        let bucket = self.bucket(&price);
+        let (current, prices) = self
+            .buckets
+            .entry(price.pair)
+            .or_insert_with(|| (bucket, Vec::new()));
+
+        if *current != bucket {
+            let complete = std::mem::take(prices);
+            *current = bucket;
+            prices.push(price);
+            out.extend(self.represent(complete));
+        } else {
+            prices.push(price);
+        }
+    }
+
+    fn finish(&mut self, out: &mut VecDeque<Price>) {
+        let mut remaining: Vec<_> = self.buckets.drain().map(|(_, bucket)| bucket).collect();
+        remaining.sort_by_key(|(bucket, _)| *bucket);
+        for (_, prices) in remaining {
+            out.extend(self.represent(prices));
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use ethers::types::Address;
+use futures::Stream;
+
+use super::Process;
+use crate::{
+    types::{Interval, Price},
+    Result,
+};
+
+/// How a [`Return`] is computed from two consecutive prices
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReturnKind {
+    /// The natural logarithm of the price ratio
+    #[default]
+    Log,
+    /// The relative price change, e.g. `0.01` for 1%
+    Percentage,
+}
+
+impl ReturnKind {
+    fn compute(self, previous: f64, close: f64) -> f64 {
+        match self {
+            Self::Log => (close / previous).ln(),
+            Self::Percentage => close / previous - 1.0,
+        }
+    }
+}
+
+/// The return of a pair over the interval starting at `start`
+///
+/// `start` is the first block number or unix timestamp of the interval, depending on the
+/// [`Interval`]. The return is computed from the last price of the previous traded interval to
+/// `close`, the last price of this interval.
+#[derive(Clone, Debug)]
+pub struct Return {
+    pub pair: Address,
+    pub start: u64,
+    pub close: f64,
+    pub value: f64,
+}
+
+/// Converts price quotes into a series of returns per pair and interval
+///
+/// An interval is yielded once a price quote of a later interval of the same pair arrives.
+/// Intervals without price quotes are skipped, so the return of the next traded interval spans
+/// them as well.
+pub struct ReturnSeries {
+    interval: Interval,
+    kind: ReturnKind,
+    pairs: HashMap<Address, PairReturns>,
+}
+
+/// The close of the previous and the start and close of the current interval of a pair
+#[derive(Default)]
+struct PairReturns {
+    previous: Option<f64>,
+    current: Option<(u64, f64)>,
+}
+
+impl ReturnSeries {
+    /// Create a new [`ReturnSeries`] of log returns per `interval`
+    pub fn new(interval: Interval) -> Self {
+        Self {
+            interval,
+            kind: ReturnKind::default(),
+            pairs: HashMap::new(),
+        }
+    }
+
+    /// Set how the returns are computed
+    pub fn with_kind(mut self, kind: ReturnKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Compute the returns of the price quotes of `stream`
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<Return>>
+    where
+        S: Stream<Item = Result<Price>>,
+    {
+        super::process(stream, self)
+    }
+
+    fn start(&self, price: &Price) -> u64 {
+        match self.interval {
+            Interval::Blocks(size) => price.block_number / size.max(1) * size.max(1),
+            Interval::Seconds(size) => price.timestamp.max(0) as u64 / size.max(1) * size.max(1),
+        }
+    }
+
+    fn close(&mut self, pair: Address, out: &mut VecDeque<Return>) {
+        let kind = self.kind;
+        let returns = match self.pairs.get_mut(&pair) {
+            Some(returns) => returns,
+            None => return,
+        };
+        let (start, close) = match returns.current.take() {
+            Some(current) => current,
+            None => return,
+        };
+        if let Some(previous) = returns.previous.replace(close) {
+            out.push_back(Return {
+                pair,
+                start,
+                close,
+                value: kind.compute(previous, close),
+            });
+        }
+    }
+}
+
+impl Process for ReturnSeries {
+    type Input = Price;
+    type Output = Return;
+
+    fn push(&mut self, price: Price, out: &mut VecDeque<Return>) {
+        let start = self.start(&price);
+        let current = self.pairs.get(&price.pair).and_then(|pair| pair.current);
+        if matches!(current, Some((current, _)) if current < start) {
+            self.close(price.pair, out);
+        }
+        self.pairs.entry(price.pair).or_default().current = Some((start, price.price));
+    }
+
+    fn finish(&mut self, out: &mut VecDeque<Return>) {
+        let pairs: Vec<_> = self.pairs.keys().copied().collect();
+        for pair in pairs {
+            self.close(pair, out);
+        }
+    }
+}
+
+/// Align the returns of multiple `pairs` by interval, e.g. to compute correlations
+///
+/// Yields the intervals in ascending order, with the returns in the order of `pairs`. Only
+/// intervals with a return for every pair are kept.
+pub fn align(returns: impl IntoIterator<Item = Return>, pairs: &[Address]) -> Vec<(u64, Vec<f64>)> {
+    let mut intervals: BTreeMap<u64, Vec<Option<f64>>> = BTreeMap::new();
+    for ret in returns {
+        if let Some(index) = pairs.iter().position(|pair| *pair == ret.pair) {
+            let values = intervals
+                .entry(ret.start)
+                .or_insert_with(|| vec![None; pairs.len()]);
+            values[index] = Some(ret.value);
+        }
+    }
+
+    intervals
+        .into_iter()
+        .filter_map(|(start, values)| Some((start, values.into_iter().collect::<Option<_>>()?)))
+        .collect()
+}
@@ -0,0 +1,95 @@
+use std::collections::{HashMap, VecDeque};
+
+use ethers::types::Address;
+use futures::Stream;
+
+use super::{window::Window, Process};
+use crate::{
+    types::{Interval, Price},
+    Result,
+};
+
+/// A volume spike of a pair, raised by [`VolumeSpikes`] for the price quote `price`
+///
+/// The volumes are in token1. `baseline_volume` is the volume of the long window, scaled to the
+/// length of the short window.
+#[derive(Clone, Debug)]
+pub struct VolumeSpike {
+    pub price: Price,
+    pub short_volume: f64,
+    pub baseline_volume: f64,
+    pub ratio: f64,
+}
+
+/// Detects spikes of the trading volume per pair
+///
+/// The volume within a short trailing window is compared to the volume within a long trailing
+/// window. A [`VolumeSpike`] is yielded once the short volume exceeds the scaled long volume by
+/// the configured ratio and is not yielded again until it fell below it. Freshly listed pairs
+/// have no history, so their first trades are likely to be reported as a spike.
+pub struct VolumeSpikes {
+    short: Interval,
+    long: Interval,
+    ratio: f64,
+    pairs: HashMap<Address, (Window, Window, bool)>,
+}
+
+impl VolumeSpikes {
+    /// Create a new [`VolumeSpikes`] detecting a short volume of `ratio` times the baseline
+    ///
+    /// `short` and `long` have to be of the same kind, i.e. both in blocks or both in seconds.
+    pub fn new(short: Interval, long: Interval, ratio: f64) -> Self {
+        Self {
+            short,
+            long,
+            ratio,
+            pairs: HashMap::new(),
+        }
+    }
+
+    /// Detect volume spikes of the price quotes of `stream`
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<VolumeSpike>>
+    where
+        S: Stream<Item = Result<Price>>,
+    {
+        super::process(stream, self)
+    }
+}
+
+fn size(interval: Interval) -> f64 {
+    match interval {
+        Interval::Blocks(size) | Interval::Seconds(size) => size.max(1) as f64,
+    }
+}
+
+fn volume(window: &Window) -> f64 {
+    window.items().iter().map(|price| price.volume1.abs()).sum()
+}
+
+impl Process for VolumeSpikes {
+    type Input = Price;
+    type Output = VolumeSpike;
+
+    fn push(&mut self, price: Price, out: &mut VecDeque<VolumeSpike>) {
+        let (short, long, spiking) = self
+            .pairs
+            .entry(price.pair)
+            .or_insert_with(|| (Window::new(self.short), Window::new(self.long), false));
+        short.push(price.clone());
+        long.push(price.clone());
+
+        let short_volume = volume(short);
+        let baseline_volume = volume(long) * size(self.short) / size(self.long);
+        let ratio = short_volume / baseline_volume;
+        let spike = ratio >= self.ratio;
+        if spike && !*spiking {
+            out.push_back(VolumeSpike {
+                price,
+                short_volume,
+                baseline_volume,
+                ratio,
+            });
+        }
+        *spiking = spike;
+    }
+}
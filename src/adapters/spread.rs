@@ -0,0 +1,77 @@
+use std::collections::VecDeque;
+
+use futures::{Stream, StreamExt, TryStreamExt};
+
+use super::Process;
+use crate::{types::Price, Result};
+
+/// The spread between the cheapest and the most expensive of several equivalent markets
+#[derive(Clone, Debug)]
+pub struct Spread {
+    /// The latest price quote of the cheapest market
+    pub buy: Price,
+    /// The latest price quote of the most expensive market
+    pub sell: Price,
+    /// The relative difference between the sell and the buy price
+    pub spread: f64,
+}
+
+/// Monitors equivalent markets, e.g. the same token pair on different DEXes, for price spreads
+///
+/// All markets have to quote the same token in the same token, i.e. have the same token order.
+/// On every price quote the latest quotes of all markets are compared and a [`Spread`] is yielded,
+/// if it is at least the threshold.
+pub struct SpreadMonitor {
+    threshold: f64,
+    latest: Vec<Option<Price>>,
+}
+
+impl SpreadMonitor {
+    /// Create a new [`SpreadMonitor`] yielding spreads of at least `threshold`, e.g. `0.01` for 1%
+    pub fn new(threshold: f64) -> Self {
+        Self {
+            threshold,
+            latest: Vec::new(),
+        }
+    }
+
+    /// Monitor the price quotes of `streams`, one stream per market
+    pub fn apply<S>(
+        mut self,
+        streams: impl IntoIterator<Item = S>,
+    ) -> impl Stream<Item = Result<Spread>>
+    where
+        S: Stream<Item = Result<Price>> + Send + 'static,
+    {
+        let streams: Vec<_> = streams
+            .into_iter()
+            .enumerate()
+            .map(|(market, stream)| stream.map_ok(move |price| (market, price)).boxed())
+            .collect();
+        self.latest = vec![None; streams.len()];
+        super::process(futures::stream::select_all(streams), self)
+    }
+}
+
+impl Process for SpreadMonitor {
+    type Input = (usize, Price);
+    type Output = Spread;
+
+    fn push(&mut self, (market, price): (usize, Price), out: &mut VecDeque<Spread>) {
+        self.latest[market] = Some(price);
+
+        let quotes = self.latest.iter().flatten();
+        let buy = quotes.clone().min_by(|a, b| a.price.total_cmp(&b.price));
+        let sell = quotes.max_by(|a, b| a.price.total_cmp(&b.price));
+        if let (Some(buy), Some(sell)) = (buy, sell) {
+            let spread = sell.price / buy.price - 1.0;
+            if spread >= self.threshold && spread.is_finite() {
+                out.push_back(Spread {
+                    buy: buy.clone(),
+                    sell: sell.clone(),
+                    spread,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::{HashMap, VecDeque};
+
+use ethers::types::Address;
+use futures::Stream;
+
+use super::{window::Window, Process};
+use crate::{
+    types::{Interval, Price},
+    Result,
+};
+
+/// The statistics of the trailing price quotes of a pair
+///
+/// `count` is the number of price quotes within the window, `volume0` and `volume1` are their
+/// summed volumes.
+#[derive(Clone, Debug)]
+pub struct RollingStats {
+    pub pair: Address,
+    pub block_number: u64,
+    pub timestamp: i64,
+    pub count: usize,
+    pub mean: f64,
+    pub variance: f64,
+    pub min: f64,
+    pub max: f64,
+    pub volume0: f64,
+    pub volume1: f64,
+}
+
+/// Computes rolling statistics of the price quotes per pair
+///
+/// For every price quote the statistics over the trailing `window` of that pair are yielded.
+pub struct RollingStatsAdapter {
+    window: Interval,
+    windows: HashMap<Address, Window>,
+}
+
+impl RollingStatsAdapter {
+    /// Create a new [`RollingStatsAdapter`] over the trailing `window`
+    pub fn new(window: Interval) -> Self {
+        Self {
+            window,
+            windows: HashMap::new(),
+        }
+    }
+
+    /// Compute the rolling statistics of the price quotes of `stream`
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<RollingStats>>
+    where
+        S: Stream<Item = Result<Price>>,
+    {
+        super::process(stream, self)
+    }
+}
+
+impl Process for RollingStatsAdapter {
+    type Input = Price;
+    type Output = RollingStats;
+
+    fn push(&mut self, price: Price, out: &mut VecDeque<RollingStats>) {
+        let (pair, block_number, timestamp) = (price.pair, price.block_number, price.timestamp);
+        let window = self
+            .windows
+            .entry(pair)
+            .or_insert_with(|| Window::new(self.window));
+        window.push(price);
+
+        let items = window.items();
+        let count = items.len();
+        let mean = items.iter().map(|price| price.price).sum::<f64>() / count as f64;
+        let variance = items
+            .iter()
+            .map(|price| (price.price - mean).powi(2))
+            .sum::<f64>()
+            / count as f64;
+
+        out.push_back(RollingStats {
+            pair,
+            block_number,
+            timestamp,
+            count,
+            mean,
+            variance,
+            min: items
+                .iter()
+                .map(|price| price.price)
+                .fold(f64::MAX, f64::min),
+            max: items
+                .iter()
+                .map(|price| price.price)
+                .fold(f64::MIN, f64::max),
+            volume0: items.iter().map(|price| price.volume0).sum(),
+            volume1: items.iter().map(|price| price.volume1).sum(),
+        });
+    }
+}
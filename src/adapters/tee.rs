@@ -0,0 +1,499 @@
+use std::io::Write;
+
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+
+use crate::Result;
+
+/// The file format a [`Tee`] writes the rows in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Comma separated values with a header row
+    Csv,
+    /// One JSON object per line
+    Ndjson,
+    /// Apache Parquet, with the schema inferred from the first rows
+    ///
+    /// 128 bit integers, e.g. the reserves of a pair, are written as decimal strings, since they
+    /// exceed the integer columns of the inferred schema.
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+enum Encoder<W: Write + Send> {
+    Csv(Box<csv::Writer<W>>),
+    Ndjson(W),
+    #[cfg(feature = "parquet")]
+    Parquet(Box<parquet_encoder::Encoder<W>>),
+}
+
+impl<W> Encoder<W>
+where
+    W: Write + Send,
+{
+    fn write<T: Serialize>(&mut self, row: &T) -> Result<()> {
+        match self {
+            Self::Csv(writer) => writer.serialize(row)?,
+            Self::Ndjson(writer) => {
+                serde_json::to_writer(&mut *writer, row)?;
+                writer.write_all(b"\n")?;
+            }
+            #[cfg(feature = "parquet")]
+            Self::Parquet(encoder) => encoder.write(row)?,
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            Self::Csv(mut writer) => writer.flush()?,
+            Self::Ndjson(mut writer) => writer.flush()?,
+            #[cfg(feature = "parquet")]
+            Self::Parquet(encoder) => encoder.finish()?,
+        }
+        Ok(())
+    }
+}
+
+/// Tees the rows of a stream to a writer, while passing them through unchanged
+///
+/// This gives every pipeline a durable raw capture of the consumed data, e.g. for replays or
+/// audits. The writer is flushed, and finalized for formats with a footer, once the consumed
+/// stream ended. A failed write is yielded as an error in place of the row, the row is lost.
+///
+/// The writes are blocking, so wrap files in a [`std::io::BufWriter`].
+pub struct Tee<W> {
+    writer: W,
+    format: Format,
+}
+
+impl<W> Tee<W>
+where
+    W: Write + Send,
+{
+    /// Create a new [`Tee`] writing the rows in `format` to `writer`
+    pub fn new(writer: W, format: Format) -> Self {
+        Self { writer, format }
+    }
+
+    /// Tee the rows of `stream`
+    pub fn apply<S, T>(self, stream: S) -> impl Stream<Item = Result<T>>
+    where
+        S: Stream<Item = Result<T>>,
+        T: Serialize,
+    {
+        let encoder = match self.format {
+            Format::Csv => Encoder::Csv(Box::new(csv::Writer::from_writer(self.writer))),
+            Format::Ndjson => Encoder::Ndjson(self.writer),
+            #[cfg(feature = "parquet")]
+            Format::Parquet => {
+                Encoder::Parquet(Box::new(parquet_encoder::Encoder::new(self.writer)))
+            }
+        };
+
+        let state = (Box::pin(stream), Some(encoder));
+        futures::stream::unfold(state, |(mut stream, mut encoder)| async move {
+            let item = match stream.next().await {
+                Some(Ok(row)) => match encoder.as_mut().map(|encoder| encoder.write(&row)) {
+                    Some(Err(err)) => Err(err),
+                    _ => Ok(row),
+                },
+                Some(Err(err)) => Err(err),
+                None => match encoder.take()?.finish() {
+                    Ok(()) => return None,
+                    Err(err) => Err(err),
+                },
+            };
+            Some((item, (stream, encoder)))
+        })
+    }
+}
+
+#[cfg(feature = "parquet")]
+mod parquet_encoder {
+    use std::{io::Write, sync::Arc};
+
+    use arrow_json::reader::{infer_json_schema_from_iterator, Decoder, ReaderBuilder};
+    use parquet::arrow::ArrowWriter;
+    use serde::Serialize;
+
+    use super::wide_ints::WideIntsAsStrings;
+    use crate::Result;
+
+    const BATCH_SIZE: usize = 8192;
+
+    /// Buffers rows and writes them as row groups, inferring the schema from the first batch
+    pub(super) struct Encoder<W: Write + Send> {
+        writer: Option<W>,
+        arrow: Option<(ArrowWriter<W>, Decoder)>,
+        rows: Vec<serde_json::Value>,
+    }
+
+    impl<W> Encoder<W>
+    where
+        W: Write + Send,
+    {
+        pub(super) fn new(writer: W) -> Self {
+            Self {
+                writer: Some(writer),
+                arrow: None,
+                rows: Vec::new(),
+            }
+        }
+
+        pub(super) fn write<T: Serialize>(&mut self, row: &T) -> Result<()> {
+            self.rows
+                .push(serde_json::to_value(WideIntsAsStrings(row))?);
+            if self.rows.len() >= BATCH_SIZE {
+                self.flush()?;
+            }
+            Ok(())
+        }
+
+        pub(super) fn finish(mut self) -> Result<()> {
+            self.flush()?;
+            if let Some((writer, _)) = self.arrow {
+                writer.close()?;
+            }
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            if self.rows.is_empty() {
+                return Ok(());
+            }
+            if self.arrow.is_none() {
+                let schema = infer_json_schema_from_iterator(self.rows.iter().map(Ok))
+                    .map_err(parquet::errors::ParquetError::from)?;
+                let schema = Arc::new(schema);
+                let decoder = ReaderBuilder::new(Arc::clone(&schema))
+                    .build_decoder()
+                    .map_err(parquet::errors::ParquetError::from)?;
+                let writer = self.writer.take().expect("taken once");
+                self.arrow = Some((ArrowWriter::try_new(writer, schema, None)?, decoder));
+            }
+
+            let (writer, decoder) = self.arrow.as_mut().expect("initialized above");
+            decoder
+                .serialize(&self.rows)
+                .map_err(parquet::errors::ParquetError::from)?;
+            if let Some(batch) = decoder
+                .flush()
+                .map_err(parquet::errors::ParquetError::from)?
+            {
+                writer.write(&batch)?;
+            }
+            self.rows.clear();
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "parquet")]
+mod wide_ints {
+    use serde::{
+        ser::{
+            SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+            SerializeTupleStruct, SerializeTupleVariant,
+        },
+        Serialize, Serializer,
+    };
+
+    /// Serializes the wrapped value with all 128 bit integers as decimal strings
+    ///
+    /// JSON values only hold 64 bit integers, so serializing e.g. reserves above `u64::MAX` to a
+    /// [`serde_json::Value`] fails otherwise.
+    pub(super) struct WideIntsAsStrings<'a, T: ?Sized>(pub(super) &'a T);
+
+    impl<T> Serialize for WideIntsAsStrings<'_, T>
+    where
+        T: Serialize + ?Sized,
+    {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.0.serialize(Wrap(serializer))
+        }
+    }
+
+    /// A serializer or compound serializer, that wraps all nested values
+    struct Wrap<S>(S);
+
+    macro_rules! forward {
+        ($($method:ident($ty:ty)),* $(,)?) => {
+            $(
+                fn $method(self, v: $ty) -> Result<S::Ok, S::Error> {
+                    self.0.$method(v)
+                }
+            )*
+        };
+    }
+
+    impl<S: Serializer> Serializer for Wrap<S> {
+        type Ok = S::Ok;
+        type Error = S::Error;
+        type SerializeSeq = Wrap<S::SerializeSeq>;
+        type SerializeTuple = Wrap<S::SerializeTuple>;
+        type SerializeTupleStruct = Wrap<S::SerializeTupleStruct>;
+        type SerializeTupleVariant = Wrap<S::SerializeTupleVariant>;
+        type SerializeMap = Wrap<S::SerializeMap>;
+        type SerializeStruct = Wrap<S::SerializeStruct>;
+        type SerializeStructVariant = Wrap<S::SerializeStructVariant>;
+
+        forward!(
+            serialize_bool(bool),
+            serialize_i8(i8),
+            serialize_i16(i16),
+            serialize_i32(i32),
+            serialize_i64(i64),
+            serialize_u8(u8),
+            serialize_u16(u16),
+            serialize_u32(u32),
+            serialize_u64(u64),
+            serialize_f32(f32),
+            serialize_f64(f64),
+            serialize_char(char),
+            serialize_str(&str),
+            serialize_bytes(&[u8]),
+            serialize_unit_struct(&'static str),
+        );
+
+        fn serialize_i128(self, v: i128) -> Result<S::Ok, S::Error> {
+            self.0.serialize_str(&v.to_string())
+        }
+
+        fn serialize_u128(self, v: u128) -> Result<S::Ok, S::Error> {
+            self.0.serialize_str(&v.to_string())
+        }
+
+        fn serialize_none(self) -> Result<S::Ok, S::Error> {
+            self.0.serialize_none()
+        }
+
+        fn serialize_some<T>(self, value: &T) -> Result<S::Ok, S::Error>
+        where
+            T: Serialize + ?Sized,
+        {
+            self.0.serialize_some(&WideIntsAsStrings(value))
+        }
+
+        fn serialize_unit(self) -> Result<S::Ok, S::Error> {
+            self.0.serialize_unit()
+        }
+
+        fn serialize_unit_variant(
+            self,
+            name: &'static str,
+            variant_index: u32,
+            variant: &'static str,
+        ) -> Result<S::Ok, S::Error> {
+            self.0.serialize_unit_variant(name, variant_index, variant)
+        }
+
+        fn serialize_newtype_struct<T>(
+            self,
+            name: &'static str,
+            value: &T,
+        ) -> Result<S::Ok, S::Error>
+        where
+            T: Serialize + ?Sized,
+        {
+            self.0
+                .serialize_newtype_struct(name, &WideIntsAsStrings(value))
+        }
+
+        fn serialize_newtype_variant<T>(
+            self,
+            name: &'static str,
+            variant_index: u32,
+            variant: &'static str,
+            value: &T,
+        ) -> Result<S::Ok, S::Error>
+        where
+            T: Serialize + ?Sized,
+        {
+            self.0.serialize_newtype_variant(
+                name,
+                variant_index,
+                variant,
+                &WideIntsAsStrings(value),
+            )
+        }
+
+        fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+            self.0.serialize_seq(len).map(Wrap)
+        }
+
+        fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, S::Error> {
+            self.0.serialize_tuple(len).map(Wrap)
+        }
+
+        fn serialize_tuple_struct(
+            self,
+            name: &'static str,
+            len: usize,
+        ) -> Result<Self::SerializeTupleStruct, S::Error> {
+            self.0.serialize_tuple_struct(name, len).map(Wrap)
+        }
+
+        fn serialize_tuple_variant(
+            self,
+            name: &'static str,
+            variant_index: u32,
+            variant: &'static str,
+            len: usize,
+        ) -> Result<Self::SerializeTupleVariant, S::Error> {
+            self.0
+                .serialize_tuple_variant(name, variant_index, variant, len)
+                .map(Wrap)
+        }
+
+        fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+            self.0.serialize_map(len).map(Wrap)
+        }
+
+        fn serialize_struct(
+            self,
+            name: &'static str,
+            len: usize,
+        ) -> Result<Self::SerializeStruct, S::Error> {
+            self.0.serialize_struct(name, len).map(Wrap)
+        }
+
+        fn serialize_struct_variant(
+            self,
+            name: &'static str,
+            variant_index: u32,
+            variant: &'static str,
+            len: usize,
+        ) -> Result<Self::SerializeStructVariant, S::Error> {
+            self.0
+                .serialize_struct_variant(name, variant_index, variant, len)
+                .map(Wrap)
+        }
+
+        fn is_human_readable(&self) -> bool {
+            self.0.is_human_readable()
+        }
+    }
+
+    macro_rules! wrap_compound {
+        ($($trait:ident::$method:ident),* $(,)?) => {
+            $(
+                impl<S: $trait> $trait for Wrap<S> {
+                    type Ok = S::Ok;
+                    type Error = S::Error;
+
+                    fn $method<T>(&mut self, value: &T) -> Result<(), S::Error>
+                    where
+                        T: Serialize + ?Sized,
+                    {
+                        self.0.$method(&WideIntsAsStrings(value))
+                    }
+
+                    fn end(self) -> Result<S::Ok, S::Error> {
+                        self.0.end()
+                    }
+                }
+            )*
+        };
+    }
+
+    wrap_compound!(
+        SerializeSeq::serialize_element,
+        SerializeTuple::serialize_element,
+        SerializeTupleStruct::serialize_field,
+        SerializeTupleVariant::serialize_field,
+    );
+
+    impl<S: SerializeMap> SerializeMap for Wrap<S> {
+        type Ok = S::Ok;
+        type Error = S::Error;
+
+        fn serialize_key<T>(&mut self, key: &T) -> Result<(), S::Error>
+        where
+            T: Serialize + ?Sized,
+        {
+            self.0.serialize_key(&WideIntsAsStrings(key))
+        }
+
+        fn serialize_value<T>(&mut self, value: &T) -> Result<(), S::Error>
+        where
+            T: Serialize + ?Sized,
+        {
+            self.0.serialize_value(&WideIntsAsStrings(value))
+        }
+
+        fn end(self) -> Result<S::Ok, S::Error> {
+            self.0.end()
+        }
+    }
+
+    impl<S: SerializeStruct> SerializeStruct for Wrap<S> {
+        type Ok = S::Ok;
+        type Error = S::Error;
+
+        fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), S::Error>
+        where
+            T: Serialize + ?Sized,
+        {
+            self.0.serialize_field(key, &WideIntsAsStrings(value))
+        }
+
+        fn skip_field(&mut self, key: &'static str) -> Result<(), S::Error> {
+            self.0.skip_field(key)
+        }
+
+        fn end(self) -> Result<S::Ok, S::Error> {
+            self.0.end()
+        }
+    }
+
+    impl<S: SerializeStructVariant> SerializeStructVariant for Wrap<S> {
+        type Ok = S::Ok;
+        type Error = S::Error;
+
+        fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), S::Error>
+        where
+            T: Serialize + ?Sized,
+        {
+            self.0.serialize_field(key, &WideIntsAsStrings(value))
+        }
+
+        fn skip_field(&mut self, key: &'static str) -> Result<(), S::Error> {
+            self.0.skip_field(key)
+        }
+
+        fn end(self) -> Result<S::Ok, S::Error> {
+            self.0.end()
+        }
+    }
+}
+
+#[cfg(all(test, feature = "parquet"))]
+mod tests {
+    use futures::TryStreamExt;
+
+    use super::{wide_ints::WideIntsAsStrings, *};
+    use crate::types::fixtures::gateway_reserves;
+
+    #[test]
+    fn writes_wide_ints_as_strings() {
+        let row = gateway_reserves(u128::MAX, 1);
+        let value = serde_json::to_value(WideIntsAsStrings(&row)).unwrap();
+        assert_eq!(value["reserve0"], u128::MAX.to_string());
+        assert_eq!(value["reserve1"], "1");
+        assert!(serde_json::to_value(&row).is_err());
+    }
+
+    #[tokio::test]
+    async fn writes_reserves_above_u64_to_parquet() {
+        let rows = vec![gateway_reserves(u128::MAX, 1), gateway_reserves(1, 2)];
+        let mut file = Vec::new();
+        let written: Vec<_> = Tee::new(&mut file, Format::Parquet)
+            .apply(futures::stream::iter(rows.into_iter().map(Ok)))
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(written.len(), 2);
+        assert!(file.starts_with(b"PAR1"));
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::VecDeque;
+
+use ethers::types::Address;
+use futures::{future::Either, Stream, StreamExt, TryStreamExt};
+
+use super::Process;
+use crate::{
+    types::{PairInfo, Reserves, Tvl, UsdPrice},
+    Error, Result,
+};
+
+/// Computes the USD denominated [`Tvl`] of a pair from its reserves
+///
+/// The reserves are valued with the latest known USD prices of both tokens, which are either
+/// fixed with [`TvlCalculator::with_usd_prices`] or taken from a stream of [`UsdPrice`]s.
+pub struct TvlCalculator {
+    pair: Address,
+    tokens: [Address; 2],
+    decimals: [u8; 2],
+    usd_prices: [Option<f64>; 2],
+    pending: Option<Reserves>,
+}
+
+impl TvlCalculator {
+    /// Create a new [`TvlCalculator`] for the pair described by `info`
+    pub fn new(info: &PairInfo) -> Self {
+        Self {
+            pair: info.pair,
+            tokens: [info.token0, info.token1],
+            decimals: [info.decimals0, info.decimals1],
+            usd_prices: [None, None],
+            pending: None,
+        }
+    }
+
+    /// Set the USD prices of token0 and token1, e.g. for offline computations
+    pub fn with_usd_prices(mut self, usd_price0: f64, usd_price1: f64) -> Self {
+        self.usd_prices = [Some(usd_price0), Some(usd_price1)];
+        self
+    }
+
+    /// Compute the TVL of the pair as of `reserves`
+    ///
+    /// Returns `None` if the USD price of either token is not known yet, or the block or
+    /// timestamp of `reserves` is unknown.
+    pub fn tvl(&self, reserves: &Reserves) -> Option<Tvl> {
+        let reserve0_usd = self.value(0, reserves.reserve0)?;
+        let reserve1_usd = self.value(1, reserves.reserve1)?;
+        Some(Tvl {
+            block_number: reserves.block_number?,
+            pair: self.pair,
+            reserve0_usd,
+            reserve1_usd,
+            tvl_usd: reserve0_usd + reserve1_usd,
+            timestamp: reserves.timestamp?,
+        })
+    }
+
+    /// Compute a per block TVL series of the pair from `reserves`, valued with `usd_prices`
+    ///
+    /// `usd_prices` usually is the stream returned by
+    /// [`WsClient::get_usd_prices`](crate::WsClient::get_usd_prices) for both tokens of the pair.
+    /// A block is yielded once reserves of a later block arrive, blocks before both USD prices are
+    /// known are skipped.
+    ///
+    /// `reserves` have to be scoped to the pair, reserves of other pairs are skipped. Reserves
+    /// without a pair, as the gateway currently sends them, are assumed to be of this pair.
+    /// Reserves without a block can't be assigned to a block and are yielded as
+    /// [`Error::MissingField`] instead.
+    pub fn apply<R, U>(self, reserves: R, usd_prices: U) -> impl Stream<Item = Result<Tvl>>
+    where
+        R: Stream<Item = Result<Reserves>>,
+        U: Stream<Item = Result<UsdPrice>>,
+    {
+        let merged = futures::stream::select(
+            reserves.map_ok(Either::Left),
+            usd_prices.map_ok(Either::Right),
+        );
+        super::process(merged, self).map(|tvl| tvl?)
+    }
+
+    fn value(&self, token: usize, reserve: u128) -> Option<f64> {
+        let amount = reserve as f64 / 10f64.powi(self.decimals[token] as i32);
+        Some(amount * self.usd_prices[token]?)
+    }
+}
+
+impl Process for TvlCalculator {
+    type Input = Either<Reserves, UsdPrice>;
+    type Output = Result<Tvl>;
+
+    fn push(&mut self, item: Self::Input, out: &mut VecDeque<Result<Tvl>>) {
+        match item {
+            Either::Left(reserves) => {
+                if matches!(reserves.pair, Some(pair) if pair != self.pair) {
+                    return;
+                }
+                if reserves.block_number.is_none() {
+                    return out.push_back(Err(Error::MissingField("block number")));
+                }
+                if let Some(pending) = self.pending.take() {
+                    if pending.block_number < reserves.block_number {
+                        out.extend(self.tvl(&pending).map(Ok));
+                    }
+                }
+                self.pending = Some(reserves);
+            }
+            Either::Right(usd_price) => {
+                for (token, price) in self.tokens.iter().zip(&mut self.usd_prices) {
+                    if *token == usd_price.token {
+                        *price = Some(usd_price.price_usd);
+                    }
+                }
+            }
+        }
+    }
+
+    fn finish(&mut self, out: &mut VecDeque<Result<Tvl>>) {
+        if let Some(pending) = self.pending.take() {
+            out.extend(self.tvl(&pending).map(Ok));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::U256;
+
+    use super::*;
+    use crate::types::fixtures::{gateway_reserves, reserves};
+
+    fn calculator() -> TvlCalculator {
+        let info = PairInfo {
+            block_number: 1,
+            factory: Address::zero(),
+            pair: Address::from_low_u64_be(1),
+            token0: Address::from_low_u64_be(2),
+            token1: Address::from_low_u64_be(3),
+            pair_index: U256::zero(),
+            timestamp: 12,
+            transaction_hash: Default::default(),
+            transaction_index: 0,
+            symbol0: "A".to_owned(),
+            symbol1: "B".to_owned(),
+            name0: "A".to_owned(),
+            name1: "B".to_owned(),
+            decimals0: 0,
+            decimals1: 0,
+            reserve0: 0,
+            reserve1: 0,
+            reserves_block_number: 1,
+        };
+        TvlCalculator::new(&info).with_usd_prices(1.0, 2.0)
+    }
+
+    async fn tvl(rows: Vec<Reserves>) -> Vec<Result<Tvl>> {
+        let rows = futures::stream::iter(rows.into_iter().map(Ok));
+        calculator()
+            .apply(rows, futures::stream::empty())
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn values_reserves_without_pair() {
+        let mut unnamed = reserves(1, 10, 0, None);
+        unnamed.pair = None;
+        let rows = vec![unnamed, reserves(1, 11, 0, None), reserves(2, 12, 0, None)];
+        let blocks: Vec<_> = tvl(rows)
+            .await
+            .into_iter()
+            .map(|tvl| tvl.unwrap().block_number)
+            .collect();
+        assert_eq!(blocks, [10, 11]);
+    }
+
+    #[tokio::test]
+    async fn reports_reserves_without_block() {
+        let rows = tvl(vec![gateway_reserves(1, 2)]).await;
+        assert!(matches!(
+            &rows[..],
+            [Err(Error::MissingField("block number"))]
+        ));
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use ethers::types::Address;
+use futures::{future::Either, Stream, TryStreamExt};
+
+use super::Process;
+use crate::{
+    types::{PairInfo, Price},
+    Result,
+};
+
+/// A price quote with USD denominated price and volume
+///
+/// The USD fields are `None` if neither token of the pair could be valued in USD yet.
+#[derive(Clone, Debug)]
+pub struct UsdSwap {
+    pub price: Price,
+    /// The USD price of token0
+    pub price_usd: Option<f64>,
+    /// The USD value of the traded amount
+    pub volume_usd: Option<f64>,
+}
+
+/// Maps price quotes to USD terms, using stablecoin reference pairs
+///
+/// Stablecoins are valued at one USD. The other token of a reference pair, e.g. WETH of a
+/// WETH/USDC pair, is valued with the latest price quote of the reference pair. A price quote is
+/// valued in USD if either of its tokens is a stablecoin or a token of a reference pair.
+pub struct UsdPricer {
+    stables: HashSet<Address>,
+    pairs: HashMap<Address, [Address; 2]>,
+    references: HashSet<Address>,
+    usd_prices: HashMap<Address, f64>,
+}
+
+impl UsdPricer {
+    /// Create a new [`UsdPricer`] without any reference pairs
+    pub fn new() -> Self {
+        Self {
+            stables: HashSet::new(),
+            pairs: HashMap::new(),
+            references: HashSet::new(),
+            usd_prices: HashMap::new(),
+        }
+    }
+
+    /// Add a reference pair, of which `stable` is the USD stablecoin
+    pub fn with_reference(mut self, info: &PairInfo, stable: Address) -> Self {
+        self.stables.insert(stable);
+        self.references.insert(info.pair);
+        self.with_pair(info)
+    }
+
+    /// Add a pair, whose price quotes are mapped to USD terms
+    ///
+    /// Price quotes of pairs, that were not added, are passed through without USD fields.
+    pub fn with_pair(mut self, info: &PairInfo) -> Self {
+        self.pairs.insert(info.pair, [info.token0, info.token1]);
+        self
+    }
+
+    /// The reference pairs, whose price quotes have to be passed to [`UsdPricer::apply`]
+    pub fn reference_pairs(&self) -> impl Iterator<Item = Address> + '_ {
+        self.references.iter().copied()
+    }
+
+    /// Map the price quotes of `prices` to USD terms, using the price quotes of `references`
+    ///
+    /// `references` usually is the stream returned by
+    /// [`WsClient::get_prices`](crate::WsClient::get_prices) for the
+    /// [`reference_pairs`](UsdPricer::reference_pairs). Price quotes of reference pairs within
+    /// `prices` update the reference prices as well.
+    pub fn apply<P, R>(self, prices: P, references: R) -> impl Stream<Item = Result<UsdSwap>>
+    where
+        P: Stream<Item = Result<Price>>,
+        R: Stream<Item = Result<Price>>,
+    {
+        let merged = futures::stream::select(
+            prices.map_ok(Either::Left),
+            references.map_ok(Either::Right),
+        );
+        super::process(merged, self)
+    }
+
+    /// The USD price of `token`, if known
+    pub fn usd_price(&self, token: Address) -> Option<f64> {
+        if self.stables.contains(&token) {
+            return Some(1.0);
+        }
+        self.usd_prices.get(&token).copied()
+    }
+
+    fn update_reference(&mut self, price: &Price) {
+        if !self.references.contains(&price.pair) {
+            return;
+        }
+        let [token0, token1] = match self.pairs.get(&price.pair) {
+            Some(tokens) => *tokens,
+            None => return,
+        };
+        if self.stables.contains(&token1) {
+            self.usd_prices.insert(token0, price.price);
+        } else if self.stables.contains(&token0) && price.price != 0.0 {
+            self.usd_prices.insert(token1, price.price.recip());
+        }
+    }
+
+    fn map(&self, price: Price) -> UsdSwap {
+        let tokens = self.pairs.get(&price.pair);
+        let usd0 = tokens.and_then(|[token0, _]| self.usd_price(*token0));
+        let usd1 = tokens.and_then(|[_, token1]| self.usd_price(*token1));
+
+        let (price_usd, volume_usd) = match (usd0, usd1) {
+            (_, Some(usd1)) => (price.price * usd1, price.volume1.abs() * usd1),
+            (Some(usd0), None) => (usd0, price.volume0.abs() * usd0),
+            (None, None) => {
+                return UsdSwap {
+                    price,
+                    price_usd: None,
+                    volume_usd: None,
+                }
+            }
+        };
+        UsdSwap {
+            price,
+            price_usd: Some(price_usd),
+            volume_usd: Some(volume_usd),
+        }
+    }
+}
+
+impl Default for UsdPricer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Process for UsdPricer {
+    type Input = Either<Price, Price>;
+    type Output = UsdSwap;
+
+    fn push(&mut self, item: Self::Input, out: &mut VecDeque<UsdSwap>) {
+        match item {
+            Either::Left(price) => {
+                self.update_reference(&price);
+                out.push_back(self.map(price));
+            }
+            Either::Right(reference) => self.update_reference(&reference),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::VecDeque;
+
+use crate::types::{Interval, Price};
+
+/// The trailing price quotes of a single pair within an [`Interval`]
+pub(crate) struct Window {
+    interval: Interval,
+    items: VecDeque<Price>,
+    /// The last price quote that left the window, which is the price at the window start
+    carry: Option<Price>,
+}
+
+impl Window {
+    pub(crate) fn new(interval: Interval) -> Self {
+        Self {
+            interval,
+            items: VecDeque::new(),
+            carry: None,
+        }
+    }
+
+    pub(crate) fn push(&mut self, price: Price) {
+        self.items.push_back(price);
+        let cutoff = self.start();
+        while let Some(front) = self.items.front() {
+            if self.position(front) > cutoff || self.items.len() == 1 {
+                break;
+            }
+            self.carry = self.items.pop_front();
+        }
+    }
+
+    pub(crate) fn items(&self) -> &VecDeque<Price> {
+        &self.items
+    }
+
+    /// The position the window starts after, in blocks or seconds
+    pub(crate) fn start(&self) -> u64 {
+        let size = match self.interval {
+            Interval::Blocks(size) | Interval::Seconds(size) => size,
+        };
+        self.end().saturating_sub(size)
+    }
+
+    /// The position of the most recent price quote, in blocks or seconds
+    pub(crate) fn end(&self) -> u64 {
+        self.items.back().map_or(0, |price| self.position(price))
+    }
+
+    /// The time weighted average price within the window
+    ///
+    /// If the window has no extent yet, this is the most recent price.
+    pub(crate) fn twap(&self) -> Option<f64> {
+        let start = self.start();
+        let points = self
+            .carry
+            .iter()
+            .chain(&self.items)
+            .map(|price| (self.position(price).max(start), price.price));
+        time_weighted(points, self.end())
+    }
+
+    fn position(&self, price: &Price) -> u64 {
+        match self.interval {
+            Interval::Blocks(_) => price.block_number,
+            Interval::Seconds(_) => price.timestamp.max(0) as u64,
+        }
+    }
+}
+
+/// Average the prices of `points`, weighted by the time until the next point or `end`
+///
+/// `points` must be ordered by their position. If they span no time at all, this is the last
+/// price.
+pub(crate) fn time_weighted(points: impl IntoIterator<Item = (u64, f64)>, end: u64) -> Option<f64> {
+    let mut points = points.into_iter().peekable();
+    let mut weighted = 0.0;
+    let mut duration = 0;
+    let mut last = None;
+    while let Some((position, price)) = points.next() {
+        let until = points.peek().map_or(end, |(next, _)| *next).max(position);
+        weighted += price * (until - position) as f64;
+        duration += until - position;
+        last = Some(price);
+    }
+
+    match duration {
+        0 => last,
+        duration => Some(weighted / duration as f64),
+    }
+}
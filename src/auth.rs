@@ -0,0 +1,290 @@
+//! Token based authentication, as an alternative to basic auth
+//!
+//! A [`TokenProvider`] fetches short lived bearer tokens, e.g. with [`OAuth2`]. Set on the
+//! [`Config`](crate::config::Config) with `ConfigBuilder::with_token_provider`, the tokens are
+//! cached in a [`TokenCache`] and refreshed shortly before they expire.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use tokio::sync::Mutex;
+
+use crate::Result;
+
+/// A bearer token, as fetched by a [`TokenProvider`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub access_token: String,
+    /// How long the token is valid from now, `None` if it doesn't expire
+    pub expires_in: Option<Duration>,
+}
+
+/// Fetches new bearer tokens
+#[async_trait::async_trait]
+pub trait TokenProvider: Send + Sync {
+    /// Fetch a new token, invalidating the previous one is up to the provider
+    async fn fetch_token(&self) -> Result<Token>;
+}
+
+/// The credentials a client authenticates with
+///
+/// Clients can switch to new credentials at runtime, e.g. with `HttpClient::set_credentials`,
+/// to rotate passwords or tokens without reconnecting.
+#[derive(Clone)]
+pub enum Credentials {
+    /// Basic auth with a username and password
+    Basic { username: String, password: String },
+    /// Basic auth with a username and the password in a [`SecretFile`]
+    BasicFile {
+        username: String,
+        password: Arc<SecretFile>,
+    },
+    /// Bearer tokens of a [`TokenCache`]
+    Tokens(Arc<TokenCache>),
+}
+
+impl Credentials {
+    pub fn basic(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Self::Basic {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+
+    /// The `Authorization` header value
+    pub async fn authorization(&self) -> Result<String> {
+        match self {
+            Self::Basic { username, password } => Ok(basic(username, password)),
+            Self::BasicFile { username, password } => Ok(basic(username, &password.read()?)),
+            Self::Tokens(tokens) => tokens.authorization().await,
+        }
+    }
+}
+
+fn basic(username: &str, password: &str) -> String {
+    let encoded = base64::encode(format!("{username}:{password}"));
+    format!("Basic {encoded}")
+}
+
+/// A secret kept in a file, e.g. a mounted Docker or Kubernetes secret
+///
+/// The file is read again once its modification time changed, so a rotated secret is picked up
+/// without restarting.
+pub struct SecretFile {
+    path: PathBuf,
+    cached: std::sync::Mutex<Option<(SystemTime, String)>>,
+}
+
+impl SecretFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            cached: std::sync::Mutex::new(None),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The current secret, without trailing whitespace like the final newline
+    pub fn read(&self) -> Result<String> {
+        let modified = fs::metadata(&self.path)?.modified()?;
+        let mut cached = self.cached.lock().expect("the lock is never poisoned");
+        match &*cached {
+            Some((at, secret)) if *at == modified => Ok(secret.clone()),
+            _ => {
+                let secret = fs::read_to_string(&self.path)?.trim_end().to_owned();
+                *cached = Some((modified, secret.clone()));
+                Ok(secret)
+            }
+        }
+    }
+}
+
+/// A [`TokenProvider`] of a fixed token, that doesn't expire
+pub struct StaticToken {
+    token: String,
+}
+
+impl StaticToken {
+    pub fn new(token: impl Into<String>) -> Self {
+        Self {
+            token: token.into(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl TokenProvider for StaticToken {
+    async fn fetch_token(&self) -> Result<Token> {
+        Ok(Token {
+            access_token: self.token.clone(),
+            expires_in: None,
+        })
+    }
+}
+
+/// Caches the tokens of a [`TokenProvider`], refreshing them shortly before they expire
+///
+/// Concurrent requests for an expired token wait for a single refresh.
+pub struct TokenCache {
+    provider: Arc<dyn TokenProvider>,
+    margin: Duration,
+    token: Mutex<Option<(String, Option<Instant>)>>,
+}
+
+impl TokenCache {
+    /// Create a new [`TokenCache`] refreshing tokens 30s before they expire
+    pub fn new(provider: Arc<dyn TokenProvider>) -> Self {
+        Self {
+            provider,
+            margin: Duration::from_secs(30),
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Set how long before their expiry tokens are refreshed, defaults to 30s
+    pub fn with_margin(mut self, margin: Duration) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// The `Authorization` header value with a valid token
+    pub async fn authorization(&self) -> Result<String> {
+        let mut token = self.token.lock().await;
+        match &*token {
+            Some((access_token, None)) => return Ok(format!("Bearer {access_token}")),
+            Some((access_token, Some(refresh_at))) if Instant::now() < *refresh_at => {
+                return Ok(format!("Bearer {access_token}"))
+            }
+            _ => {}
+        }
+
+        let fetched = self.provider.fetch_token().await?;
+        let refresh_at = fetched
+            .expires_in
+            .map(|expires_in| Instant::now() + expires_in.saturating_sub(self.margin));
+        let authorization = format!("Bearer {}", fetched.access_token);
+        *token = Some((fetched.access_token, refresh_at));
+        Ok(authorization)
+    }
+
+    /// Drop the cached token, e.g. after the gateway rejected it, so the next request fetches a
+    /// new one
+    pub async fn invalidate(&self) {
+        *self.token.lock().await = None;
+    }
+}
+
+/// A [`TokenProvider`] fetching tokens from an OAuth2 token endpoint
+///
+/// With a refresh token, tokens are fetched with the `refresh_token` grant and rotated refresh
+/// tokens are kept for the next refresh. Otherwise the `client_credentials` grant is used.
+#[cfg(feature = "http")]
+pub struct OAuth2 {
+    client: reqwest::Client,
+    token_url: url::Url,
+    client_id: String,
+    client_secret: Option<String>,
+    scope: Option<String>,
+    refresh_token: std::sync::Mutex<Option<String>>,
+}
+
+#[cfg(feature = "http")]
+impl OAuth2 {
+    /// Create a new [`OAuth2`] provider of `client_id` fetching from `token_url`
+    pub fn new(token_url: url::Url, client_id: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            token_url,
+            client_id: client_id.into(),
+            client_secret: None,
+            scope: None,
+            refresh_token: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Use a custom configured `client`, e.g. with a request timeout
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn with_client_secret(mut self, client_secret: impl Into<String>) -> Self {
+        self.client_secret = Some(client_secret.into());
+        self
+    }
+
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    /// Fetch tokens with the `refresh_token` grant, starting with `refresh_token`
+    pub fn with_refresh_token(self, refresh_token: impl Into<String>) -> Self {
+        *self
+            .refresh_token
+            .lock()
+            .expect("the lock is never poisoned") = Some(refresh_token.into());
+        self
+    }
+}
+
+#[cfg(feature = "http")]
+#[async_trait::async_trait]
+impl TokenProvider for OAuth2 {
+    async fn fetch_token(&self) -> Result<Token> {
+        #[derive(serde::Deserialize)]
+        struct Response {
+            access_token: String,
+            expires_in: Option<u64>,
+            refresh_token: Option<String>,
+        }
+
+        let refresh_token = self
+            .refresh_token
+            .lock()
+            .expect("the lock is never poisoned")
+            .clone();
+        let mut form = vec![("client_id", self.client_id.as_str())];
+        match &refresh_token {
+            Some(refresh_token) => form.extend([
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token),
+            ]),
+            None => form.push(("grant_type", "client_credentials")),
+        }
+        if let Some(client_secret) = &self.client_secret {
+            form.push(("client_secret", client_secret));
+        }
+        if let Some(scope) = &self.scope {
+            form.push(("scope", scope));
+        }
+
+        let response: Response = self
+            .client
+            .post(self.token_url.clone())
+            .form(&form)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if let Some(rotated) = response.refresh_token {
+            *self
+                .refresh_token
+                .lock()
+                .expect("the lock is never poisoned") = Some(rotated);
+        }
+
+        Ok(Token {
+            access_token: response.access_token,
+            expires_in: response.expires_in.map(Duration::from_secs),
+        })
+    }
+}
@@ -0,0 +1,276 @@
+//! Bulk export of historical rows to partitioned Parquet files
+//!
+//! A [`Backfill`] is the batch counterpart to the live streams: it splits the block range of
+//! every pair into chunks, fetches them in parallel and writes each chunk to its own Parquet
+//! file. A `manifest.json` listing all files is written last, so its presence marks a complete
+//! export.
+
+use std::{
+    fs,
+    future::Future,
+    io::BufWriter,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use ethers::types::H160;
+use futures::{StreamExt, TryStreamExt};
+use serde::Serialize;
+
+use crate::{
+    adapters::{Format, Tee},
+    Result, SuperchainProvider,
+};
+
+/// The rows a [`Backfill`] exports
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dataset {
+    Prices,
+    Reserves,
+}
+
+impl Dataset {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Prices => "prices",
+            Self::Reserves => "reserves",
+        }
+    }
+}
+
+/// A Parquet file written by a [`Backfill`]
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct ManifestEntry {
+    /// The path of the file, relative to the export directory
+    pub path: String,
+    pub pair: H160,
+    pub from_block: u64,
+    pub to_block: u64,
+    pub rows: u64,
+}
+
+/// The files of a completed [`Backfill`], as stored in `manifest.json`
+///
+/// Chunks without any rows are not written and thus not listed.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Manifest {
+    pub dataset: Dataset,
+    pub from_block: u64,
+    pub to_block: u64,
+    pub files: Vec<ManifestEntry>,
+}
+
+/// Exports the rows of a set of pairs in a block range to Parquet files
+///
+/// The files are partitioned by pair and chunk, as
+/// `<dataset>/pair=<pair>/blocks=<from>-<to>.parquet`. Chunks failing with a retryable error are
+/// fetched again with an exponential backoff, the export fails once a chunk exhausted its
+/// retries.
+pub struct Backfill<P> {
+    provider: P,
+    pairs: Vec<H160>,
+    blocks: RangeInclusive<u64>,
+    dataset: Dataset,
+    chunk_size: u64,
+    concurrency: usize,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl<P> Backfill<P>
+where
+    P: SuperchainProvider,
+{
+    /// Create a new [`Backfill`] of the prices of `pairs` in `blocks`
+    pub fn new(
+        provider: P,
+        pairs: impl IntoIterator<Item = H160>,
+        blocks: RangeInclusive<u64>,
+    ) -> Self {
+        Self {
+            provider,
+            pairs: pairs.into_iter().collect(),
+            blocks,
+            dataset: Dataset::Prices,
+            chunk_size: 100_000,
+            concurrency: 4,
+            retries: 3,
+            backoff: Duration::from_secs(1),
+        }
+    }
+
+    /// Set the rows to export, defaults to [`Dataset::Prices`]
+    pub fn with_dataset(mut self, dataset: Dataset) -> Self {
+        self.dataset = dataset;
+        self
+    }
+
+    /// Set the number of blocks per file, defaults to 100,000
+    pub fn with_chunk_size(mut self, chunk_size: u64) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Set the number of chunks fetched in parallel, defaults to 4
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set how often a failed chunk is fetched again, and the backoff before the first retry,
+    /// defaults to 3 retries after 1s
+    pub fn with_retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.retries = retries;
+        self.backoff = backoff;
+        self
+    }
+
+    /// Export all chunks to `dir` and write the manifest
+    pub async fn run(&self, dir: impl AsRef<Path>) -> Result<Manifest> {
+        let dir = dir.as_ref();
+        let chunks = self.pairs.iter().flat_map(|pair| {
+            let (start, end) = (*self.blocks.start(), *self.blocks.end());
+            (start..=end)
+                .step_by(self.chunk_size as usize)
+                .map(move |from| {
+                    (
+                        *pair,
+                        from,
+                        end.min(from.saturating_add(self.chunk_size - 1)),
+                    )
+                })
+        });
+
+        let mut files: Vec<_> = futures::stream::iter(chunks)
+            .map(|(pair, from, to)| self.export(dir, pair, from, to))
+            .buffer_unordered(self.concurrency)
+            .try_filter_map(|entry| futures::future::ready(Ok(entry)))
+            .try_collect()
+            .await?;
+        files.sort_by_key(|entry| (entry.pair, entry.from_block));
+
+        let manifest = Manifest {
+            dataset: self.dataset,
+            from_block: *self.blocks.start(),
+            to_block: *self.blocks.end(),
+            files,
+        };
+        fs::create_dir_all(dir)?;
+        fs::write(
+            dir.join("manifest.json"),
+            serde_json::to_vec_pretty(&manifest)?,
+        )?;
+
+        Ok(manifest)
+    }
+
+    /// Fetch and write a single chunk, returning its entry if it has any rows
+    async fn export(
+        &self,
+        dir: &Path,
+        pair: H160,
+        from: u64,
+        to: u64,
+    ) -> Result<Option<ManifestEntry>> {
+        let path = PathBuf::from(self.dataset.name())
+            .join(format!("pair={pair:?}"))
+            .join(format!("blocks={from}-{to}.parquet"));
+        let rows = match self.dataset {
+            Dataset::Prices => {
+                let rows = self
+                    .retry(|| async {
+                        let prices = self.provider.get_prices(pair, from, Some(to)).await?;
+                        prices.try_collect::<Vec<_>>().await
+                    })
+                    .await?;
+                write(&dir.join(&path), rows).await?
+            }
+            Dataset::Reserves => {
+                let rows = self
+                    .retry(|| async {
+                        let reserves = self.provider.get_reserves(pair, from, Some(to)).await?;
+                        reserves.try_collect::<Vec<_>>().await
+                    })
+                    .await?;
+                write(&dir.join(&path), rows).await?
+            }
+        };
+        if rows == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(ManifestEntry {
+            path: path.to_string_lossy().into_owned(),
+            pair,
+            from_block: from,
+            to_block: to,
+            rows,
+        }))
+    }
+
+    async fn retry<T, F, Fut>(&self, fetch: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut backoff = self.backoff;
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match fetch().await {
+                Err(err) if err.is_retryable() && attempts <= self.retries => {
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Write `rows` as Parquet to `path`, returning the number of rows written
+async fn write<T: Serialize>(path: &Path, rows: Vec<T>) -> Result<u64> {
+    if rows.is_empty() {
+        return Ok(0);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = BufWriter::new(fs::File::create(path)?);
+
+    let count = rows.len() as u64;
+    Tee::new(file, Format::Parquet)
+        .apply(futures::stream::iter(rows.into_iter().map(Ok)))
+        .try_for_each(|_| futures::future::ready(Ok(())))
+        .await?;
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{types::fixtures::reserves, MockClient};
+
+    #[tokio::test]
+    async fn exports_reserves_above_u64() {
+        let mut wide = reserves(1, 10, 0, None);
+        wide.reserve0 = u128::from(u64::MAX) + 1;
+        let mock = MockClient::new().with_reserves([wide, reserves(1, 11, 0, None)]);
+        let dir = std::env::temp_dir().join(format!("sc-backfill-{}", std::process::id()));
+
+        let manifest = Backfill::new(mock, [H160::from_low_u64_be(1)], 10..=19)
+            .with_dataset(Dataset::Reserves)
+            .run(&dir)
+            .await
+            .unwrap();
+        let written = dir.join(&manifest.files[0].path).exists();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(manifest.files.len(), 1);
+        assert_eq!(manifest.files[0].rows, 2);
+        assert!(written);
+    }
+}
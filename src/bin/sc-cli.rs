@@ -0,0 +1,194 @@
+//! Pull data from the Superchain gateway on the command line
+//!
+//! Credentials are read from `--username`/`--password`, or the `SC_USERNAME`/`SC_PASSWORD`
+//! environment variables (a `.env` file works as well).
+
+use std::{fs::File, io::Write, path::PathBuf};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use superchain_client::{
+    config::Config, ethers::types::H160, Client, Result, DEFAULT_HTTP_URL, DEFAULT_WS_URL,
+};
+
+#[derive(Parser)]
+#[clap(
+    name = "sc-cli",
+    version,
+    about = "Pull data from the Superchain gateway"
+)]
+struct Cli {
+    #[clap(long, env = "SC_USERNAME")]
+    username: String,
+    #[clap(long, env = "SC_PASSWORD", hide_env_values = true)]
+    password: String,
+    #[clap(long, default_value = DEFAULT_HTTP_URL)]
+    http_url: String,
+    #[clap(long, default_value = DEFAULT_WS_URL)]
+    ws_url: String,
+    /// The output format of the rows
+    #[clap(long, value_enum, default_value = "csv")]
+    format: Format,
+    /// Write the rows to this file instead of stdout
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Get the PairCreated events of the provided pairs, or of all pairs without any
+    Pairs(Range),
+    /// Get the price quotes of the provided pairs, or of all pairs without any
+    Prices(Range),
+    /// Get the reserves of the provided pairs, or of all pairs without any
+    Reserves(Range),
+    /// Print the current and the finalized height
+    Height,
+}
+
+/// The queried pairs and range, without an end the stream follows head
+#[derive(Args)]
+struct Range {
+    /// A pair to query, can be repeated
+    #[clap(long = "pair", value_parser)]
+    pairs: Vec<H160>,
+    /// Start at this block
+    #[clap(long, conflicts_with = "from-time")]
+    from_block: Option<u64>,
+    /// End with this block, inclusive
+    #[clap(long, conflicts_with = "to-time")]
+    to_block: Option<u64>,
+    /// Start at the first block at or after this unix timestamp
+    #[clap(long)]
+    from_time: Option<i64>,
+    /// End with the last block at or before this unix timestamp
+    #[clap(long)]
+    to_time: Option<i64>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Csv,
+    /// One JSON object per line
+    Json,
+}
+
+enum Output {
+    Csv(Box<csv::Writer<Box<dyn Write>>>),
+    Json(Box<dyn Write>),
+}
+
+impl Output {
+    fn new(format: Format, path: Option<PathBuf>) -> Result<Self> {
+        let writer: Box<dyn Write> = match path {
+            Some(path) => Box::new(File::create(path)?),
+            None => Box::new(std::io::stdout()),
+        };
+        Ok(match format {
+            Format::Csv => Self::Csv(Box::new(csv::Writer::from_writer(writer))),
+            Format::Json => Self::Json(writer),
+        })
+    }
+
+    /// Write a row, flushing right away, since live streams never end
+    fn write<T: Serialize>(&mut self, row: &T) -> Result<()> {
+        match self {
+            Self::Csv(writer) => {
+                writer.serialize(row)?;
+                writer.flush()?;
+            }
+            Self::Json(writer) => {
+                serde_json::to_writer(&mut *writer, row)?;
+                writer.write_all(b"\n")?;
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn write_all<S, T>(&mut self, stream: S) -> Result<()>
+    where
+        S: Stream<Item = Result<T>>,
+        T: Serialize,
+    {
+        futures::pin_mut!(stream);
+        while let Some(row) = stream.next().await {
+            self.write(&row?)?;
+        }
+        Ok(())
+    }
+}
+
+impl Range {
+    /// Resolve the block range, converting timestamps via the gateway
+    async fn blocks(&self, client: &Client) -> Result<(Option<u64>, Option<u64>)> {
+        let from_block = match self.from_time {
+            Some(from_time) => {
+                let block = client.block_for_timestamp(from_time).await?;
+                if client.timestamp_for_block(block).await? < from_time {
+                    Some(block + 1)
+                } else {
+                    Some(block)
+                }
+            }
+            None => self.from_block,
+        };
+        let to_block = match self.to_time {
+            Some(to_time) => Some(client.block_for_timestamp(to_time).await?),
+            None => self.to_block,
+        };
+        Ok((from_block, to_block))
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv::dotenv().ok();
+    let cli = Cli::parse();
+
+    if let Err(err) = run(cli).await {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let config = Config::builder()
+        .with_username(cli.username)
+        .with_password(cli.password)
+        .with_http_url(cli.http_url.parse()?)
+        .with_ws_url(cli.ws_url.parse()?)
+        .build()?;
+    let client = Client::connect(&config).await?;
+    let mut output = Output::new(cli.format, cli.output)?;
+
+    match cli.command {
+        Command::Pairs(range) => {
+            let (from_block, to_block) = range.blocks(&client).await?;
+            let stream = client
+                .get_pairs_created(range.pairs, from_block, to_block)
+                .await?;
+            output.write_all(stream).await
+        }
+        Command::Prices(range) => {
+            let (from_block, to_block) = range.blocks(&client).await?;
+            let stream = client.get_prices(range.pairs, from_block, to_block).await?;
+            output.write_all(stream).await
+        }
+        Command::Reserves(range) => {
+            let (from_block, to_block) = range.blocks(&client).await?;
+            let stream = client
+                .get_reserves(range.pairs, from_block, to_block)
+                .await?;
+            output.write_all(stream).await
+        }
+        Command::Height => {
+            println!("height: {}", client.get_height().await?);
+            println!("finalized: {}", client.finalized_height().await?);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::{collections::HashMap, sync::Mutex};
+
+/// The maximum number of mappings kept per direction before the cache is reset
+const MAX_ENTRIES: usize = 65_536;
+
+/// A cache of the block <-> timestamp mappings resolved by the gateway
+///
+/// Both directions are cached separately, since a timestamp usually lies between two blocks. Only
+/// mappings of finalized blocks are kept, since the block at the head of the chain might still be
+/// reorged, or not exist yet for a timestamp in the future. A timestamp is only mapped to a block
+/// for good once the block after it is finalized, too.
+#[derive(Default)]
+pub(crate) struct BlockTimeCache {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    timestamps: HashMap<u64, i64>,
+    blocks: HashMap<i64, u64>,
+    /// The highest finalized height seen so far
+    finalized: u64,
+}
+
+impl BlockTimeCache {
+    pub(crate) fn timestamp(&self, block: u64) -> Option<i64> {
+        self.inner
+            .lock()
+            .expect("the lock is never poisoned")
+            .timestamps
+            .get(&block)
+            .copied()
+    }
+
+    pub(crate) fn block(&self, timestamp: i64) -> Option<u64> {
+        self.inner
+            .lock()
+            .expect("the lock is never poisoned")
+            .blocks
+            .get(&timestamp)
+            .copied()
+    }
+
+    /// Whether `block` is known to be finalized, so mappings involving it can be cached
+    pub(crate) fn is_finalized(&self, block: u64) -> bool {
+        block
+            <= self
+                .inner
+                .lock()
+                .expect("the lock is never poisoned")
+                .finalized
+    }
+
+    /// Record the latest finalized height reported by the gateway
+    pub(crate) fn set_finalized(&self, height: u64) {
+        let mut inner = self.inner.lock().expect("the lock is never poisoned");
+        inner.finalized = inner.finalized.max(height);
+    }
+
+    pub(crate) fn insert_timestamp(&self, block: u64, timestamp: i64) {
+        let mut inner = self.inner.lock().expect("the lock is never poisoned");
+        if block > inner.finalized {
+            return;
+        }
+        if inner.timestamps.len() >= MAX_ENTRIES {
+            inner.timestamps.clear();
+        }
+        inner.timestamps.insert(block, timestamp);
+    }
+
+    pub(crate) fn insert_block(&self, timestamp: i64, block: u64) {
+        let mut inner = self.inner.lock().expect("the lock is never poisoned");
+        if block >= inner.finalized {
+            return;
+        }
+        if inner.blocks.len() >= MAX_ENTRIES {
+            inner.blocks.clear();
+        }
+        inner.blocks.insert(timestamp, block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_caches_finalized_blocks() {
+        let cache = BlockTimeCache::default();
+        cache.set_finalized(100);
+
+        cache.insert_block(1_000, 99);
+        cache.insert_block(2_000, 100);
+        cache.insert_timestamp(100, 1_000);
+        cache.insert_timestamp(101, 2_000);
+
+        assert_eq!(cache.block(1_000), Some(99));
+        assert_eq!(cache.block(2_000), None);
+        assert_eq!(cache.timestamp(100), Some(1_000));
+        assert_eq!(cache.timestamp(101), None);
+    }
+
+    #[test]
+    fn finalized_height_never_moves_back() {
+        let cache = BlockTimeCache::default();
+        cache.set_finalized(100);
+        cache.set_finalized(50);
+
+        assert!(cache.is_finalized(100));
+        assert!(!cache.is_finalized(101));
+    }
+}
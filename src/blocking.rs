@@ -0,0 +1,222 @@
+//! Synchronous wrappers around the async clients, for code without an async runtime
+//!
+//! The [`Client`] owns a tokio runtime, on which all queries are run. Point queries block until
+//! their response arrived, streams are exposed as an [`Iterator`] blocking on every item.
+
+use std::sync::Arc;
+
+use ethers::types::{H160, H256};
+use futures::{stream::BoxStream, StreamExt};
+use tokio::runtime::Runtime;
+
+use crate::{
+    config::Config,
+    types::{
+        DepthLevel, Finality, PairCreated, PairInfo, Price, PriceOptions, ReserveOptions, Reserves,
+        TransactionEvents,
+    },
+    Result,
+};
+
+/// A blocking Superchain client, see [`crate::Client`]
+pub struct Client {
+    inner: crate::Client,
+    runtime: Arc<Runtime>,
+}
+
+impl Client {
+    /// Connect to the gateway endpoints of `config`, authenticating with its credentials
+    pub fn connect(config: &Config) -> Result<Self> {
+        Self::connect_to(config, config.http_url.as_str(), config.ws_url.as_str())
+    }
+
+    /// Connect to the provided gateway endpoints, authenticating with `config`
+    pub fn connect_to(config: &Config, http_url: &str, ws_url: &str) -> Result<Self> {
+        // the WebSocket is read by a background task, which has to run between calls as well
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        let inner = runtime.block_on(crate::Client::connect_to(config, http_url, ws_url))?;
+
+        Ok(Self {
+            inner,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// The underlying async client
+    pub fn inner(&self) -> &crate::Client {
+        &self.inner
+    }
+
+    /// Get the uniswap v2 pair created events, see [`crate::WsClient::get_pairs_created`]
+    pub fn get_pairs_created(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<Iter<PairCreated>> {
+        let pairs_filter = pairs_filter.into_iter().collect::<Vec<_>>();
+        let stream = self.runtime.block_on(self.inner.get_pairs_created(
+            pairs_filter,
+            from_block,
+            to_block_inc,
+        ))?;
+        Ok(self.iter(stream.boxed()))
+    }
+
+    /// Get the uniswap v2 price quotes, see [`crate::WsClient::get_prices`]
+    pub fn get_prices(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<Iter<Price>> {
+        self.get_prices_with_options(
+            pairs_filter,
+            from_block,
+            to_block_inc,
+            PriceOptions::default(),
+        )
+    }
+
+    /// Get the uniswap v2 price quotes, see [`crate::WsClient::get_prices_with_options`]
+    pub fn get_prices_with_options(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+        options: PriceOptions,
+    ) -> Result<Iter<Price>> {
+        let pairs_filter = pairs_filter.into_iter().collect::<Vec<_>>();
+        let stream = self.runtime.block_on(self.inner.get_prices_with_options(
+            pairs_filter,
+            from_block,
+            to_block_inc,
+            options,
+        ))?;
+        Ok(self.iter(stream.boxed()))
+    }
+
+    /// Get the uniswap v2 reserves, see [`crate::WsClient::get_reserves`]
+    pub fn get_reserves(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<Iter<Reserves>> {
+        self.get_reserves_with_options(
+            pairs_filter,
+            from_block,
+            to_block_inc,
+            ReserveOptions::default(),
+        )
+    }
+
+    /// Get the uniswap v2 reserves, see [`crate::WsClient::get_reserves_with_options`]
+    pub fn get_reserves_with_options(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+        options: ReserveOptions,
+    ) -> Result<Iter<Reserves>> {
+        let pairs_filter = pairs_filter.into_iter().collect::<Vec<_>>();
+        let stream = self.runtime.block_on(self.inner.get_reserves_with_options(
+            pairs_filter,
+            from_block,
+            to_block_inc,
+            options,
+        ))?;
+        Ok(self.iter(stream.boxed()))
+    }
+
+    /// Get the uniswap v2 pair created event of the provided `pair`
+    pub fn get_pair_created(&self, pair: H160) -> Result<Option<PairCreated>> {
+        self.runtime.block_on(self.inner.get_pair_created(pair))
+    }
+
+    /// Get the uniswap v2 pair created event of the provided `pair`, see
+    /// [`crate::HttpClient::get_pair_info`]
+    pub fn get_pair_info(&self, pair: H160) -> Result<Option<PairInfo>> {
+        self.runtime.block_on(self.inner.get_pair_info(pair))
+    }
+
+    /// Get the uniswap v2 pair created events of all pairs containing `token`
+    pub fn get_pairs_for_token(&self, token: H160) -> Result<Vec<PairCreated>> {
+        self.runtime.block_on(self.inner.get_pairs_for_token(token))
+    }
+
+    /// Get the most recent uniswap v2 price quote of the provided `pair`
+    pub fn get_latest_price(&self, pair: H160) -> Result<Option<Price>> {
+        self.runtime.block_on(self.inner.get_latest_price(pair))
+    }
+
+    /// Get all events emitted by the provided transaction, see
+    /// [`crate::HttpClient::get_events_for_tx`]
+    pub fn get_events_for_tx(&self, transaction_hash: H256) -> Result<TransactionEvents> {
+        self.runtime
+            .block_on(self.inner.get_events_for_tx(transaction_hash))
+    }
+
+    /// Get the liquidity depth of the provided `pair`, see [`crate::HttpClient::get_depth`]
+    pub fn get_depth(
+        &self,
+        pair: H160,
+        at_block: Option<u64>,
+        levels: impl IntoIterator<Item = u32>,
+    ) -> Result<Vec<DepthLevel>> {
+        self.runtime
+            .block_on(self.inner.get_depth(pair, at_block, levels))
+    }
+
+    /// Get the finality status of the provided `block`
+    pub fn get_finality(&self, block: u64) -> Result<Option<Finality>> {
+        self.runtime.block_on(self.inner.get_finality(block))
+    }
+
+    /// Get the current height of the indexed chain
+    pub fn get_height(&self) -> Result<u64> {
+        self.runtime.block_on(self.inner.get_height())
+    }
+
+    /// Get the height of the latest finalized block
+    pub fn finalized_height(&self) -> Result<u64> {
+        self.runtime.block_on(self.inner.finalized_height())
+    }
+
+    /// Get the latest block at or before the provided unix `timestamp`
+    pub fn block_for_timestamp(&self, timestamp: i64) -> Result<u64> {
+        self.runtime
+            .block_on(self.inner.block_for_timestamp(timestamp))
+    }
+
+    /// Get the unix timestamp of the provided `block`
+    pub fn timestamp_for_block(&self, block: u64) -> Result<i64> {
+        self.runtime.block_on(self.inner.timestamp_for_block(block))
+    }
+
+    fn iter<T>(&self, stream: BoxStream<'static, Result<T>>) -> Iter<T> {
+        Iter {
+            stream,
+            runtime: Arc::clone(&self.runtime),
+        }
+    }
+}
+
+/// A blocking iterator over the items of a stream
+///
+/// Live streams never end, so [`Iterator::next`] blocks until the next item arrived.
+pub struct Iter<T> {
+    stream: BoxStream<'static, Result<T>>,
+    runtime: Arc<Runtime>,
+}
+
+impl<T> Iterator for Iter<T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.stream.next())
+    }
+}
@@ -0,0 +1,365 @@
+//! A persistent cache of the historical rows of a provider, backed by SQLite
+//!
+//! Only rows up to the finalized height are cached, since later rows might still be reorged. Rows
+//! without a block, e.g. the reserves sent by the gateway, can't be served by block range, so
+//! streams containing them bypass the cache.
+
+use std::{path::Path, sync::Mutex};
+
+use ethers::types::H160;
+use futures::{future::BoxFuture, stream::BoxStream, FutureExt, StreamExt, TryStreamExt};
+use rusqlite::{params, Connection};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    types::{PairCreated, PairInfo, Positioned, Price, Reserves},
+    Result, SuperchainProvider,
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS rows (
+        kind TEXT NOT NULL,
+        pair TEXT NOT NULL,
+        block_number INTEGER NOT NULL,
+        transaction_index INTEGER,
+        log_index INTEGER,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS rows_block ON rows (kind, pair, block_number);
+    CREATE TABLE IF NOT EXISTS ranges (
+        kind TEXT NOT NULL,
+        pair TEXT NOT NULL,
+        from_block INTEGER NOT NULL,
+        to_block INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS ranges_pair ON ranges (kind, pair);
+    CREATE TABLE IF NOT EXISTS pairs (
+        pair TEXT PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+";
+
+/// A [`SuperchainProvider`] serving historical rows from a local cache
+///
+/// Price and reserve queries are served from the cache for all blocks up to the finalized height.
+/// Only the block ranges missing in the cache are fetched from the wrapped provider and stored,
+/// newer rows are always streamed from the wrapped provider. `PairCreated` events are cached as
+/// well, all other queries are passed through.
+pub struct CachedClient<P> {
+    inner: P,
+    db: Mutex<Connection>,
+}
+
+impl<P: SuperchainProvider> CachedClient<P> {
+    /// Create a new [`CachedClient`], storing the cache in the SQLite database at `path`
+    pub fn open(inner: P, path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_connection(inner, Connection::open(path)?)
+    }
+
+    /// Create a new [`CachedClient`], keeping the cache in memory only
+    pub fn in_memory(inner: P) -> Result<Self> {
+        Self::with_connection(inner, Connection::open_in_memory()?)
+    }
+
+    fn with_connection(inner: P, db: Connection) -> Result<Self> {
+        db.execute_batch(SCHEMA)?;
+        Ok(Self {
+            inner,
+            db: Mutex::new(db),
+        })
+    }
+
+    /// The wrapped provider
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// The block ranges within `from_block..=to_block` not covered by the cache
+    fn missing_ranges(
+        &self,
+        kind: &str,
+        pair: H160,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<(u64, u64)>> {
+        let db = self.db.lock().expect("the lock is never poisoned");
+        let mut statement = db.prepare_cached(
+            "SELECT from_block, to_block FROM ranges
+             WHERE kind = ?1 AND pair = ?2 AND to_block >= ?3 AND from_block <= ?4
+             ORDER BY from_block",
+        )?;
+        let covered = statement
+            .query_map(
+                params![kind, format!("{:x}", pair), from_block, to_block],
+                |row| Ok((row.get::<_, u64>(0)?, row.get::<_, u64>(1)?)),
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut missing = Vec::new();
+        let mut next = from_block;
+        for (from, to) in covered {
+            if from > next {
+                missing.push((next, from - 1));
+            }
+            next = next.max(to.saturating_add(1));
+        }
+        if next <= to_block {
+            missing.push((next, to_block));
+        }
+
+        Ok(missing)
+    }
+
+    fn store<T: Positioned + Serialize>(
+        &self,
+        kind: &str,
+        pair: H160,
+        range: (u64, u64),
+        rows: &[T],
+    ) -> Result<()> {
+        let pair = format!("{:x}", pair);
+        let mut db = self.db.lock().expect("the lock is never poisoned");
+        let transaction = db.transaction()?;
+        {
+            let mut statement = transaction.prepare_cached(
+                "INSERT INTO rows
+                 (kind, pair, block_number, transaction_index, log_index, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for row in rows {
+                let position = row.position();
+                let block_number = row
+                    .block_number()
+                    .expect("only rows with a block are stored");
+                statement.execute(params![
+                    kind,
+                    pair,
+                    block_number,
+                    position.map(|position| position.transaction_index),
+                    position.and_then(|position| position.log_index),
+                    serde_json::to_string(row)?,
+                ])?;
+            }
+        }
+        transaction.execute(
+            "INSERT INTO ranges (kind, pair, from_block, to_block) VALUES (?1, ?2, ?3, ?4)",
+            params![kind, pair, range.0, range.1],
+        )?;
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    fn load<T: DeserializeOwned>(
+        &self,
+        kind: &str,
+        pair: H160,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<T>> {
+        let db = self.db.lock().expect("the lock is never poisoned");
+        let mut statement = db.prepare_cached(
+            "SELECT data FROM rows
+             WHERE kind = ?1 AND pair = ?2 AND block_number BETWEEN ?3 AND ?4
+             ORDER BY block_number, transaction_index, log_index, rowid",
+        )?;
+        let rows = statement
+            .query_map(
+                params![kind, format!("{:x}", pair), from_block, to_block],
+                |row| row.get::<_, String>(0),
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        rows.iter()
+            .map(|data| Ok(serde_json::from_str(data)?))
+            .collect()
+    }
+
+    /// Serve the finalized part of the range from the cache, filling it up with `fetch` first
+    async fn cached<'a, T, F>(
+        &'a self,
+        kind: &str,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+        fetch: F,
+    ) -> Result<BoxStream<'a, Result<T>>>
+    where
+        T: Positioned + Serialize + DeserializeOwned + Send + 'a,
+        F: Fn(u64, Option<u64>) -> BoxFuture<'a, Result<BoxStream<'a, Result<T>>>>,
+    {
+        let finalized = self.inner.finalized_height().await?;
+        let cached_to = match to_block_inc {
+            Some(to_block) => to_block.min(finalized),
+            None => finalized,
+        };
+        if from_block > cached_to {
+            return fetch(from_block, to_block_inc).await;
+        }
+
+        for range in self.missing_ranges(kind, pair, from_block, cached_to)? {
+            let rows = fetch(range.0, Some(range.1))
+                .await?
+                .try_collect::<Vec<_>>()
+                .await?;
+            if rows.iter().any(|row| row.block_number().is_none()) {
+                return fetch(from_block, to_block_inc).await;
+            }
+            self.store(kind, pair, range, &rows)?;
+        }
+
+        let cached = futures::stream::iter(
+            self.load(kind, pair, from_block, cached_to)?
+                .into_iter()
+                .map(Ok),
+        );
+        if matches!(to_block_inc, Some(to_block) if to_block <= cached_to) {
+            return Ok(cached.boxed());
+        }
+
+        let live = fetch(cached_to + 1, to_block_inc).await?;
+        Ok(cached.chain(live).boxed())
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: SuperchainProvider> SuperchainProvider for CachedClient<P> {
+    async fn get_pair_created(&self, pair: H160) -> Result<Option<PairCreated>> {
+        let cached = {
+            let db = self.db.lock().expect("the lock is never poisoned");
+            let mut statement = db.prepare_cached("SELECT data FROM pairs WHERE pair = ?1")?;
+            let mut rows = statement.query(params![format!("{:x}", pair)])?;
+            match rows.next()? {
+                Some(row) => Some(row.get::<_, String>(0)?),
+                None => None,
+            }
+        };
+        if let Some(data) = cached {
+            return Ok(Some(serde_json::from_str(&data)?));
+        }
+
+        let created = self.inner.get_pair_created(pair).await?;
+        if let Some(created) = &created {
+            let db = self.db.lock().expect("the lock is never poisoned");
+            db.execute(
+                "INSERT OR REPLACE INTO pairs (pair, data) VALUES (?1, ?2)",
+                params![format!("{:x}", pair), serde_json::to_string(created)?],
+            )?;
+        }
+
+        Ok(created)
+    }
+
+    async fn get_pair_info(&self, pair: H160) -> Result<Option<PairInfo>> {
+        self.inner.get_pair_info(pair).await
+    }
+
+    async fn get_prices(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Price>>> {
+        self.cached("price", pair, from_block, to_block_inc, |from, to| {
+            self.inner.get_prices(pair, from, to).boxed()
+        })
+        .await
+    }
+
+    async fn get_reserves(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Reserves>>> {
+        self.cached("reserves", pair, from_block, to_block_inc, |from, to| {
+            self.inner.get_reserves(pair, from, to).boxed()
+        })
+        .await
+    }
+
+    async fn get_latest_price(&self, pair: H160) -> Result<Option<Price>> {
+        self.inner.get_latest_price(pair).await
+    }
+
+    async fn get_height(&self) -> Result<u64> {
+        self.inner.get_height().await
+    }
+
+    async fn finalized_height(&self) -> Result<u64> {
+        self.inner.finalized_height().await
+    }
+
+    async fn block_for_timestamp(&self, timestamp: i64) -> Result<u64> {
+        self.inner.block_for_timestamp(timestamp).await
+    }
+
+    async fn timestamp_for_block(&self, block: u64) -> Result<i64> {
+        self.inner.timestamp_for_block(block).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        types::fixtures::{gateway_price, gateway_reserves},
+        MockClient,
+    };
+
+    const PAIR: u64 = 1;
+
+    #[tokio::test]
+    async fn round_trips_gateway_prices() {
+        let pair = H160::from_low_u64_be(PAIR);
+        let prices = [
+            gateway_price(PAIR, 10, 0),
+            gateway_price(PAIR, 10, 0),
+            gateway_price(PAIR, 11, 3),
+            gateway_price(PAIR, 12, 1),
+        ];
+        let client = CachedClient::in_memory(MockClient::new().with_prices(prices)).unwrap();
+
+        let fetched: Vec<Price> = client
+            .get_prices(pair, 10, Some(11))
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(fetched.len(), 3);
+
+        assert!(client
+            .missing_ranges("price", pair, 10, 11)
+            .unwrap()
+            .is_empty());
+        let cached: Vec<Price> = client.load("price", pair, 10, 11).unwrap();
+        let positions: Vec<_> = cached
+            .iter()
+            .map(|price| (price.block_number, price.transaction_index, price.log_index))
+            .collect();
+        assert_eq!(positions, [(10, 0, None), (10, 0, None), (11, 3, None)]);
+    }
+
+    #[tokio::test]
+    async fn passes_through_gateway_reserves() {
+        let pair = H160::from_low_u64_be(PAIR);
+        let mock = MockClient::new()
+            .with_prices([gateway_price(PAIR, 12, 0)])
+            .with_reserves([gateway_reserves(1, 2), gateway_reserves(3, 4)]);
+        let client = CachedClient::in_memory(mock).unwrap();
+
+        let fetched: Vec<Reserves> = client
+            .get_reserves(pair, 10, Some(11))
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(fetched.len(), 2);
+        assert_eq!(
+            client.missing_ranges("reserves", pair, 10, 11).unwrap(),
+            [(10, 11)]
+        );
+    }
+}
@@ -0,0 +1,452 @@
+//! Record the responses of a provider to a cassette file and serve them back deterministically
+//!
+//! A [`Recorder`] wraps any [`SuperchainProvider`] and records all responses, including their
+//! timing. A [`Player`] loads the cassette and serves the recorded responses for the same queries,
+//! which makes integration tests reproducible and allows demos without network access.
+
+use std::{collections::VecDeque, fs::File, path::Path, sync::Mutex, time::Duration};
+
+use ethers::types::H160;
+use futures::{stream::BoxStream, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::value::RawValue;
+use tokio::time::Instant;
+
+use crate::{
+    adapters::Speed,
+    types::{PairCreated, PairInfo, Price, Reserves},
+    Error, Result, SuperchainProvider,
+};
+
+/// A query of the [`SuperchainProvider`] trait, identifying recorded responses
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "operation", rename_all = "camelCase")]
+enum Operation {
+    GetPairCreated {
+        pair: H160,
+    },
+    GetPairInfo {
+        pair: H160,
+    },
+    #[serde(rename_all = "camelCase")]
+    GetPrices {
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    },
+    #[serde(rename_all = "camelCase")]
+    GetReserves {
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    },
+    GetLatestPrice {
+        pair: H160,
+    },
+    GetHeight,
+    FinalizedHeight,
+    BlockForTimestamp {
+        timestamp: i64,
+    },
+    TimestampForBlock {
+        block: u64,
+    },
+}
+
+/// A recorded response, offsets are in milliseconds since the query was made
+///
+/// This is externally tagged, since raw values can't be buffered by tagged or flattened enums.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum Response {
+    Value {
+        offset_ms: u64,
+        value: Box<RawValue>,
+    },
+    Stream {
+        frames: Vec<Frame>,
+    },
+    Error {
+        offset_ms: u64,
+        message: String,
+    },
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct Frame {
+    offset_ms: u64,
+    item: std::result::Result<Box<RawValue>, String>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct Interaction {
+    operation: Operation,
+    response: Response,
+}
+
+fn offset_ms(start: Instant) -> u64 {
+    start.elapsed().as_millis() as u64
+}
+
+/// A [`SuperchainProvider`] recording all responses of the wrapped provider
+///
+/// Streams are recorded as they are consumed, so [`Recorder::save`] should be called once all
+/// streams of interest ended.
+pub struct Recorder<P> {
+    inner: P,
+    interactions: Mutex<Vec<Interaction>>,
+}
+
+impl<P: SuperchainProvider> Recorder<P> {
+    /// Create a new [`Recorder`] of the responses of `inner`
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            interactions: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Write all responses recorded so far to the cassette file at `path`
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let interactions = self
+            .interactions
+            .lock()
+            .expect("the lock is never poisoned");
+        serde_json::to_writer_pretty(File::create(path)?, &*interactions)?;
+        Ok(())
+    }
+
+    fn push(&self, operation: Operation, response: Response) -> usize {
+        let mut interactions = self
+            .interactions
+            .lock()
+            .expect("the lock is never poisoned");
+        interactions.push(Interaction {
+            operation,
+            response,
+        });
+        interactions.len() - 1
+    }
+
+    fn record<T: Serialize>(
+        &self,
+        operation: Operation,
+        start: Instant,
+        response: Result<T>,
+    ) -> Result<T> {
+        let offset_ms = offset_ms(start);
+        let recorded = match &response {
+            Ok(value) => Response::Value {
+                offset_ms,
+                value: serde_json::value::to_raw_value(value)?,
+            },
+            Err(err) => Response::Error {
+                offset_ms,
+                message: err.to_string(),
+            },
+        };
+        self.push(operation, recorded);
+        response
+    }
+
+    fn record_stream<'a, T>(
+        &'a self,
+        operation: Operation,
+        start: Instant,
+        response: Result<BoxStream<'a, Result<T>>>,
+    ) -> Result<BoxStream<'a, Result<T>>>
+    where
+        T: Serialize + Send + 'a,
+    {
+        let stream = match response {
+            Ok(stream) => stream,
+            Err(err) => {
+                let message = err.to_string();
+                let offset_ms = offset_ms(start);
+                self.push(operation, Response::Error { offset_ms, message });
+                return Err(err);
+            }
+        };
+
+        let index = self.push(operation, Response::Stream { frames: Vec::new() });
+        let stream = stream.inspect(move |item| {
+            let frame = Frame {
+                offset_ms: offset_ms(start),
+                item: match item {
+                    Ok(item) => {
+                        serde_json::value::to_raw_value(item).map_err(|err| err.to_string())
+                    }
+                    Err(err) => Err(err.to_string()),
+                },
+            };
+            let mut interactions = self
+                .interactions
+                .lock()
+                .expect("the lock is never poisoned");
+            if let Response::Stream { frames } = &mut interactions[index].response {
+                frames.push(frame);
+            }
+        });
+
+        Ok(stream.boxed())
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: SuperchainProvider> SuperchainProvider for Recorder<P> {
+    async fn get_pair_created(&self, pair: H160) -> Result<Option<PairCreated>> {
+        let start = Instant::now();
+        let response = self.inner.get_pair_created(pair).await;
+        self.record(Operation::GetPairCreated { pair }, start, response)
+    }
+
+    async fn get_pair_info(&self, pair: H160) -> Result<Option<PairInfo>> {
+        let start = Instant::now();
+        let response = self.inner.get_pair_info(pair).await;
+        self.record(Operation::GetPairInfo { pair }, start, response)
+    }
+
+    async fn get_prices(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Price>>> {
+        let start = Instant::now();
+        let response = self.inner.get_prices(pair, from_block, to_block_inc).await;
+        let operation = Operation::GetPrices {
+            pair,
+            from_block,
+            to_block_inc,
+        };
+        self.record_stream(operation, start, response)
+    }
+
+    async fn get_reserves(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Reserves>>> {
+        let start = Instant::now();
+        let response = self
+            .inner
+            .get_reserves(pair, from_block, to_block_inc)
+            .await;
+        let operation = Operation::GetReserves {
+            pair,
+            from_block,
+            to_block_inc,
+        };
+        self.record_stream(operation, start, response)
+    }
+
+    async fn get_latest_price(&self, pair: H160) -> Result<Option<Price>> {
+        let start = Instant::now();
+        let response = self.inner.get_latest_price(pair).await;
+        self.record(Operation::GetLatestPrice { pair }, start, response)
+    }
+
+    async fn get_height(&self) -> Result<u64> {
+        let start = Instant::now();
+        let response = self.inner.get_height().await;
+        self.record(Operation::GetHeight, start, response)
+    }
+
+    async fn finalized_height(&self) -> Result<u64> {
+        let start = Instant::now();
+        let response = self.inner.finalized_height().await;
+        self.record(Operation::FinalizedHeight, start, response)
+    }
+
+    async fn block_for_timestamp(&self, timestamp: i64) -> Result<u64> {
+        let start = Instant::now();
+        let response = self.inner.block_for_timestamp(timestamp).await;
+        self.record(Operation::BlockForTimestamp { timestamp }, start, response)
+    }
+
+    async fn timestamp_for_block(&self, block: u64) -> Result<i64> {
+        let start = Instant::now();
+        let response = self.inner.timestamp_for_block(block).await;
+        self.record(Operation::TimestampForBlock { block }, start, response)
+    }
+}
+
+/// A [`SuperchainProvider`] serving the responses of a cassette recorded by a [`Recorder`]
+///
+/// Repeated queries are served the recorded responses in the order they were recorded. Queries
+/// without a remaining recorded response fail. Recorded errors are returned as
+/// [`Error::Custom`].
+pub struct Player {
+    interactions: Mutex<VecDeque<Interaction>>,
+    speed: Speed,
+}
+
+impl Player {
+    /// Load the cassette file at `path`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let interactions = serde_json::from_reader(std::io::BufReader::new(File::open(path)?))?;
+        Ok(Self {
+            interactions: Mutex::new(interactions),
+            speed: Speed::Unbounded,
+        })
+    }
+
+    /// Set the speed at which responses are served, defaults to [`Speed::Unbounded`]
+    ///
+    /// With [`Speed::Scaled`], the recorded timing of all responses is reproduced. Fails with
+    /// [`Error::InvalidConfig`] unless its factor is positive and finite.
+    pub fn with_speed(mut self, speed: Speed) -> Result<Self> {
+        self.speed = speed.validate()?;
+        Ok(self)
+    }
+
+    fn take(&self, operation: &Operation) -> Result<Response> {
+        let mut interactions = self
+            .interactions
+            .lock()
+            .expect("the lock is never poisoned");
+        let index = interactions
+            .iter()
+            .position(|interaction| interaction.operation == *operation)
+            .ok_or_else(|| Error::Custom(format!("no recorded response for {:?}", operation)))?;
+        let interaction = interactions.remove(index).expect("the index exists");
+        Ok(interaction.response)
+    }
+
+    async fn value<T: DeserializeOwned>(&self, operation: Operation) -> Result<T> {
+        let start = Instant::now();
+        match self.take(&operation)? {
+            Response::Value { offset_ms, value } => {
+                wait(self.speed, start, offset_ms).await;
+                Ok(serde_json::from_str(value.get())?)
+            }
+            Response::Error { offset_ms, message } => {
+                wait(self.speed, start, offset_ms).await;
+                Err(Error::Custom(message))
+            }
+            Response::Stream { .. } => Err(Error::Custom(format!(
+                "recorded a stream for {:?}",
+                operation
+            ))),
+        }
+    }
+
+    async fn stream<T>(&self, operation: Operation) -> Result<BoxStream<'_, Result<T>>>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        let start = Instant::now();
+        let frames = match self.take(&operation)? {
+            Response::Stream { frames } => frames,
+            Response::Error { offset_ms, message } => {
+                wait(self.speed, start, offset_ms).await;
+                return Err(Error::Custom(message));
+            }
+            Response::Value { .. } => {
+                return Err(Error::Custom(format!(
+                    "recorded a value for {:?}",
+                    operation
+                )))
+            }
+        };
+
+        let speed = self.speed;
+        let stream = futures::stream::iter(frames).then(move |frame| async move {
+            wait(speed, start, frame.offset_ms).await;
+            match frame.item {
+                Ok(item) => Ok(serde_json::from_str(item.get())?),
+                Err(message) => Err(Error::Custom(message)),
+            }
+        });
+
+        Ok(stream.boxed())
+    }
+}
+
+/// Wait until `offset_ms` after `start`, scaled by `speed`
+async fn wait(speed: Speed, start: Instant, offset_ms: u64) {
+    speed
+        .wait(start, Duration::from_millis(offset_ms).as_secs_f64())
+        .await;
+}
+
+#[async_trait::async_trait]
+impl SuperchainProvider for Player {
+    async fn get_pair_created(&self, pair: H160) -> Result<Option<PairCreated>> {
+        self.value(Operation::GetPairCreated { pair }).await
+    }
+
+    async fn get_pair_info(&self, pair: H160) -> Result<Option<PairInfo>> {
+        self.value(Operation::GetPairInfo { pair }).await
+    }
+
+    async fn get_prices(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Price>>> {
+        self.stream(Operation::GetPrices {
+            pair,
+            from_block,
+            to_block_inc,
+        })
+        .await
+    }
+
+    async fn get_reserves(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Reserves>>> {
+        self.stream(Operation::GetReserves {
+            pair,
+            from_block,
+            to_block_inc,
+        })
+        .await
+    }
+
+    async fn get_latest_price(&self, pair: H160) -> Result<Option<Price>> {
+        self.value(Operation::GetLatestPrice { pair }).await
+    }
+
+    async fn get_height(&self) -> Result<u64> {
+        self.value(Operation::GetHeight).await
+    }
+
+    async fn finalized_height(&self) -> Result<u64> {
+        self.value(Operation::FinalizedHeight).await
+    }
+
+    async fn block_for_timestamp(&self, timestamp: i64) -> Result<u64> {
+        self.value(Operation::BlockForTimestamp { timestamp }).await
+    }
+
+    async fn timestamp_for_block(&self, block: u64) -> Result<i64> {
+        self.value(Operation::TimestampForBlock { block }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_invalid_speeds() {
+        let path = std::env::temp_dir().join(format!("cassette-{}.json", std::process::id()));
+        std::fs::write(&path, "[]").unwrap();
+
+        for factor in [0.0, -2.0, f64::NAN] {
+            let player = Player::load(&path).unwrap();
+            let err = player.with_speed(Speed::Scaled(factor)).err().unwrap();
+            assert!(matches!(err, Error::InvalidConfig(_)));
+        }
+        let player = Player::load(&path).unwrap();
+        assert!(player.with_speed(Speed::Scaled(2.0)).is_ok());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
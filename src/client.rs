@@ -0,0 +1,238 @@
+use ethers::types::{H160, H256};
+use futures::{stream::BoxStream, Stream, StreamExt};
+
+use crate::{
+    config::Config,
+    types::{
+        DepthLevel, Finality, PairCreated, PairInfo, Price, PriceOptions, ReserveOptions, Reserves,
+        TransactionEvents,
+    },
+    HttpClient, Result, SuperchainProvider, WsClient,
+};
+
+/// A Superchain client, picking the best suited transport per query
+///
+/// Streams are requested via WebSocket, point queries via HTTP. The underlying clients are
+/// available with [`Client::http`] and [`Client::ws`] for everything else.
+pub struct Client {
+    http: HttpClient,
+    ws: WsClient,
+}
+
+impl Client {
+    /// Connect to the gateway endpoints of `config`, authenticating with its credentials
+    pub async fn connect(config: &Config) -> Result<Self> {
+        Self::connect_to(config, config.http_url.as_str(), config.ws_url.as_str()).await
+    }
+
+    /// Connect to the provided gateway endpoints, authenticating with `config`
+    pub async fn connect_to(config: &Config, http_url: &str, ws_url: &str) -> Result<Self> {
+        let mut config = config.clone();
+        config.http_url = http_url.parse()?;
+        config.ws_url = ws_url.parse()?;
+
+        let http = HttpClient::from_config(&config)?;
+        let ws = WsClient::connect_with_config(&config).await?;
+        Ok(Self::from_clients(http, ws))
+    }
+
+    /// Create a new [`Client`] from already configured clients
+    pub fn from_clients(http: HttpClient, ws: WsClient) -> Self {
+        Self { http, ws }
+    }
+
+    /// Switch to new `credentials` for HTTP requests, see [`HttpClient::set_credentials`]
+    ///
+    /// The WebSocket connection was authenticated when connecting and stays open.
+    pub fn set_credentials(&self, credentials: crate::auth::Credentials) {
+        self.http.set_credentials(credentials);
+    }
+
+    /// The underlying HTTP client
+    pub fn http(&self) -> &HttpClient {
+        &self.http
+    }
+
+    /// The underlying WebSocket client
+    pub fn ws(&self) -> &WsClient {
+        &self.ws
+    }
+
+    /// Get the uniswap v2 pair created events, see [`WsClient::get_pairs_created`]
+    pub async fn get_pairs_created(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<PairCreated>> + Send> {
+        self.ws
+            .get_pairs_created(pairs_filter, from_block, to_block_inc)
+            .await
+    }
+
+    /// Get the uniswap v2 price quotes, see [`WsClient::get_prices`]
+    pub async fn get_prices(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<Price>> + Send> {
+        self.ws
+            .get_prices(pairs_filter, from_block, to_block_inc)
+            .await
+    }
+
+    /// Get the uniswap v2 price quotes, see [`WsClient::get_prices_with_options`]
+    pub async fn get_prices_with_options(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+        options: PriceOptions,
+    ) -> Result<impl Stream<Item = Result<Price>> + Send> {
+        self.ws
+            .get_prices_with_options(pairs_filter, from_block, to_block_inc, options)
+            .await
+    }
+
+    /// Get the uniswap v2 reserves, see [`WsClient::get_reserves`]
+    pub async fn get_reserves(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<Reserves>> + Send> {
+        self.ws
+            .get_reserves(pairs_filter, from_block, to_block_inc)
+            .await
+    }
+
+    /// Get the uniswap v2 reserves, see [`WsClient::get_reserves_with_options`]
+    pub async fn get_reserves_with_options(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+        options: ReserveOptions,
+    ) -> Result<impl Stream<Item = Result<Reserves>> + Send> {
+        self.ws
+            .get_reserves_with_options(pairs_filter, from_block, to_block_inc, options)
+            .await
+    }
+
+    /// Get the uniswap v2 pair created event of the provided `pair`
+    pub async fn get_pair_created(&self, pair: H160) -> Result<Option<PairCreated>> {
+        self.http.get_pair_created(pair).await
+    }
+
+    /// Get the uniswap v2 pair created event of the provided `pair`, see
+    /// [`HttpClient::get_pair_info`]
+    pub async fn get_pair_info(&self, pair: H160) -> Result<Option<PairInfo>> {
+        self.http.get_pair_info(pair).await
+    }
+
+    /// Get the uniswap v2 pair created events of all pairs containing `token`
+    pub async fn get_pairs_for_token(&self, token: H160) -> Result<Vec<PairCreated>> {
+        self.http
+            .get_pairs_for_token(token)
+            .await?
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect()
+    }
+
+    /// Get the most recent uniswap v2 price quote of the provided `pair`
+    pub async fn get_latest_price(&self, pair: H160) -> Result<Option<Price>> {
+        self.http.get_latest_price(pair).await
+    }
+
+    /// Get all events emitted by the provided transaction, see [`HttpClient::get_events_for_tx`]
+    pub async fn get_events_for_tx(&self, transaction_hash: H256) -> Result<TransactionEvents> {
+        self.http.get_events_for_tx(transaction_hash).await
+    }
+
+    /// Get the liquidity depth of the provided `pair`, see [`HttpClient::get_depth`]
+    pub async fn get_depth(
+        &self,
+        pair: H160,
+        at_block: Option<u64>,
+        levels: impl IntoIterator<Item = u32>,
+    ) -> Result<Vec<DepthLevel>> {
+        self.http.get_depth(pair, at_block, levels).await
+    }
+
+    /// Get the finality status of the provided `block`
+    pub async fn get_finality(&self, block: u64) -> Result<Option<Finality>> {
+        self.http.get_finality(block).await
+    }
+
+    /// Get the current height of the indexed chain
+    pub async fn get_height(&self) -> Result<u64> {
+        self.http.get_height().await
+    }
+
+    /// Get the height of the latest finalized block
+    pub async fn finalized_height(&self) -> Result<u64> {
+        self.http.finalized_height().await
+    }
+
+    /// Get the latest block at or before the provided unix `timestamp`
+    pub async fn block_for_timestamp(&self, timestamp: i64) -> Result<u64> {
+        self.http.block_for_timestamp(timestamp).await
+    }
+
+    /// Get the unix timestamp of the provided `block`
+    pub async fn timestamp_for_block(&self, block: u64) -> Result<i64> {
+        self.http.timestamp_for_block(block).await
+    }
+}
+
+#[async_trait::async_trait]
+impl SuperchainProvider for Client {
+    async fn get_pair_created(&self, pair: H160) -> Result<Option<PairCreated>> {
+        Client::get_pair_created(self, pair).await
+    }
+
+    async fn get_pair_info(&self, pair: H160) -> Result<Option<PairInfo>> {
+        Client::get_pair_info(self, pair).await
+    }
+
+    async fn get_prices(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Price>>> {
+        SuperchainProvider::get_prices(&self.ws, pair, from_block, to_block_inc).await
+    }
+
+    async fn get_reserves(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Reserves>>> {
+        SuperchainProvider::get_reserves(&self.ws, pair, from_block, to_block_inc).await
+    }
+
+    async fn get_latest_price(&self, pair: H160) -> Result<Option<Price>> {
+        Client::get_latest_price(self, pair).await
+    }
+
+    async fn get_height(&self) -> Result<u64> {
+        Client::get_height(self).await
+    }
+
+    async fn finalized_height(&self) -> Result<u64> {
+        Client::finalized_height(self).await
+    }
+
+    async fn block_for_timestamp(&self, timestamp: i64) -> Result<u64> {
+        Client::block_for_timestamp(self, timestamp).await
+    }
+
+    async fn timestamp_for_block(&self, block: u64) -> Result<i64> {
+        Client::timestamp_for_block(self, block).await
+    }
+}
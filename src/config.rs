@@ -1,22 +1,707 @@
-use std::env;
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use dotenv::dotenv;
+use url::Url;
 
+#[cfg(feature = "keyring")]
+use crate::auth::StaticToken;
+use crate::{
+    auth::{Credentials, SecretFile, TokenCache, TokenProvider},
+    Error, Result,
+};
+
+/// The default HTTP endpoint of the Superchain gateway
+pub const DEFAULT_HTTP_URL: &str = "https://beta.superchain.app/";
+/// The default WebSocket endpoint of the Superchain gateway
+pub const DEFAULT_WS_URL: &str = "wss://beta.superchain.app/websocket";
+/// The service name credentials are stored under in the OS keyring, by default
+#[cfg(feature = "keyring")]
+pub const KEYRING_SERVICE: &str = "superchain";
+/// The keyring user a token is stored under, next to the passwords of the users
+#[cfg(feature = "keyring")]
+const KEYRING_TOKEN_USER: &str = "token";
+/// The config file read by [`Config::profile`], unless `SC_CONFIG` is set
+pub const DEFAULT_CONFIG_FILE: &str = "superchain.toml";
+
+/// A deployment of the Superchain gateway
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Environment {
+    /// The public beta at `beta.superchain.app`, the default
+    Beta,
+    /// The production gateway, whose endpoints aren't built in
+    ///
+    /// Its endpoints are required, e.g. in `SC_HTTP_URL` and `SC_WS_URL`, otherwise building the
+    /// config fails with [`Error::MissingConfig`].
+    Production,
+    /// Any other gateway, with its HTTP and WebSocket endpoint
+    #[serde(skip)]
+    Custom(Url, Url),
+}
+
+impl Environment {
+    /// The environment of the endpoints `http_url` and `ws_url`, [`Environment::Custom`] unless
+    /// they are the ones of [`Environment::Beta`]
+    ///
+    /// The endpoints of [`Environment::Production`] aren't built in, so they are reported as
+    /// [`Environment::Custom`].
+    pub fn from_urls(http_url: Url, ws_url: Url) -> Self {
+        let beta = Self::Beta;
+        if beta.http_url().as_ref() == Some(&http_url) && beta.ws_url().as_ref() == Some(&ws_url) {
+            return beta;
+        }
+        Self::Custom(http_url, ws_url)
+    }
+
+    /// The HTTP endpoint of the environment, `None` for [`Environment::Production`]
+    pub fn http_url(&self) -> Option<Url> {
+        match self {
+            Self::Beta => Some(DEFAULT_HTTP_URL.parse().expect("the url is valid")),
+            Self::Production => None,
+            Self::Custom(http_url, _) => Some(http_url.clone()),
+        }
+    }
+
+    /// The WebSocket endpoint of the environment, `None` for [`Environment::Production`]
+    pub fn ws_url(&self) -> Option<Url> {
+        match self {
+            Self::Beta => Some(DEFAULT_WS_URL.parse().expect("the url is valid")),
+            Self::Production => None,
+            Self::Custom(_, ws_url) => Some(ws_url.clone()),
+        }
+    }
+}
+
+impl std::str::FromStr for Environment {
+    type Err = Error;
+
+    fn from_str(environment: &str) -> Result<Self> {
+        match environment {
+            "beta" => Ok(Self::Beta),
+            "production" => Ok(Self::Production),
+            _ => Err(Error::InvalidConfig(format!(
+                "unknown environment {environment}, expected beta or production"
+            ))),
+        }
+    }
+}
+
+/// The credentials and endpoints used to connect to the Superchain gateway
+#[derive(Clone)]
 pub struct Config {
     pub username: String,
     pub password: String,
+    pub http_url: Url,
+    pub ws_url: Url,
+    /// The timeout of connecting and of HTTP point queries, without one they may wait forever
+    ///
+    /// Streams are not limited, since they may follow the head for any time.
+    pub timeout: Option<Duration>,
+    /// How often a failed HTTP request is retried
+    pub retries: u32,
+    /// The backoff before the first retry, doubling after each retry
+    pub retry_backoff: Duration,
+    /// The tokens to authenticate with instead of basic auth, shared by all clones
+    pub tokens: Option<Arc<TokenCache>>,
+    /// The file the password is read from, read again whenever it changes
+    pub password_file: Option<Arc<SecretFile>>,
 }
 
 impl Config {
+    /// Create a new [`ConfigBuilder`]
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::new()
+    }
+
+    /// Read the config from the environment, see [`Config::try_from_env`]
+    ///
+    /// # Panics
+    ///
+    /// If a required variable is missing or a variable is malformed.
     pub fn from_env() -> Self {
+        Self::try_from_env().unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Read the config from the environment
+    ///
+    /// The credentials are read from `SC_USERNAME` and `SC_PASSWORD`, or `SC_PASSWORD_FILE` to
+    /// read the password from a mounted secret file instead. The endpoints are read from the
+    /// optional `SC_ENVIRONMENT` (`beta` or `production`), `SC_HTTP_URL` and `SC_WS_URL`, the
+    /// latter two are required for `production`. Variables in a `.env` file are loaded as well.
+    pub fn try_from_env() -> Result<Self> {
+        Self::try_from_env_vars(&EnvVars::default())
+    }
+
+    /// Read the config from the environment variables named by `vars`, e.g. to avoid collisions
+    /// with other tooling by using `EnvVars::with_prefix("SUPERCHAIN_")`
+    pub fn try_from_env_vars(vars: &EnvVars) -> Result<Self> {
+        ConfigBuilder::new().with_env_vars(vars)?.build()
+    }
+
+    /// Read the config from the file at `path`, overridden by the environment
+    ///
+    /// The format is picked by the extension, JSON (`.json`) is always supported, TOML (`.toml`)
+    /// with the `toml` feature and YAML (`.yaml`, `.yml`) with the `yaml` feature. All fields
+    /// are optional, so credentials can be left to the environment, e.g.
+    ///
+    /// ```toml
+    /// username = "alice"
+    /// http_url = "https://beta.superchain.app/"
+    /// ws_url = "wss://beta.superchain.app/websocket"
+    /// timeout_ms = 30000
+    /// retries = 3
+    /// retry_backoff_ms = 500
+    /// ```
+    ///
+    /// A file can hold several named profiles, e.g. one per gateway environment. Their fields
+    /// override the top level fields, which are shared by all profiles:
+    ///
+    /// ```toml
+    /// username = "alice"
+    ///
+    /// [profiles.staging]
+    /// http_url = "https://staging.superchain.app/"
+    ///
+    /// [profiles.prod]
+    /// environment = "production"
+    /// http_url = "https://gateway.example.com/"
+    /// ws_url = "wss://gateway.example.com/websocket"
+    /// retries = 5
+    /// ```
+    ///
+    /// The profile named by `SC_PROFILE` is applied, if set. The variables read by
+    /// [`Config::try_from_env`] take precedence over the file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_file_with_env_vars(path, &EnvVars::default())
+    }
+
+    /// Read the config from the file at `path`, overridden by the environment variables named by
+    /// `vars`, see [`Config::from_file`]
+    pub fn from_file_with_env_vars(path: impl AsRef<Path>, vars: &EnvVars) -> Result<Self> {
+        dotenv().ok();
+        let builder = match env::var(&vars.profile) {
+            Ok(profile) => ConfigBuilder::new().with_file_profile(path, &profile)?,
+            Err(_) => ConfigBuilder::new().with_file(path)?,
+        };
+        builder.with_env_vars(vars)?.build()
+    }
+
+    /// Read the `profile` of the config file, overridden by the environment
+    ///
+    /// The file is read from the path in `SC_CONFIG`, defaulting to [`DEFAULT_CONFIG_FILE`].
+    /// See [`Config::from_file`] for its format.
+    pub fn profile(profile: &str) -> Result<Self> {
+        Self::profile_with_env_vars(profile, &EnvVars::default())
+    }
+
+    /// Read the `profile` of the config file, overridden by the environment variables named by
+    /// `vars`, see [`Config::profile`]
+    pub fn profile_with_env_vars(profile: &str, vars: &EnvVars) -> Result<Self> {
         dotenv().ok();
-        let username = env::var("SC_USERNAME").expect("SC_USERNAME environment variable");
-        let password = env::var("SC_PASSWORD").expect("SC_PASSWORD environment variable");
-        Config { username, password }
+        let path =
+            env::var_os(&vars.config).map_or_else(|| DEFAULT_CONFIG_FILE.into(), PathBuf::from);
+        ConfigBuilder::new()
+            .with_file_profile(path, profile)?
+            .with_env_vars(vars)?
+            .build()
     }
 
     pub fn get_basic_authorization_value(&self) -> String {
         let encoded = base64::encode(format!("{}:{}", self.username, self.password));
         format!("Basic {encoded}")
     }
+
+    /// The environment of the endpoints, see [`Environment::from_urls`]
+    pub fn environment(&self) -> Environment {
+        Environment::from_urls(self.http_url.clone(), self.ws_url.clone())
+    }
+
+    /// The `Authorization` header value, a bearer token if [`Config::tokens`] is set and basic
+    /// auth otherwise
+    pub async fn authorization(&self) -> Result<String> {
+        self.credentials().authorization().await
+    }
+
+    /// The credentials to authenticate with, the tokens if [`Config::tokens`] is set and basic
+    /// auth otherwise
+    ///
+    /// With [`Config::password_file`], the password is read from the file on each use.
+    pub fn credentials(&self) -> Credentials {
+        match (&self.tokens, &self.password_file) {
+            (Some(tokens), _) => Credentials::Tokens(Arc::clone(tokens)),
+            (None, Some(password_file)) => Credentials::BasicFile {
+                username: self.username.clone(),
+                password: Arc::clone(password_file),
+            },
+            (None, None) => Credentials::basic(&self.username, &self.password),
+        }
+    }
+
+    /// Check the config for mistakes, that would only surface once connecting
+    ///
+    /// All problems found are reported at once, each with a hint how to fix it.
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+        if !matches!(self.http_url.scheme(), "http" | "https") {
+            problems.push(format!(
+                "http_url {} has to use http or https, e.g. {}",
+                self.http_url, DEFAULT_HTTP_URL
+            ));
+        }
+        if !matches!(self.ws_url.scheme(), "ws" | "wss") {
+            problems.push(format!(
+                "ws_url {} has to use ws or wss, e.g. {}",
+                self.ws_url, DEFAULT_WS_URL
+            ));
+        }
+        for url in [&self.http_url, &self.ws_url] {
+            let local = matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+            if matches!(url.scheme(), "http" | "ws") && !local {
+                problems.push(format!(
+                    "{url} is not encrypted, so the credentials are sent in plain text, use \
+                     https or wss instead"
+                ));
+            }
+        }
+        if self.http_url.host_str() != self.ws_url.host_str() {
+            problems.push(format!(
+                "http_url {} and ws_url {} point to different hosts, use the endpoints of a single \
+                 environment, e.g. with ConfigBuilder::with_environment",
+                self.http_url, self.ws_url
+            ));
+        }
+
+        if self.tokens.is_none() {
+            if self.username.is_empty() {
+                problems.push("the username is empty, set SC_USERNAME".to_owned());
+            } else if self.username.contains(':') {
+                problems.push(
+                    "the username must not contain a colon, as it separates the \
+                     username from the password in basic auth"
+                        .to_owned(),
+                );
+            }
+            if self.password.is_empty() {
+                problems.push("the password is empty, set SC_PASSWORD".to_owned());
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidConfig(problems.join("; ")))
+        }
+    }
+
+    /// Store the password of the user in the OS keyring under `service`, e.g.
+    /// [`KEYRING_SERVICE`], to read it with [`ConfigBuilder::with_keyring`] from then on
+    #[cfg(feature = "keyring")]
+    pub fn store_in_keyring(&self, service: &str) -> Result<()> {
+        keyring::Entry::new(service, &self.username)?.set_password(&self.password)?;
+        Ok(())
+    }
+}
+
+/// Store `token` in the OS keyring under `service`, to read it with
+/// [`ConfigBuilder::with_keyring_token`] from then on
+#[cfg(feature = "keyring")]
+pub fn store_token_in_keyring(service: &str, token: &str) -> Result<()> {
+    keyring::Entry::new(service, KEYRING_TOKEN_USER)?.set_password(token)?;
+    Ok(())
+}
+
+/// The names of the environment variables a [`Config`] is read from
+///
+/// Defaults to the `SC_` prefixed names, e.g. `SC_USERNAME`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnvVars {
+    pub username: String,
+    pub password: String,
+    pub password_file: String,
+    pub http_url: String,
+    pub ws_url: String,
+    pub environment: String,
+    /// The profile applied by [`Config::from_file`]
+    pub profile: String,
+    /// The config file read by [`Config::profile`]
+    pub config: String,
+}
+
+impl EnvVars {
+    /// Name all variables with `prefix`, e.g. `SUPERCHAIN_USERNAME` for `SUPERCHAIN_`
+    pub fn with_prefix(prefix: &str) -> Self {
+        Self {
+            username: format!("{prefix}USERNAME"),
+            password: format!("{prefix}PASSWORD"),
+            password_file: format!("{prefix}PASSWORD_FILE"),
+            http_url: format!("{prefix}HTTP_URL"),
+            ws_url: format!("{prefix}WS_URL"),
+            environment: format!("{prefix}ENVIRONMENT"),
+            profile: format!("{prefix}PROFILE"),
+            config: format!("{prefix}CONFIG"),
+        }
+    }
+}
+
+impl Default for EnvVars {
+    fn default() -> Self {
+        Self::with_prefix("SC_")
+    }
+}
+
+/// A builder of a [`Config`] from explicit values
+///
+/// The credentials are required, the endpoints default to [`DEFAULT_HTTP_URL`] and
+/// [`DEFAULT_WS_URL`].
+#[derive(Clone, Default)]
+pub struct ConfigBuilder {
+    username: Option<String>,
+    password: Option<String>,
+    http_url: Option<Url>,
+    ws_url: Option<Url>,
+    timeout: Option<Duration>,
+    retries: u32,
+    retry_backoff: Option<Duration>,
+    tokens: Option<Arc<TokenCache>>,
+    password_file: Option<Arc<SecretFile>>,
+    environment: Option<Environment>,
+}
+
+/// The fields of a config file
+#[derive(Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    username: Option<String>,
+    password: Option<String>,
+    password_file: Option<PathBuf>,
+    environment: Option<Environment>,
+    http_url: Option<String>,
+    ws_url: Option<String>,
+    timeout_ms: Option<u64>,
+    retries: Option<u32>,
+    retry_backoff_ms: Option<u64>,
+    #[serde(default)]
+    profiles: HashMap<String, ConfigFile>,
+}
+
+/// The config file extensions supported with the enabled features
+const SUPPORTED_EXTENSIONS: &[&str] = &[
+    ".json",
+    #[cfg(feature = "toml")]
+    ".toml",
+    #[cfg(feature = "yaml")]
+    ".yaml",
+    #[cfg(feature = "yaml")]
+    ".yml",
+];
+
+impl ConfigFile {
+    fn read(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Ok(serde_json::from_str(&data)?),
+            #[cfg(feature = "toml")]
+            Some("toml") => Ok(toml::from_str(&data)?),
+            #[cfg(feature = "yaml")]
+            Some("yaml" | "yml") => Ok(serde_yaml::from_str(&data)?),
+            _ => Err(Error::InvalidConfig(format!(
+                "unsupported format of the config file {}, expected one of {}",
+                path.display(),
+                SUPPORTED_EXTENSIONS.join(", ")
+            ))),
+        }
+    }
+}
+
+impl ConfigBuilder {
+    /// Create a new, empty [`ConfigBuilder`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_username(mut self, username: impl Into<String>) -> Self {
+        self.username = Some(username.into());
+        self
+    }
+
+    pub fn with_password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self.password_file = None;
+        self
+    }
+
+    /// Read the password from the file at `path`, e.g. a mounted Docker or Kubernetes secret
+    ///
+    /// The file is read again whenever it changes, so clients pick up a rotated password with
+    /// their next request. This replaces a password set before.
+    pub fn with_password_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.password = None;
+        self.password_file = Some(Arc::new(SecretFile::new(path)));
+        self
+    }
+
+    pub fn with_http_url(mut self, http_url: Url) -> Self {
+        self.http_url = Some(http_url);
+        self
+    }
+
+    pub fn with_ws_url(mut self, ws_url: Url) -> Self {
+        self.ws_url = Some(ws_url);
+        self
+    }
+
+    /// Set the endpoints to the ones of `environment`
+    ///
+    /// The endpoints of [`Environment::Production`] have to be set afterwards, otherwise building
+    /// fails with [`Error::MissingConfig`].
+    pub fn with_environment(mut self, environment: Environment) -> Self {
+        self.http_url = environment.http_url();
+        self.ws_url = environment.ws_url();
+        self.environment = Some(environment);
+        self
+    }
+
+    /// Set the timeout of connecting and of HTTP point queries, see [`Config::timeout`]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set how often a failed HTTP request is retried, and the backoff before the first retry,
+    /// defaults to no retries
+    pub fn with_retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.retries = retries;
+        self.retry_backoff = Some(backoff);
+        self
+    }
+
+    /// Authenticate with the tokens of `provider` instead of basic auth
+    ///
+    /// The credentials are optional then.
+    pub fn with_token_provider(mut self, provider: impl TokenProvider + 'static) -> Self {
+        self.tokens = Some(Arc::new(TokenCache::new(Arc::new(provider))));
+        self
+    }
+
+    /// Read the password of the user from the OS keyring under `service`, unless it is set
+    ///
+    /// The username has to be set before, a missing keyring entry is no error.
+    #[cfg(feature = "keyring")]
+    pub fn with_keyring(mut self, service: &str) -> Result<Self> {
+        if let (Some(username), None, None) = (&self.username, &self.password, &self.password_file)
+        {
+            match keyring::Entry::new(service, username)?.get_password() {
+                Ok(password) => self.password = Some(password),
+                Err(keyring::Error::NoEntry) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(self)
+    }
+
+    /// Authenticate with the token stored in the OS keyring under `service`, if any
+    #[cfg(feature = "keyring")]
+    pub fn with_keyring_token(mut self, service: &str) -> Result<Self> {
+        match keyring::Entry::new(service, KEYRING_TOKEN_USER)?.get_password() {
+            Ok(token) => self = self.with_token_provider(StaticToken::new(token)),
+            Err(keyring::Error::NoEntry) => {}
+            Err(err) => return Err(err.into()),
+        }
+        Ok(self)
+    }
+
+    /// Set the values present in the environment, see [`Config::try_from_env`]
+    pub fn with_env(self) -> Result<Self> {
+        self.with_env_vars(&EnvVars::default())
+    }
+
+    /// Set the values present in the environment variables named by `vars`
+    pub fn with_env_vars(mut self, vars: &EnvVars) -> Result<Self> {
+        dotenv().ok();
+        if let Ok(username) = env::var(&vars.username) {
+            self = self.with_username(username);
+        }
+        if let Ok(password) = env::var(&vars.password) {
+            self = self.with_password(password);
+        }
+        if let Ok(password_file) = env::var(&vars.password_file) {
+            self = self.with_password_file(password_file);
+        }
+        if let Ok(environment) = env::var(&vars.environment) {
+            self = self.with_environment(environment.parse()?);
+        }
+        if let Ok(http_url) = env::var(&vars.http_url) {
+            self = self.with_http_url(http_url.parse()?);
+        }
+        if let Ok(ws_url) = env::var(&vars.ws_url) {
+            self = self.with_ws_url(ws_url.parse()?);
+        }
+        Ok(self)
+    }
+
+    /// Set the top level values present in the file at `path`, see [`Config::from_file`]
+    pub fn with_file(self, path: impl AsRef<Path>) -> Result<Self> {
+        self.apply(ConfigFile::read(path.as_ref())?)
+    }
+
+    /// Set the top level values and then the values of `profile` present in the file at `path`
+    ///
+    /// Fails with [`Error::UnknownProfile`], if the file has no such profile.
+    pub fn with_file_profile(self, path: impl AsRef<Path>, profile: &str) -> Result<Self> {
+        let mut file = ConfigFile::read(path.as_ref())?;
+        let overrides = file
+            .profiles
+            .remove(profile)
+            .ok_or_else(|| Error::UnknownProfile(profile.to_owned()))?;
+        self.apply(file)?.apply(overrides)
+    }
+
+    fn apply(mut self, file: ConfigFile) -> Result<Self> {
+        self.username = file.username.or(self.username);
+        if let Some(password) = file.password {
+            self = self.with_password(password);
+        }
+        if let Some(password_file) = file.password_file {
+            self = self.with_password_file(password_file);
+        }
+        if let Some(environment) = file.environment {
+            self = self.with_environment(environment);
+        }
+        if let Some(http_url) = file.http_url {
+            self.http_url = Some(http_url.parse()?);
+        }
+        if let Some(ws_url) = file.ws_url {
+            self.ws_url = Some(ws_url.parse()?);
+        }
+        self.timeout = file.timeout_ms.map(Duration::from_millis).or(self.timeout);
+        self.retries = file.retries.unwrap_or(self.retries);
+        self.retry_backoff = file
+            .retry_backoff_ms
+            .map(Duration::from_millis)
+            .or(self.retry_backoff);
+        Ok(self)
+    }
+
+    /// Build the [`Config`], failing with [`Error::MissingConfig`] without credentials, or
+    /// without the endpoints of [`Environment::Production`]
+    pub fn build(mut self) -> Result<Config> {
+        if let Some(password_file) = &self.password_file {
+            self.password = Some(password_file.read()?);
+        }
+        let environment = self.environment.take().unwrap_or(Environment::Beta);
+        let optional = self.tokens.is_some();
+        // Only the production endpoints aren't built in
+        let missing_endpoint = |kind: &str| {
+            Error::MissingConfig(format!("{kind} endpoint of the production environment"))
+        };
+        let credential = |value: Option<String>, name: &str| match value {
+            Some(value) => Ok(value),
+            None if optional => Ok(String::new()),
+            None => Err(Error::MissingConfig(name.to_owned())),
+        };
+        Ok(Config {
+            username: credential(self.username, "username")?,
+            password: credential(self.password, "password")?,
+            http_url: self
+                .http_url
+                .or_else(|| environment.http_url())
+                .ok_or_else(|| missing_endpoint("HTTP"))?,
+            ws_url: self
+                .ws_url
+                .or_else(|| environment.ws_url())
+                .ok_or_else(|| missing_endpoint("WebSocket"))?,
+            timeout: self.timeout,
+            retries: self.retries,
+            retry_backoff: self.retry_backoff.unwrap_or(Duration::from_secs(1)),
+            tokens: self.tokens,
+            password_file: self.password_file,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder() -> ConfigBuilder {
+        ConfigBuilder::new()
+            .with_username("alice")
+            .with_password("secret")
+    }
+
+    #[test]
+    fn prefixes_all_variables() {
+        let vars = EnvVars::with_prefix("SUPERCHAIN_");
+
+        assert_eq!(vars.profile, "SUPERCHAIN_PROFILE");
+        assert_eq!(vars.config, "SUPERCHAIN_CONFIG");
+    }
+
+    #[test]
+    fn reads_the_profile_named_by_the_prefixed_variable() {
+        let path = std::env::temp_dir().join(format!("superchain-{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"{"username": "alice", "password": "secret", "retries": 1,
+                "profiles": {"prod": {"retries": 5}}}"#,
+        )
+        .unwrap();
+
+        let vars = EnvVars::with_prefix("SC_TEST_PREFIX_");
+        env::set_var(&vars.profile, "prod");
+        let config = Config::from_file_with_env_vars(&path, &vars);
+        env::remove_var(&vars.profile);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.ok().unwrap().retries, 5);
+    }
+
+    #[test]
+    fn rejects_unsupported_config_files() {
+        let path = std::env::temp_dir().join(format!("superchain-{}.ini", std::process::id()));
+        fs::write(&path, "username = alice").unwrap();
+        let err = ConfigBuilder::new().with_file(&path).err().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        match err {
+            Error::InvalidConfig(message) => {
+                assert!(message.contains(".ini"));
+                assert!(message.contains(".json"));
+            }
+            err => panic!("expected an invalid config, got {err:?}"),
+        }
+    }
+
+    #[test]
+    fn defaults_to_beta() {
+        let config = builder().build().unwrap();
+        assert_eq!(config.environment(), Environment::Beta);
+    }
+
+    #[test]
+    fn production_requires_explicit_endpoints() {
+        let err = builder()
+            .with_environment(Environment::Beta)
+            .with_environment(Environment::Production)
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(err, Error::MissingConfig(_)));
+
+        let http_url: Url = "https://gateway.example.com/".parse().unwrap();
+        let ws_url: Url = "wss://gateway.example.com/websocket".parse().unwrap();
+        let config = builder()
+            .with_environment(Environment::Production)
+            .with_http_url(http_url.clone())
+            .with_ws_url(ws_url.clone())
+            .build()
+            .unwrap();
+        assert_eq!(config.environment(), Environment::Custom(http_url, ws_url));
+    }
 }
@@ -0,0 +1,215 @@
+//! How both clients handle rows, that fail to decode
+
+use std::sync::Arc;
+
+use futures::{stream::BoxStream, AsyncRead, Stream, StreamExt};
+
+use crate::{
+    telemetry::{self, Transport},
+    Error, Result,
+};
+
+type Report = Arc<dyn Fn(DecodeWarning) + Send + Sync>;
+
+/// How a client handles rows, that fail to decode
+///
+/// Only malformed rows are affected, transport and server errors are always yielded.
+#[derive(Clone, Default)]
+pub enum DecodePolicy {
+    /// Yield the error in place of the row, the default
+    #[default]
+    FailFast,
+    /// Skip the row and report it to the callback, so long backfills survive isolated bad rows
+    SkipAndReport(Report),
+    /// Skip the row
+    SkipSilently,
+}
+
+impl DecodePolicy {
+    /// Skip malformed rows and pass them to `report`, e.g. to log or count them
+    pub fn skip_and_report<F>(report: F) -> Self
+    where
+        F: Fn(DecodeWarning) + Send + Sync + 'static,
+    {
+        Self::SkipAndReport(Arc::new(report))
+    }
+
+    /// Apply the policy to the decoded rows of `stream`
+    pub(crate) fn apply<S, T>(&self, stream: S) -> impl Stream<Item = Result<T>>
+    where
+        S: Stream<Item = Result<T>>,
+    {
+        let policy = self.clone();
+        stream.filter_map(move |row| futures::future::ready(policy.check(row)))
+    }
+
+    /// Apply the policy to a single decoded `row`, `None` if it is skipped
+    fn check<T>(&self, row: Result<T>) -> Option<Result<T>> {
+        match row {
+            Err(err) if is_decode_error(&err) => match self {
+                Self::FailFast => Some(Err(err)),
+                Self::SkipAndReport(report) => {
+                    report(DecodeWarning::new(err));
+                    None
+                }
+                Self::SkipSilently => None,
+            },
+            row => Some(row),
+        }
+    }
+}
+
+/// A row skipped by [`DecodePolicy::SkipAndReport`]
+#[derive(Debug)]
+pub struct DecodeWarning {
+    /// The line of the row in the response, if known
+    pub line: Option<u64>,
+    pub error: Error,
+}
+
+impl DecodeWarning {
+    fn new(error: Error) -> Self {
+        let line = match error.inner() {
+            Error::CsvAsync(err) => err.position().map(|position| position.line()),
+            _ => None,
+        };
+        Self { line, error }
+    }
+}
+
+/// Decode the CSV rows of `reader`, reusing a single record buffer for all rows
+///
+/// Unlike a `csv_async::AsyncDeserializer`, this doesn't allocate per row. Fields are borrowed from
+/// the record, so addresses and hashes are parsed from its bytes without intermediate strings.
+/// The stream ends after a transport error, malformed rows are yielded as errors.
+pub(crate) fn reusing_rows<R, T>(reader: R) -> BoxStream<'static, Result<T>>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    T: serde::de::DeserializeOwned + Send + 'static,
+{
+    let reader = csv_async::AsyncReader::from_reader(reader);
+    let state = (reader, None, csv_async::ByteRecord::new(), false);
+    futures::stream::unfold(
+        state,
+        |(mut reader, headers, mut record, done)| async move {
+            if done {
+                return None;
+            }
+            let headers = match headers {
+                Some(headers) => headers,
+                None => match reader.byte_headers().await {
+                    Ok(headers) => headers.clone(),
+                    Err(err) => return Some((Err(err.into()), (reader, None, record, true))),
+                },
+            };
+            let row = match reader.read_byte_record(&mut record).await {
+                Ok(true) => record.deserialize(Some(&headers)).map_err(Error::from),
+                Ok(false) => return None,
+                Err(err) => {
+                    let done = err.is_io_error();
+                    return Some((Err(err.into()), (reader, Some(headers), record, done)));
+                }
+            };
+            Some((row, (reader, Some(headers), record, false)))
+        },
+    )
+    .boxed()
+}
+
+/// Decode the CSV rows of `reader` in batches of up to `batch_size` rows, reusing a single record
+/// buffer like [`reusing_rows`]
+///
+/// Rows are counted and checked against `policy` as they are decoded. A batch is yielded once it
+/// is full, or earlier with the rows decoded so far, once `reader` has to wait for more data, so
+/// head following streams don't stall. An error is yielded in place of a batch, after the rows
+/// decoded before it.
+pub(crate) fn reusing_batches<R, T>(
+    reader: R,
+    batch_size: usize,
+    transport: Transport,
+    policy: DecodePolicy,
+) -> BoxStream<'static, Result<Vec<T>>>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    T: serde::de::DeserializeOwned + Send + 'static,
+{
+    let rows = reusing_rows(reader).inspect(move |row| telemetry::decoded(transport, row));
+    policy
+        .apply(rows)
+        .ready_chunks(batch_size.max(1))
+        .flat_map(|rows| futures::stream::iter(split_errors(rows)))
+        .boxed()
+}
+
+/// Split `rows` into batches of consecutive rows and the errors between them
+fn split_errors<T>(rows: Vec<Result<T>>) -> Vec<Result<Vec<T>>> {
+    let mut batches = Vec::new();
+    let mut batch = Vec::with_capacity(rows.len());
+    for row in rows {
+        match row {
+            Ok(row) => batch.push(row),
+            Err(err) => {
+                if !batch.is_empty() {
+                    batches.push(Ok(std::mem::take(&mut batch)));
+                }
+                batches.push(Err(err));
+            }
+        }
+    }
+    if !batch.is_empty() {
+        batches.push(Ok(batch));
+    }
+    batches
+}
+
+/// Whether `err` is caused by a malformed row, rather than the transport
+fn is_decode_error(err: &Error) -> bool {
+    match err.inner() {
+        Error::CsvAsync(err) => !err.is_io_error(),
+        Error::SerdeJson(_) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::TryStreamExt;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Row {
+        block: u64,
+    }
+
+    fn batches(csv: &'static str, batch_size: usize) -> BoxStream<'static, Result<Vec<Row>>> {
+        let chunks = futures::stream::iter([Ok::<_, std::io::Error>(csv.as_bytes())]);
+        let reader = chunks.chain(futures::stream::pending()).into_async_read();
+        reusing_batches(reader, batch_size, Transport::Http, DecodePolicy::default())
+    }
+
+    /// The next item of `stream`, failing if it doesn't arrive in time
+    async fn next<T>(stream: &mut BoxStream<'static, Result<T>>) -> Result<T> {
+        tokio::time::timeout(Duration::from_secs(1), stream.next())
+            .await
+            .expect("the next item did not arrive in time")
+            .expect("the stream ended")
+    }
+
+    #[tokio::test]
+    async fn flushes_partial_batches_while_waiting() {
+        let mut stream = batches("block\n1\n2\n", 100);
+        let batch = next(&mut stream).await.unwrap();
+        assert_eq!(batch, [Row { block: 1 }, Row { block: 2 }]);
+    }
+
+    #[tokio::test]
+    async fn yields_errors_after_the_rows_before_them() {
+        let mut stream = batches("block\n1\nx\n3\n", 100);
+        assert_eq!(next(&mut stream).await.unwrap(), [Row { block: 1 }]);
+        assert!(next(&mut stream).await.is_err());
+        assert_eq!(next(&mut stream).await.unwrap(), [Row { block: 3 }]);
+    }
+}
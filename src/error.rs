@@ -1,6 +1,160 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use ethers::types::H160;
+
 /// A Result alias, that uses [`Error`] as the default error
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
+type Observer = Arc<dyn Fn(&Error) + Send + Sync>;
+
+static OBSERVER: RwLock<Option<Observer>> = RwLock::new(None);
+
+/// Set the observer called with every error the clients encounter, replacing the previous one
+///
+/// This includes errors handled internally, like requests failing before a retry or failed
+/// reconnects, so telemetry can capture failure rates without wrapping every stream.
+pub fn set_error_observer<F>(observer: F)
+where
+    F: Fn(&Error) + Send + Sync + 'static,
+{
+    *OBSERVER.write().expect("the lock is never poisoned") = Some(Arc::new(observer));
+}
+
+/// Remove the observer set with [`set_error_observer`]
+pub fn clear_error_observer() {
+    *OBSERVER.write().expect("the lock is never poisoned") = None;
+}
+
+/// Pass `err` to the observer, if one is set
+#[cfg(any(feature = "http", feature = "ws", feature = "wasm"))]
+pub(crate) fn observe(err: &Error) {
+    let observer = OBSERVER.read().expect("the lock is never poisoned").clone();
+    if let Some(observer) = observer {
+        observer(err);
+    }
+}
+
+/// Pass `err` to the observer and return it
+#[cfg(any(feature = "http", feature = "ws", feature = "wasm"))]
+pub(crate) fn observed(err: Error) -> Error {
+    observe(&err);
+    err
+}
+
+/// A structured error of the gateway, with a machine-readable code
+///
+/// The gateway sends these as JSON objects with a `code`, a `message` and optional `details`.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, thiserror::Error)]
+#[error("{code}: {message}")]
+pub struct ServerError {
+    pub code: String,
+    pub message: String,
+    #[serde(default)]
+    pub details: Option<serde_json::Value>,
+    /// The HTTP status of the response, if the error was sent over HTTP
+    #[serde(skip)]
+    pub status: Option<u16>,
+}
+
+impl ServerError {
+    /// Parse the error payload `msg`, `None` if it is not a structured error
+    pub fn parse(msg: &str) -> Option<Self> {
+        serde_json::from_str(msg).ok()
+    }
+}
+
+/// The phase of a request, that timed out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeoutPhase {
+    /// Establishing the connection
+    Connect,
+    /// Waiting for the gateway to acknowledge a subscription
+    HandshakeAck,
+    /// Waiting for the next message of a stream
+    Idle,
+    /// The whole request
+    Total,
+}
+
+impl std::fmt::Display for TimeoutPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Connect => "connect",
+            Self::HandshakeAck => "handshake ack",
+            Self::Idle => "idle",
+            Self::Total => "total",
+        })
+    }
+}
+
+/// The request an [`Error`] occurred in
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// The URL the request was sent to
+    pub endpoint: Option<String>,
+    /// The name of the operation, e.g. `getPrices`
+    pub operation: Option<String>,
+    /// The parameters of the operation as JSON, e.g. the pairs and block range
+    pub params: Option<String>,
+    /// The name of the subscription, see `subscriptions::SubscriptionManager`
+    pub subscription: Option<String>,
+}
+
+impl ErrorContext {
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    pub fn with_operation(
+        mut self,
+        operation: impl Into<String>,
+        params: impl Into<String>,
+    ) -> Self {
+        self.operation = Some(operation.into());
+        self.params = Some(params.into());
+        self
+    }
+
+    pub fn with_subscription(mut self, subscription: impl Into<String>) -> Self {
+        self.subscription = Some(subscription.into());
+        self
+    }
+
+    /// Fill the fields missing in `self` with the ones of `outer`
+    fn merge(self, outer: Self) -> Self {
+        Self {
+            endpoint: self.endpoint.or(outer.endpoint),
+            operation: self.operation.or(outer.operation),
+            params: self.params.or(outer.params),
+            subscription: self.subscription.or(outer.subscription),
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields = [
+            ("subscription", &self.subscription),
+            ("operation", &self.operation),
+            ("params", &self.params),
+            ("endpoint", &self.endpoint),
+        ];
+        let mut first = true;
+        for (name, value) in fields {
+            if let Some(value) = value {
+                let separator = if first { "" } else { ", " };
+                write!(f, "{separator}{name} {value}")?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// A collections of errors that can occur when using this crate
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -29,23 +183,126 @@ pub enum Error {
     /// The server sent an error message as part of the response
     #[error("An error occurred while processing the request: {0}")]
     ErrorMsg(String),
+    /// The server sent a structured error as part of the response
+    #[error("An error occurred while processing the request: {0}")]
+    Server(ServerError),
+    /// The gateway rejected the credentials
+    #[error("The gateway rejected the credentials: {message}")]
+    Unauthorized { message: String },
+    /// The gateway throttled the requests, retry after `retry_after` if provided
+    #[error("The gateway rate limited the request: {message}")]
+    RateLimited {
+        retry_after: Option<Duration>,
+        message: String,
+    },
+    /// The requested block range is invalid, e.g. ends before it starts or beyond the head
+    #[error("The block range is invalid: {message}")]
+    InvalidBlockRange {
+        from: Option<u64>,
+        to: Option<u64>,
+        message: String,
+    },
+    /// The requested pair is not indexed by the gateway
+    #[error("The pair is not supported: {message}")]
+    UnsupportedPair { pair: Option<H160>, message: String },
+    /// A phase of the request exceeded its time limit, `limit` is the limit if known
+    #[error("The {phase} phase timed out after {elapsed:?}")]
+    Timeout {
+        phase: TimeoutPhase,
+        elapsed: Duration,
+        limit: Option<Duration>,
+    },
     /// The websocket connection was closed by the server
     #[error("The websocket connection was closed")]
     ConnectionClosed,
+    /// A subscriber of a broadcast stream fell behind and missed the given number of items
+    #[error("The subscriber lagged behind and missed {0} items")]
+    Lagged(u64),
+    /// No API keys are configured for the tenant
+    #[error("No API keys are configured for tenant {0}")]
+    UnknownTenant(String),
+    /// All API keys of the tenant hit their quota or were throttled
+    #[error("All API keys of tenant {0} exhausted their quota")]
+    QuotaExhausted(String),
+    /// A subscription of the same name is already registered with a different filter
+    #[error("Subscription {0} is already registered with a different filter")]
+    SubscriptionConflict(String),
+    /// A required configuration value was not provided
+    #[error("The configuration is missing the {0}")]
+    MissingConfig(String),
+    /// The configuration has no profile of that name
+    #[error("The configuration has no profile {0}")]
+    UnknownProfile(String),
+    /// A row lacks a field required to process it, e.g. reserves without their block
+    #[error("The row is missing its {0}")]
+    MissingField(&'static str),
+    /// The configuration is invalid, the message explains how to fix it
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
 
     /// An error encountered during csv parsing
+    #[cfg(any(feature = "http", feature = "ws", feature = "wasm"))]
     #[error(transparent)]
-    CsvAsync(#[from] csv_async::Error),
+    CsvAsync(csv_async::Error),
+    /// An error encountered during csv writing
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
     /// An IO error
     #[error(transparent)]
-    IO(#[from] std::io::Error),
+    IO(std::io::Error),
+    /// An error status returned by a gRPC call
+    #[cfg(feature = "grpc")]
+    #[error(transparent)]
+    Grpc(Box<tonic::Status>),
+    /// An error encountered during connecting to a gRPC endpoint
+    #[cfg(feature = "grpc")]
+    #[error(transparent)]
+    GrpcTransport(#[from] tonic::transport::Error),
     /// An error encountered during making HTTP requests
+    #[cfg(feature = "http")]
     #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
     /// An error encountered during cbor parsing
+    #[cfg(any(feature = "ws", feature = "wasm"))]
     #[error(transparent)]
     SerdeCbor(#[from] serde_cbor::Error),
+    /// An error encountered during json encoding
+    #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+    /// An error encountered during parquet writing
+    #[cfg(feature = "parquet")]
+    #[error(transparent)]
+    Parquet(#[from] parquet::errors::ParquetError),
+    /// An error encountered during publishing to Kafka
+    #[cfg(feature = "kafka")]
+    #[error(transparent)]
+    Kafka(#[from] rdkafka::error::KafkaError),
+    /// An error encountered during Postgres access
+    #[cfg(feature = "postgres")]
+    #[error(transparent)]
+    Postgres(#[from] tokio_postgres::Error),
+    /// An error encountered during OS keyring access
+    #[cfg(feature = "keyring")]
+    #[error(transparent)]
+    Keyring(#[from] keyring::Error),
+    /// An error encountered during publishing to Redis
+    #[cfg(feature = "redis")]
+    #[error(transparent)]
+    Redis(#[from] redis::RedisError),
+    /// An error encountered during TOML parsing
+    #[cfg(feature = "toml")]
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+    /// An error encountered during YAML parsing
+    #[cfg(feature = "yaml")]
+    #[error(transparent)]
+    Yaml(#[from] serde_yaml::Error),
+    /// An error encountered during SQLite access
+    #[cfg(feature = "sqlite")]
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
     /// An error encountered during websocket handling
+    #[cfg(feature = "ws")]
     #[error(transparent)]
     Tungstenite(#[from] tungstenite::Error),
     /// An error encountered during url parsing
@@ -53,4 +310,416 @@ pub enum Error {
     Url(#[from] url::ParseError),
     #[error("an unexpected error occurred: {0}")]
     Custom(String),
+    /// An error shared between several receivers, e.g. the subscribers of a
+    /// [`Broadcast`](crate::adapters::Broadcast)
+    #[error(transparent)]
+    Shared(Arc<Error>),
+    /// An error with the request it occurred in, see [`Error::context`]
+    #[error("{source} ({context})")]
+    WithContext {
+        context: Box<ErrorContext>,
+        source: Box<Error>,
+    },
+}
+
+/// Unwraps the errors of this crate and of `reqwest`, that were wrapped into an IO error to pass
+/// through a reader
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        if matches!(err.get_ref(), Some(inner) if inner.is::<Error>()) {
+            let inner = err.into_inner().expect("the inner error was checked");
+            return *inner.downcast::<Error>().expect("the type was checked");
+        }
+        #[cfg(feature = "http")]
+        if matches!(err.get_ref(), Some(inner) if inner.is::<reqwest::Error>()) {
+            let inner = err.into_inner().expect("the inner error was checked");
+            return Self::Reqwest(*inner.downcast().expect("the type was checked"));
+        }
+        Self::IO(err)
+    }
+}
+
+/// Unwraps the IO errors of the underlying reader, see the conversion of [`std::io::Error`]
+#[cfg(any(feature = "http", feature = "ws", feature = "wasm"))]
+impl From<csv_async::Error> for Error {
+    fn from(err: csv_async::Error) -> Self {
+        if !err.is_io_error() {
+            return Self::CsvAsync(err);
+        }
+        match err.into_kind() {
+            csv_async::ErrorKind::Io(err) => err.into(),
+            _ => unreachable!("the error is an IO error"),
+        }
+    }
+}
+
+// The status is boxed, since it is a lot larger than all other errors
+#[cfg(feature = "grpc")]
+impl From<tonic::Status> for Error {
+    fn from(status: tonic::Status) -> Self {
+        Self::Grpc(Box::new(status))
+    }
+}
+
+impl Error {
+    /// The error for the error payload `msg` of the server
+    ///
+    /// Known errors are mapped to their typed variant, other structured errors to
+    /// [`Error::Server`] and anything else to [`Error::ErrorMsg`].
+    #[cfg(any(feature = "http", feature = "ws", feature = "wasm"))]
+    pub(crate) fn from_server_message(msg: String) -> Self {
+        match ServerError::parse(&msg) {
+            Some(err) => Self::from_server_error(err),
+            None => Self::from_plain_message(msg),
+        }
+    }
+
+    /// The typed error for a structured error of the server, by its code
+    #[cfg(any(feature = "http", feature = "ws", feature = "wasm"))]
+    pub(crate) fn from_server_error(err: ServerError) -> Self {
+        let details = err.details.as_ref();
+        let number = |name: &str| details?.get(name)?.as_u64();
+        let (from, to, retry_after) = (number("from"), number("to"), number("retry_after"));
+        let pair = details
+            .and_then(|details| details.get("pair")?.as_str()?.parse().ok())
+            .or_else(|| find_address(&err.message));
+
+        let message = err.message.clone();
+        match err.code.as_str() {
+            "unauthorized" | "forbidden" | "invalid_credentials" => Self::Unauthorized { message },
+            "rate_limited" | "too_many_requests" => Self::RateLimited {
+                retry_after: retry_after.map(Duration::from_secs),
+                message,
+            },
+            "invalid_block_range" => Self::InvalidBlockRange { from, to, message },
+            "unsupported_pair" | "unknown_pair" => Self::UnsupportedPair { pair, message },
+            _ => Self::Server(err),
+        }
+    }
+
+    /// The typed error for a plain error message of the server, recognized by its wording
+    #[cfg(any(feature = "http", feature = "ws", feature = "wasm"))]
+    fn from_plain_message(message: String) -> Self {
+        let lower = message.to_lowercase();
+        let contains_any = |needles: &[&str]| needles.iter().any(|needle| lower.contains(needle));
+        if contains_any(&[
+            "unauthorized",
+            "unauthorised",
+            "forbidden",
+            "invalid credentials",
+        ]) {
+            Self::Unauthorized { message }
+        } else if contains_any(&["rate limit", "too many requests"]) {
+            Self::RateLimited {
+                retry_after: None,
+                message,
+            }
+        } else if contains_any(&["block range"]) {
+            let mut numbers = message
+                .split(|c: char| !c.is_ascii_digit())
+                .filter_map(|number| number.parse().ok());
+            Self::InvalidBlockRange {
+                from: numbers.next(),
+                to: numbers.next(),
+                message,
+            }
+        } else if contains_any(&["unsupported pair", "unknown pair"]) {
+            Self::UnsupportedPair {
+                pair: find_address(&message),
+                message,
+            }
+        } else {
+            Self::ErrorMsg(message)
+        }
+    }
+
+    /// Attach `context` to the error, keeping the fields of a context attached before
+    pub fn with_context(self, context: ErrorContext) -> Self {
+        match self {
+            Self::WithContext {
+                context: inner,
+                source,
+            } => Self::WithContext {
+                context: Box::new(inner.merge(context)),
+                source,
+            },
+            err => Self::WithContext {
+                context: Box::new(context),
+                source: Box::new(err),
+            },
+        }
+    }
+
+    /// The request the error occurred in, if known
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Self::WithContext { context, .. } => Some(context),
+            Self::Shared(err) => err.context(),
+            _ => None,
+        }
+    }
+
+    /// The error without its context
+    pub fn inner(&self) -> &Error {
+        match self {
+            Self::WithContext { source, .. } => source,
+            Self::Shared(err) => err.inner(),
+            err => err,
+        }
+    }
+
+    /// The URL the failed request was sent to, if known
+    pub fn endpoint(&self) -> Option<&str> {
+        self.context()?.endpoint.as_deref()
+    }
+
+    /// The name of the failed operation, if known
+    pub fn operation(&self) -> Option<&str> {
+        self.context()?.operation.as_deref()
+    }
+
+    /// The parameters of the failed operation as JSON, if known
+    pub fn params(&self) -> Option<&str> {
+        self.context()?.params.as_deref()
+    }
+
+    /// The name of the subscription the error occurred in, if known
+    pub fn subscription(&self) -> Option<&str> {
+        self.context()?.subscription.as_deref()
+    }
+
+    /// The first error of type `E` in the chain of causes, e.g. the `tungstenite::Error` of a
+    /// failed connection
+    pub fn find_source<E>(&self) -> Option<&E>
+    where
+        E: std::error::Error + 'static,
+    {
+        let mut cause: Option<&(dyn std::error::Error + 'static)> = Some(self);
+        while let Some(err) = cause {
+            if let Some(err) = err.downcast_ref::<E>() {
+                return Some(err);
+            }
+            cause = match err.downcast_ref::<Error>() {
+                Some(err) => err.cause(),
+                None => err.source(),
+            };
+        }
+        None
+    }
+
+    /// The innermost error of the chain of causes
+    pub fn root_cause(&self) -> &(dyn std::error::Error + 'static) {
+        let mut root: &(dyn std::error::Error + 'static) = self;
+        loop {
+            let cause = match root.downcast_ref::<Error>() {
+                Some(err) => err.cause(),
+                None => root.source(),
+            };
+            match cause {
+                Some(cause) => root = cause,
+                None => return root,
+            }
+        }
+    }
+
+    /// The error this error wraps, if any
+    ///
+    /// Unlike [`std::error::Error::source`], this includes the wrapped errors of the transparent
+    /// variants.
+    fn cause(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::WithContext { source, .. } => Some(&**source),
+            Self::Shared(err) => Some(&**err),
+            Self::Server(err) => Some(err),
+            #[cfg(any(feature = "http", feature = "ws", feature = "wasm"))]
+            Self::CsvAsync(err) => Some(err),
+            Self::Csv(err) => Some(err),
+            Self::IO(err) => Some(err),
+            #[cfg(feature = "http")]
+            Self::Reqwest(err) => Some(err),
+            #[cfg(any(feature = "ws", feature = "wasm"))]
+            Self::SerdeCbor(err) => Some(err),
+            Self::SerdeJson(err) => Some(err),
+            #[cfg(feature = "parquet")]
+            Self::Parquet(err) => Some(err),
+            #[cfg(feature = "kafka")]
+            Self::Kafka(err) => Some(err),
+            #[cfg(feature = "postgres")]
+            Self::Postgres(err) => Some(err),
+            #[cfg(feature = "keyring")]
+            Self::Keyring(err) => Some(err),
+            #[cfg(feature = "redis")]
+            Self::Redis(err) => Some(err),
+            #[cfg(feature = "toml")]
+            Self::Toml(err) => Some(err),
+            #[cfg(feature = "yaml")]
+            Self::Yaml(err) => Some(err),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(err) => Some(err),
+            #[cfg(feature = "ws")]
+            Self::Tungstenite(err) => Some(err),
+            Self::Url(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Whether sending the request again may succeed
+    ///
+    /// This holds for dropped connections, timeouts, rate limits and server side failures, but
+    /// not for authentication failures, invalid requests or an exhausted quota, which only
+    /// recovers once the quota is reset.
+    pub fn is_retryable(&self) -> bool {
+        if let Self::WithContext { source, .. } = self {
+            return source.is_retryable();
+        }
+        if let Self::Shared(source) = self {
+            return source.is_retryable();
+        }
+        if matches!(self, Self::QuotaExhausted(_)) {
+            return false;
+        }
+        if self.is_rate_limited() {
+            return true;
+        }
+        if self.is_auth_failure() {
+            return false;
+        }
+        if let Some(status) = self.status() {
+            return (500..600).contains(&status);
+        }
+        match self {
+            Self::BackendShutDown | Self::ConnectionClosed | Self::IO(_) | Self::Timeout { .. } => {
+                true
+            }
+            #[cfg(feature = "grpc")]
+            Self::GrpcTransport(_) => true,
+            Self::ErrorMsg(_) | Self::Server(_) => self.server_message_contains(&[
+                "timeout",
+                "timed out",
+                "unavailable",
+                "try again",
+                "internal",
+            ]),
+            #[cfg(feature = "http")]
+            Self::Reqwest(err) => err.is_connect() || err.is_timeout() || err.is_request(),
+            #[cfg(feature = "ws")]
+            Self::Tungstenite(err) => matches!(
+                err,
+                tungstenite::Error::ConnectionClosed
+                    | tungstenite::Error::AlreadyClosed
+                    | tungstenite::Error::Io(_)
+                    | tungstenite::Error::Protocol(
+                        tungstenite::error::ProtocolError::ResetWithoutClosingHandshake
+                    )
+            ),
+            _ => false,
+        }
+    }
+
+    /// Whether the gateway rejected the request, because the rate limit or quota was hit
+    pub fn is_rate_limited(&self) -> bool {
+        if let Self::WithContext { source, .. } = self {
+            return source.is_rate_limited();
+        }
+        if let Self::Shared(source) = self {
+            return source.is_rate_limited();
+        }
+        match self {
+            Self::QuotaExhausted(_) | Self::RateLimited { .. } => true,
+            _ if self.status() == Some(429) => true,
+            _ => self.server_message_contains(&[
+                "rate limit",
+                "rate_limit",
+                "too many requests",
+                "quota",
+            ]),
+        }
+    }
+
+    /// Whether the gateway rejected the credentials
+    pub fn is_auth_failure(&self) -> bool {
+        if let Self::WithContext { source, .. } = self {
+            return source.is_auth_failure();
+        }
+        if let Self::Shared(source) = self {
+            return source.is_auth_failure();
+        }
+        match self {
+            Self::Unauthorized { .. } => true,
+            _ if matches!(self.status(), Some(401 | 403)) => true,
+            _ => self.server_message_contains(&[
+                "unauthorized",
+                "unauthorised",
+                "forbidden",
+                "invalid credentials",
+                "invalid_credentials",
+            ]),
+        }
+    }
+
+    /// The HTTP status the gateway responded with, including the WebSocket handshake
+    ///
+    /// gRPC status codes are mapped to their HTTP counterpart, where there is one.
+    fn status(&self) -> Option<u16> {
+        match self {
+            Self::Server(err) => err.status,
+            #[cfg(feature = "grpc")]
+            Self::Grpc(status) => match status.code() {
+                tonic::Code::Unauthenticated => Some(401),
+                tonic::Code::PermissionDenied => Some(403),
+                tonic::Code::ResourceExhausted => Some(429),
+                tonic::Code::Internal => Some(500),
+                tonic::Code::Unavailable => Some(503),
+                tonic::Code::DeadlineExceeded => Some(504),
+                _ => None,
+            },
+            #[cfg(feature = "http")]
+            Self::Reqwest(err) => err.status().map(|status| status.as_u16()),
+            #[cfg(feature = "ws")]
+            Self::Tungstenite(tungstenite::Error::Http(response)) => {
+                Some(response.status().as_u16())
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the error message or code of the server contains any of `needles`, ignoring case
+    fn server_message_contains(&self, needles: &[&str]) -> bool {
+        let msg = match self {
+            Self::ErrorMsg(msg) => msg.to_lowercase(),
+            Self::Server(err) => format!("{} {}", err.code, err.message).to_lowercase(),
+            _ => return false,
+        };
+        needles.iter().any(|needle| msg.contains(needle))
+    }
+}
+
+/// The first hex address in `msg`, if any
+#[cfg(any(feature = "http", feature = "ws", feature = "wasm"))]
+fn find_address(msg: &str) -> Option<H160> {
+    msg.match_indices("0x")
+        .find_map(|(start, _)| msg.get(start..start + 42)?.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exhausted_quota_is_not_retryable() {
+        let err = Error::QuotaExhausted("acme".to_owned())
+            .with_context(ErrorContext::default().with_endpoint("https://example.com/"));
+
+        assert!(err.is_rate_limited());
+        assert!(!err.is_retryable());
+    }
+
+    #[cfg(feature = "grpc")]
+    #[test]
+    fn classifies_grpc_statuses() {
+        assert!(Error::from(tonic::Status::unavailable("draining")).is_retryable());
+        assert!(Error::from(tonic::Status::resource_exhausted("slow down")).is_rate_limited());
+        assert!(Error::from(tonic::Status::unauthenticated("no token")).is_auth_failure());
+        assert!(!Error::from(tonic::Status::invalid_argument("bad pair")).is_retryable());
+    }
 }
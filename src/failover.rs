@@ -0,0 +1,502 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use ethers::types::H160;
+use futures::{future::BoxFuture, stream::BoxStream, FutureExt, StreamExt};
+use tokio::{sync::Mutex, time::Instant};
+
+use crate::{
+    auth::Credentials,
+    config::Config,
+    error, telemetry,
+    types::{PairCreated, PairInfo, Positioned, Price, Reserves},
+    Error, HttpClient, Result, SuperchainProvider, WsClient,
+};
+
+type Connect = Box<dyn Fn() -> BoxFuture<'static, Result<WsClient>> + Send + Sync>;
+
+/// A stream opened by a [`FailoverClient`], with the WebSocket client it was opened with
+type Opened<T> = (BoxStream<'static, Result<T>>, Option<Arc<WsClient>>);
+
+struct WsState {
+    client: Option<Arc<WsClient>>,
+    retry_at: Option<Instant>,
+}
+
+/// A client preferring the WebSocket transport, that falls back to HTTP during outages
+///
+/// The WebSocket connection is established lazily with the provided `connect` function. Once it
+/// fails, queries are served via HTTP and reconnecting is retried after the retry interval.
+/// Streams survive an outage by resuming right after their last item with the other transport,
+/// and switch back once the WebSocket connection recovered.
+pub struct FailoverClient {
+    http: HttpClient,
+    connect: Connect,
+    credentials: Option<Arc<std::sync::Mutex<Credentials>>>,
+    retry_interval: Duration,
+    ws: Mutex<WsState>,
+}
+
+impl FailoverClient {
+    /// Create a new [`FailoverClient`] falling back to `http`
+    ///
+    /// `connect` establishes a new WebSocket connection, e.g. with
+    /// [`connect_async`](tokio_tungstenite::connect_async) and [`WsClient::new`].
+    pub fn new<F, Fut>(http: HttpClient, connect: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<WsClient>> + Send + 'static,
+    {
+        Self {
+            http,
+            connect: Box::new(move || connect().boxed()),
+            credentials: None,
+            retry_interval: Duration::from_secs(10),
+            ws: Mutex::new(WsState {
+                client: None,
+                retry_at: None,
+            }),
+        }
+    }
+
+    /// Create a new [`FailoverClient`] for the gateway endpoints of `config`, authenticating with
+    /// its credentials
+    ///
+    /// Credentials set with [`FailoverClient::set_credentials`] are used when reconnecting.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let http = HttpClient::from_config(config)?;
+        let credentials = Arc::new(std::sync::Mutex::new(config.credentials()));
+        let ws_url = config.ws_url.clone();
+        let current = Arc::clone(&credentials);
+        let mut client = Self::new(http, move || {
+            let ws_url = ws_url.clone();
+            let credentials = current.lock().expect("the lock is never poisoned").clone();
+            async move { WsClient::connect_with_credentials(&ws_url, &credentials).await }
+        });
+        client.credentials = Some(credentials);
+        Ok(client)
+    }
+
+    /// Switch to new `credentials`, e.g. after rotating a password
+    ///
+    /// HTTP requests use them right away. The open WebSocket connection stays authenticated with
+    /// the previous credentials, the new ones are used with the next reconnect. With a custom
+    /// `connect` function passed to [`FailoverClient::new`], reconnecting is up to that function.
+    pub fn set_credentials(&self, credentials: Credentials) {
+        if let Some(current) = &self.credentials {
+            *current.lock().expect("the lock is never poisoned") = credentials.clone();
+        }
+        self.http.set_credentials(credentials);
+    }
+
+    /// Set the interval after which reconnecting the WebSocket is retried, defaults to 10s
+    pub fn with_retry_interval(mut self, retry_interval: Duration) -> Self {
+        self.retry_interval = retry_interval;
+        self
+    }
+
+    /// Whether queries are currently served via the WebSocket transport
+    pub async fn is_ws_connected(&self) -> bool {
+        self.ws.lock().await.client.is_some()
+    }
+
+    /// The WebSocket client, reconnecting it if the retry interval passed
+    async fn ws(&self) -> Option<Arc<WsClient>> {
+        let mut state = self.ws.lock().await;
+        if let Some(client) = &state.client {
+            return Some(Arc::clone(client));
+        }
+        if matches!(state.retry_at, Some(retry_at) if retry_at > Instant::now()) {
+            return None;
+        }
+
+        if state.retry_at.is_some() {
+            telemetry::reconnect();
+        }
+        match (self.connect)().await {
+            Ok(client) => {
+                let client = Arc::new(client);
+                state.client = Some(Arc::clone(&client));
+                Some(client)
+            }
+            Err(err) => {
+                error::observe(&err);
+                state.retry_at = Some(Instant::now() + self.retry_interval);
+                None
+            }
+        }
+    }
+
+    /// Whether the WebSocket transport is down, but reconnecting is due
+    async fn ws_due(&self) -> bool {
+        let state = self.ws.lock().await;
+        state.client.is_none() && !matches!(state.retry_at, Some(at) if at > Instant::now())
+    }
+
+    async fn mark_down(&self, client: &Arc<WsClient>) {
+        let mut state = self.ws.lock().await;
+        if matches!(&state.client, Some(current) if Arc::ptr_eq(current, client)) {
+            state.client = None;
+            state.retry_at = Some(Instant::now() + self.retry_interval);
+        }
+    }
+
+    /// Run `query` via WebSocket if available, falling back to HTTP on a transport error
+    async fn query<T, F>(&self, query: F) -> Result<T>
+    where
+        F: for<'a> Fn(&'a dyn SuperchainProvider) -> BoxFuture<'a, Result<T>>,
+    {
+        if let Some(ws) = self.ws().await {
+            match query(&*ws).await {
+                Err(err) if is_transport_error(&err) => self.mark_down(&ws).await,
+                result => return result,
+            }
+        }
+        query(&self.http).await
+    }
+
+    async fn open_prices(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<Opened<Price>> {
+        if let Some(ws) = self.ws().await {
+            match ws.get_prices([pair], Some(from_block), to_block_inc).await {
+                Ok(stream) => return Ok((stream.boxed(), Some(ws))),
+                Err(err) if is_transport_error(&err) => self.mark_down(&ws).await,
+                Err(err) => return Err(err),
+            }
+        }
+        let stream = match to_block_inc {
+            Some(to_block) => self
+                .http
+                .get_prices_in_range(pair, from_block..=to_block)
+                .await?
+                .boxed(),
+            None => self
+                .http
+                .get_prices_live_stream(pair, from_block)
+                .await?
+                .boxed(),
+        };
+        Ok((stream, None))
+    }
+
+    async fn open_reserves(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<Opened<Reserves>> {
+        if let Some(ws) = self.ws().await {
+            match ws
+                .get_reserves([pair], Some(from_block), to_block_inc)
+                .await
+            {
+                Ok(stream) => return Ok((stream.boxed(), Some(ws))),
+                Err(err) if is_transport_error(&err) => self.mark_down(&ws).await,
+                Err(err) => return Err(err),
+            }
+        }
+        let stream = match to_block_inc {
+            Some(to_block) => self
+                .http
+                .get_reserves_in_range(pair, from_block..=to_block)
+                .await?
+                .boxed(),
+            None => self
+                .http
+                .get_reserves_live_stream(pair, from_block)
+                .await?
+                .boxed(),
+        };
+        Ok((stream, None))
+    }
+
+    /// A stream, that reopens itself with `open` right after its last item on transport errors
+    fn resuming<'a, T, F>(&'a self, from_block: u64, open: F) -> BoxStream<'a, Result<T>>
+    where
+        T: Positioned + Send + 'a,
+        F: Fn(u64) -> BoxFuture<'a, Result<Opened<T>>> + Send + 'a,
+    {
+        let resume = Resume {
+            block: from_block,
+            yielded: 0,
+        };
+        let state = (open, None::<Opened<T>>, resume, 0, false);
+        futures::stream::unfold(
+            state,
+            move |(open, mut opened, mut resume, mut skip, done)| async move {
+                if done {
+                    return None;
+                }
+                loop {
+                    if opened.is_none() {
+                        match open(resume.block).await {
+                            Ok(reopened) => {
+                                opened = Some(reopened);
+                                skip = resume.yielded;
+                            }
+                            Err(err) => return Some((Err(err), (open, None, resume, 0, true))),
+                        }
+                    } else if matches!(&opened, Some((_, None))) && self.ws_due().await {
+                        if let Ok(reopened @ (_, Some(_))) = open(resume.block).await {
+                            opened = Some(reopened);
+                            skip = resume.yielded;
+                        }
+                    }
+                    let (stream, ws) = opened.as_mut().expect("opened above");
+
+                    match stream.next().await {
+                        Some(Ok(_)) if skip > 0 => skip -= 1,
+                        Some(Ok(item)) => {
+                            resume.advance(item.block_number());
+                            return Some((Ok(item), (open, opened, resume, skip, false)));
+                        }
+                        Some(Err(err)) if is_transport_error(&err) => match ws {
+                            Some(ws) => {
+                                self.mark_down(ws).await;
+                                opened = None;
+                            }
+                            None => return Some((Err(err), (open, None, resume, skip, false))),
+                        },
+                        Some(Err(err)) => {
+                            return Some((Err(err), (open, opened, resume, skip, false)))
+                        }
+                        None => return None,
+                    }
+                }
+            },
+        )
+        .boxed()
+    }
+}
+
+/// Where a reopened stream resumes: at `block`, skipping the `yielded` rows already yielded
+/// since the first row of that block
+///
+/// Rows are counted rather than compared by position, since the rows of one transaction without
+/// a log index share a position and rows without a block have none. This relies on both
+/// transports streaming the rows of a block in the same order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Resume {
+    block: u64,
+    yielded: usize,
+}
+
+impl Resume {
+    /// Account for a yielded row of `block`
+    fn advance(&mut self, block: Option<u64>) {
+        match block {
+            Some(block) if block > self.block => *self = Self { block, yielded: 1 },
+            _ => self.yielded += 1,
+        }
+    }
+}
+
+/// Whether `err` indicates an unavailable transport, rather than a rejected query or a row that
+/// failed to decode
+fn is_transport_error(err: &Error) -> bool {
+    match err.inner() {
+        Error::BackendShutDown | Error::ConnectionClosed | Error::IO(_) | Error::Timeout { .. } => {
+            true
+        }
+        Error::Reqwest(err) => {
+            err.is_connect() || err.is_timeout() || err.is_request() || err.is_body()
+        }
+        Error::Tungstenite(tungstenite::Error::Http(response)) => {
+            response.status().is_server_error()
+        }
+        Error::Tungstenite(_) => true,
+        _ => false,
+    }
+}
+
+#[async_trait::async_trait]
+impl SuperchainProvider for FailoverClient {
+    async fn get_pair_created(&self, pair: H160) -> Result<Option<PairCreated>> {
+        self.query(|provider| provider.get_pair_created(pair)).await
+    }
+
+    async fn get_pair_info(&self, pair: H160) -> Result<Option<PairInfo>> {
+        self.query(|provider| provider.get_pair_info(pair)).await
+    }
+
+    async fn get_prices(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Price>>> {
+        Ok(self.resuming(from_block, move |from| {
+            self.open_prices(pair, from, to_block_inc).boxed()
+        }))
+    }
+
+    async fn get_reserves(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Reserves>>> {
+        Ok(self.resuming(from_block, move |from| {
+            self.open_reserves(pair, from, to_block_inc).boxed()
+        }))
+    }
+
+    async fn get_latest_price(&self, pair: H160) -> Result<Option<Price>> {
+        self.query(|provider| provider.get_latest_price(pair)).await
+    }
+
+    async fn get_height(&self) -> Result<u64> {
+        self.query(|provider| provider.get_height()).await
+    }
+
+    async fn finalized_height(&self) -> Result<u64> {
+        self.query(|provider| provider.finalized_height()).await
+    }
+
+    async fn block_for_timestamp(&self, timestamp: i64) -> Result<u64> {
+        self.query(|provider| provider.block_for_timestamp(timestamp))
+            .await
+    }
+
+    async fn timestamp_for_block(&self, block: u64) -> Result<i64> {
+        self.query(|provider| provider.timestamp_for_block(block))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+
+    use futures::SinkExt;
+    use tokio_tungstenite::WebSocketStream;
+    use tungstenite::{protocol::Role, Message};
+
+    use super::*;
+    use crate::{config::Config, types::fixtures};
+
+    fn resume(block: u64, yielded: usize) -> Resume {
+        Resume { block, yielded }
+    }
+
+    #[test]
+    fn counts_rows_sharing_a_position() {
+        let mut cursor = resume(10, 0);
+        for _ in 0..3 {
+            cursor.advance(Some(10));
+        }
+        assert_eq!(cursor, resume(10, 3));
+
+        cursor.advance(Some(12));
+        assert_eq!(cursor, resume(12, 1));
+    }
+
+    #[test]
+    fn counts_rows_without_a_block() {
+        let mut cursor = resume(10, 0);
+        cursor.advance(None);
+        cursor.advance(None);
+        assert_eq!(cursor, resume(10, 2));
+    }
+
+    #[test]
+    fn classifies_transport_errors() {
+        assert!(is_transport_error(&Error::ConnectionClosed));
+        assert!(is_transport_error(
+            &Error::BackendShutDown.with_context(crate::ErrorContext::default())
+        ));
+        assert!(is_transport_error(&Error::Tungstenite(
+            tungstenite::Error::ConnectionClosed
+        )));
+
+        assert!(!is_transport_error(&Error::UnexpectedMessageFormat));
+        assert!(!is_transport_error(&Error::SerdeCbor(
+            serde_cbor::from_slice::<u64>(&[0xff]).unwrap_err()
+        )));
+        assert!(!is_transport_error(&Error::ErrorMsg(
+            "invalid pair".to_owned()
+        )));
+        assert!(!is_transport_error(&Error::Custom("broken".to_owned())));
+    }
+
+    fn csv_rows(prices: &[Price]) -> Vec<u8> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        for price in prices {
+            writer.serialize(price).unwrap();
+        }
+        writer.into_inner().unwrap()
+    }
+
+    /// A gateway answering a single HTTP request with `body`
+    fn http_gateway(body: Vec<u8>) -> reqwest::Url {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/csv\r\nContent-Length: {}\r\n\
+                 Connection: close\r\n\r\n",
+                body.len()
+            );
+            stream.write_all(head.as_bytes()).unwrap();
+            stream.write_all(&body).unwrap();
+        });
+        url.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn resumes_over_http_when_the_websocket_drops() {
+        let prices: Vec<_> = (1..=4)
+            .map(|block| fixtures::gateway_price(1, block, 0))
+            .collect();
+        let config = Config::builder()
+            .with_username("user")
+            .with_password("password")
+            .with_http_url(http_gateway(csv_rows(&prices[1..])))
+            .build()
+            .unwrap();
+        let http = HttpClient::from_config(&config).unwrap();
+
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let client = WebSocketStream::from_raw_socket(client, Role::Client, None).await;
+        let ws = std::sync::Mutex::new(Some(WsClient::new(client).await));
+        let failover = FailoverClient::new(http, move || {
+            let ws = ws.lock().expect("the lock is never poisoned").take();
+            async move { ws.ok_or(Error::ConnectionClosed) }
+        });
+
+        let served = csv_rows(&prices[..2]);
+        tokio::spawn(async move {
+            let mut server = WebSocketStream::from_raw_socket(server, Role::Server, None).await;
+            loop {
+                match server.next().await {
+                    Some(Ok(Message::Binary(_))) => break,
+                    Some(Ok(_)) => continue,
+                    other => panic!("expected a request, got {other:?}"),
+                }
+            }
+            // The rows of the first request, then the socket drops without a close frame
+            let mut data = served;
+            data.extend_from_slice(&[0b00000010, 0, 0, 0, 0, 1]);
+            server.send(Message::Binary(data)).await.unwrap();
+        });
+
+        let blocks: Vec<_> = failover
+            .get_prices(H160::from_low_u64_be(1), 1, Some(4))
+            .await
+            .unwrap()
+            .map(|price| price.unwrap().block_number)
+            .collect()
+            .await;
+        assert_eq!(blocks, [1, 2, 3, 4]);
+        assert!(!failover.is_ws_connected().await);
+    }
+}
@@ -0,0 +1,342 @@
+use ethers::types::{Address, H160, H256, U256};
+use futures::{Stream, StreamExt};
+use tonic::{
+    metadata::MetadataMap,
+    transport::{Channel, ClientTlsConfig, Endpoint},
+    Request,
+};
+
+use crate::{
+    types::{PairCreated, PairInfo, Price, Reserves, Side, Type},
+    Error, Result,
+};
+
+/// The messages and the client generated from `proto/superchain.proto`
+mod proto {
+    #![allow(clippy::derive_partial_eq_without_eq)]
+
+    tonic::include_proto!("superchain.v1");
+}
+
+use proto::superchain_client::SuperchainClient;
+
+/// A Superchain gRPC client
+///
+/// Prices and reserves are streamed with server-streaming RPCs, all other queries are unary.
+#[derive(Clone)]
+pub struct Client {
+    inner: SuperchainClient<Channel>,
+    metadata: MetadataMap,
+}
+
+impl Client {
+    /// Create a new [`Client`] sending its requests over `channel`
+    pub fn new(channel: Channel) -> Self {
+        Self {
+            inner: SuperchainClient::new(channel),
+            metadata: MetadataMap::new(),
+        }
+    }
+
+    /// Connect to the gRPC endpoint at `url`, like `http://localhost:50051`
+    ///
+    /// TLS is used for `https` URLs, verified against the system roots.
+    pub async fn connect(url: &str) -> Result<Self> {
+        let mut endpoint = Endpoint::from_shared(url.to_owned())
+            .map_err(|_| Error::Custom(format!("invalid gRPC endpoint {url}")))?;
+        if url.starts_with("https://") {
+            endpoint = endpoint.tls_config(ClientTlsConfig::new())?;
+        }
+        Ok(Self::new(endpoint.connect().await?))
+    }
+
+    /// Set the metadata provided with each request, e.g. an `authorization` entry
+    pub fn with_default_metadata(mut self, metadata: MetadataMap) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Get the uniswap v2 pair created event for the provided `pair`
+    pub async fn get_pair_created(&self, pair: H160) -> Result<Option<PairCreated>> {
+        let reply = self
+            .inner
+            .clone()
+            .get_pair_created(self.request(proto::PairRequest {
+                pair: pair.as_bytes().to_vec(),
+            }))
+            .await?;
+        reply
+            .into_inner()
+            .pair_created
+            .map(PairCreated::try_from)
+            .transpose()
+    }
+
+    /// Get the uniswap v2 pair created event of the provided `pair`, joined with the metadata of
+    /// its tokens and its current reserves
+    pub async fn get_pair_info(&self, pair: H160) -> Result<Option<PairInfo>> {
+        let reply = self
+            .inner
+            .clone()
+            .get_pair_info(self.request(proto::PairRequest {
+                pair: pair.as_bytes().to_vec(),
+            }))
+            .await?;
+        reply
+            .into_inner()
+            .pair_info
+            .map(PairInfo::try_from)
+            .transpose()
+    }
+
+    /// Get the uniswap v2 prices for the provided `pair` from `from_block` up to and including
+    /// `to_block_inc`
+    ///
+    /// A `to_block_inc` of `None` will lead to a head following stream.
+    pub async fn get_prices(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<Price>> + Send> {
+        let stream = self
+            .inner
+            .clone()
+            .get_prices(self.request(proto::RangeRequest {
+                pair: pair.as_bytes().to_vec(),
+                from_block,
+                to_block: to_block_inc,
+            }))
+            .await?
+            .into_inner();
+        Ok(stream.map(|price| Price::try_from(price?)))
+    }
+
+    /// Get the uniswap v2 reserves for the provided `pair` from `from_block` up to and including
+    /// `to_block_inc`
+    ///
+    /// A `to_block_inc` of `None` will lead to a head following stream.
+    pub async fn get_reserves(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<Reserves>> + Send> {
+        let stream = self
+            .inner
+            .clone()
+            .get_reserves(self.request(proto::RangeRequest {
+                pair: pair.as_bytes().to_vec(),
+                from_block,
+                to_block: to_block_inc,
+            }))
+            .await?
+            .into_inner();
+        Ok(stream.map(|reserves| Reserves::try_from(reserves?)))
+    }
+
+    /// Get the most recent uniswap v2 price quote of the provided `pair`
+    ///
+    /// Returns `None` if the pair was never traded.
+    pub async fn get_latest_price(&self, pair: H160) -> Result<Option<Price>> {
+        let reply = self
+            .inner
+            .clone()
+            .get_latest_price(self.request(proto::PairRequest {
+                pair: pair.as_bytes().to_vec(),
+            }))
+            .await?;
+        reply.into_inner().price.map(Price::try_from).transpose()
+    }
+
+    /// Get the current height of the indexed chain
+    pub async fn get_height(&self) -> Result<u64> {
+        let reply = self
+            .inner
+            .clone()
+            .get_height(self.request(proto::HeightRequest {}))
+            .await?;
+        Ok(reply.into_inner().block)
+    }
+
+    /// Get the height of the latest finalized block
+    pub async fn finalized_height(&self) -> Result<u64> {
+        let reply = self
+            .inner
+            .clone()
+            .get_finalized_height(self.request(proto::HeightRequest {}))
+            .await?;
+        Ok(reply.into_inner().block)
+    }
+
+    /// Get the latest block at or before the provided unix `timestamp`
+    pub async fn block_for_timestamp(&self, timestamp: i64) -> Result<u64> {
+        let reply = self
+            .inner
+            .clone()
+            .get_block_for_timestamp(self.request(proto::BlockForTimestampRequest { timestamp }))
+            .await?;
+        Ok(reply.into_inner().block)
+    }
+
+    /// Get the unix timestamp of the provided `block`
+    pub async fn timestamp_for_block(&self, block: u64) -> Result<i64> {
+        let reply = self
+            .inner
+            .clone()
+            .get_timestamp_for_block(self.request(proto::TimestampForBlockRequest { block }))
+            .await?;
+        Ok(reply.into_inner().timestamp)
+    }
+
+    fn request<T>(&self, message: T) -> Request<T> {
+        let mut request = Request::new(message);
+        *request.metadata_mut() = self.metadata.clone();
+        request
+    }
+}
+
+impl TryFrom<proto::PairCreated> for PairCreated {
+    type Error = Error;
+
+    fn try_from(pair: proto::PairCreated) -> Result<Self> {
+        Ok(Self {
+            block_number: pair.block_number,
+            factory: address(pair.factory)?,
+            pair: address(pair.pair)?,
+            token0: address(pair.token0)?,
+            token1: address(pair.token1)?,
+            pair_index: uint256(pair.pair_index)?,
+            timestamp: pair.timestamp,
+            transaction_hash: hash(pair.transaction_hash)?,
+            transaction_index: pair.transaction_index,
+        })
+    }
+}
+
+impl TryFrom<proto::PairInfo> for PairInfo {
+    type Error = Error;
+
+    fn try_from(info: proto::PairInfo) -> Result<Self> {
+        let pair = PairCreated::try_from(info.pair_created.ok_or(Error::UnexpectedMessageFormat)?)?;
+        Ok(Self {
+            block_number: pair.block_number,
+            factory: pair.factory,
+            pair: pair.pair,
+            token0: pair.token0,
+            token1: pair.token1,
+            pair_index: pair.pair_index,
+            timestamp: pair.timestamp,
+            transaction_hash: pair.transaction_hash,
+            transaction_index: pair.transaction_index,
+            symbol0: info.symbol0,
+            symbol1: info.symbol1,
+            name0: info.name0,
+            name1: info.name1,
+            decimals0: decimals(info.decimals0)?,
+            decimals1: decimals(info.decimals1)?,
+            reserve0: uint128(info.reserve0)?,
+            reserve1: uint128(info.reserve1)?,
+            reserves_block_number: info.reserves_block_number,
+        })
+    }
+}
+
+impl TryFrom<proto::Price> for Price {
+    type Error = Error;
+
+    fn try_from(price: proto::Price) -> Result<Self> {
+        let side = match proto::Side::from_i32(price.side) {
+            Some(proto::Side::Buy) => Side::Buy,
+            Some(proto::Side::Sell) => Side::Sell,
+            None => return Err(Error::UnexpectedMessageFormat),
+        };
+        Ok(Self {
+            block_number: price.block_number,
+            pair: address(price.pair)?,
+            sender: address(price.sender)?,
+            receiver: address(price.receiver)?,
+            price: price.price,
+            volume0: price.volume0,
+            volume1: price.volume1,
+            fixed0: uint256(price.fixed0)?,
+            fixed1: uint256(price.fixed1)?,
+            decimals0: decimals(price.decimals0)?,
+            decimals1: decimals(price.decimals1)?,
+            side,
+            timestamp: price.timestamp,
+            transaction_hash: hash(price.transaction_hash)?,
+            transaction_index: price.transaction_index,
+            wash_trade: price.wash_trade,
+            log_index: price.log_index,
+        })
+    }
+}
+
+impl TryFrom<proto::Reserves> for Reserves {
+    type Error = Error;
+
+    fn try_from(reserves: proto::Reserves) -> Result<Self> {
+        let event = match proto::EventType::from_i32(reserves.event) {
+            Some(proto::EventType::Mint) => Type::Mint,
+            Some(proto::EventType::Burn) => Type::Burn,
+            Some(proto::EventType::Swap) => Type::Swap,
+            Some(proto::EventType::Sync) => Type::Sync,
+            None => return Err(Error::UnexpectedMessageFormat),
+        };
+        Ok(Self {
+            event,
+            reserve0: uint128(reserves.reserve0)?,
+            reserve1: uint128(reserves.reserve1)?,
+            amount0: uint256(reserves.amount0)?,
+            amount1: uint256(reserves.amount1)?,
+            lp_amount: uint256(reserves.lp_amount)?,
+            protocol_fee: reserves.protocol_fee.map(uint256).transpose()?,
+            block_number: reserves.block_number,
+            pair: reserves.pair.map(address).transpose()?,
+            timestamp: reserves.timestamp,
+            transaction_hash: reserves.transaction_hash.map(hash).transpose()?,
+            transaction_index: reserves.transaction_index,
+            log_index: reserves.log_index,
+        })
+    }
+}
+
+fn address(bytes: Vec<u8>) -> Result<Address> {
+    if bytes.len() != Address::len_bytes() {
+        return Err(Error::UnexpectedMessageFormat);
+    }
+    Ok(Address::from_slice(&bytes))
+}
+
+fn hash(bytes: Vec<u8>) -> Result<H256> {
+    if bytes.len() != H256::len_bytes() {
+        return Err(Error::UnexpectedMessageFormat);
+    }
+    Ok(H256::from_slice(&bytes))
+}
+
+/// Decode a big-endian 256 bit integer
+fn uint256(bytes: Vec<u8>) -> Result<U256> {
+    if bytes.len() > 32 {
+        return Err(Error::UnexpectedMessageFormat);
+    }
+    Ok(U256::from_big_endian(&bytes))
+}
+
+/// Decode a big-endian 128 bit integer
+fn uint128(bytes: Vec<u8>) -> Result<u128> {
+    let mut buf = [0; 16];
+    let start = buf
+        .len()
+        .checked_sub(bytes.len())
+        .ok_or(Error::UnexpectedMessageFormat)?;
+    buf[start..].copy_from_slice(&bytes);
+    Ok(u128::from_be_bytes(buf))
+}
+
+fn decimals(decimals: u32) -> Result<u8> {
+    decimals
+        .try_into()
+        .map_err(|_| Error::UnexpectedMessageFormat)
+}
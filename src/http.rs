@@ -1,9 +1,23 @@
-use ethers::types::H160;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use ethers::types::{H160, H256};
 use futures::{Stream, StreamExt, TryStreamExt};
 
 use crate::{
-    types::{PairCreated, Price, Reserves},
-    Error, Result,
+    auth::{Credentials, TokenCache},
+    block_time::BlockTimeCache,
+    error,
+    telemetry::{self, Transport},
+    types::{
+        BridgeTransfer, CrossRate, Cursor, DepthLevel, Fees, Finality, FlashLoan, Interval,
+        LpSupply, MevEvent, PairCreated, PairInfo, Price, PriceOptions, RateLimit, ReserveOptions,
+        Reserves, StablecoinSupply, StakingDeposit, StakingWithdrawal, Trace, TransactionEvents,
+        Tvl, Usage, UsdPrice, Volume,
+    },
+    DecodePolicy, Error, ErrorContext, Result, ServerError, TimeoutPhase,
 };
 
 /// A Superchain HTTP client
@@ -11,6 +25,15 @@ pub struct Client {
     inner: reqwest::Client,
     headers: reqwest::header::HeaderMap,
     base_url: reqwest::Url,
+    block_times: BlockTimeCache,
+    sse: bool,
+    rate_limit: Mutex<Option<RateLimit>>,
+    retries: u32,
+    backoff: Duration,
+    timeout: Option<Duration>,
+    fast_decode: bool,
+    credentials: Mutex<Option<Credentials>>,
+    decode_policy: DecodePolicy,
 }
 
 impl Client {
@@ -23,7 +46,34 @@ impl Client {
             inner: client,
             headers: reqwest::header::HeaderMap::new(),
             base_url,
+            block_times: BlockTimeCache::default(),
+            sse: false,
+            rate_limit: Mutex::new(None),
+            retries: 0,
+            backoff: Duration::from_secs(1),
+            timeout: None,
+            fast_decode: false,
+            credentials: Mutex::new(None),
+            decode_policy: DecodePolicy::default(),
+        }
+    }
+
+    /// Create a new [`Client`] for the HTTP endpoint of `config`, authenticating with its
+    /// credentials
+    ///
+    /// The timeout and retries of `config` are applied as well. The timeout limits connecting
+    /// and the point queries, like [`Client::get_height`], but not streams, which may follow the
+    /// head for any time.
+    pub fn from_config(config: &crate::config::Config) -> Result<Self> {
+        let mut client = reqwest::Client::builder();
+        if let Some(timeout) = config.timeout {
+            client = client.connect_timeout(timeout);
         }
+        let mut client = Self::new(client.build()?, config.http_url.clone())
+            .with_retries(config.retries, config.retry_backoff)
+            .with_credentials(config.credentials());
+        client.timeout = config.timeout;
+        Ok(client)
     }
 
     /// Set the default headers provided for each request
@@ -34,6 +84,84 @@ impl Client {
         self
     }
 
+    /// Authenticate each request with `credentials`
+    ///
+    /// This replaces an `Authorization` default header. With tokens, a request rejected with `401`
+    /// is sent once more with a freshly fetched token.
+    pub fn with_credentials(self, credentials: Credentials) -> Self {
+        self.set_credentials(credentials);
+        self
+    }
+
+    /// Authenticate each request with a bearer token of `tokens`, see
+    /// [`Client::with_credentials`]
+    pub fn with_tokens(self, tokens: Arc<TokenCache>) -> Self {
+        self.with_credentials(Credentials::Tokens(tokens))
+    }
+
+    /// Switch to new `credentials`, e.g. after rotating a password
+    ///
+    /// Requests sent from now on use the new credentials, streams already open stay open.
+    pub fn set_credentials(&self, credentials: Credentials) {
+        *self.credentials.lock().expect("the lock is never poisoned") = Some(credentials);
+    }
+
+    /// A client issuing requests with `credentials` instead of the credentials of this client
+    ///
+    /// This allows a multi-tenant service to query on behalf of a single customer. The returned
+    /// client shares the connection pool and settings, but tracks the rate limit of `credentials`
+    /// separately.
+    pub fn authenticated_as(&self, credentials: Credentials) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            headers: self.headers.clone(),
+            base_url: self.base_url.clone(),
+            block_times: BlockTimeCache::default(),
+            sse: self.sse,
+            rate_limit: Mutex::new(None),
+            retries: self.retries,
+            backoff: self.backoff,
+            timeout: self.timeout,
+            fast_decode: self.fast_decode,
+            credentials: Mutex::new(Some(credentials)),
+            decode_policy: self.decode_policy.clone(),
+        }
+    }
+
+    /// Request streams as Server-Sent Events instead of CSV
+    ///
+    /// This keeps live streams flowing through proxies, that block WebSockets and buffer plain
+    /// long-lived responses. The yielded rows are the same in either mode.
+    pub fn with_sse(mut self) -> Self {
+        self.sse = true;
+        self
+    }
+
+    /// Set how rows, that fail to decode, are handled, defaults to [`DecodePolicy::FailFast`]
+    pub fn with_decode_policy(mut self, decode_policy: DecodePolicy) -> Self {
+        self.decode_policy = decode_policy;
+        self
+    }
+
+    /// Decode CSV streams with a single reused row buffer, instead of allocating per row
+    ///
+    /// This cuts the CPU time of high-throughput streams. The rows are the same either way, this
+    /// has no effect on Server-Sent Events.
+    pub fn with_fast_decode(mut self) -> Self {
+        self.fast_decode = true;
+        self
+    }
+
+    /// Set how often a failed request is retried, and the backoff before the first retry
+    ///
+    /// Requests are retried on connection errors, timeouts, `429` and `5xx` responses, with the
+    /// backoff doubling after each retry. By default, requests are not retried.
+    pub fn with_retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.retries = retries;
+        self.backoff = backoff;
+        self
+    }
+
     /// Get the uniswap v2 pair created event for the provided `pair`
     pub async fn get_pair_created(&self, pair: H160) -> Result<Option<PairCreated>> {
         self.get_pair_created_(format!("{:x}", pair)).await
@@ -71,19 +199,79 @@ impl Client {
         self.request(url).await?.next().await.transpose()
     }
 
+    /// Get the uniswap v2 pair created events of all pairs containing the provided `token`
+    ///
+    /// This yields the pairs of all indexed factories, where `token` is either `token0` or
+    /// `token1`.
+    pub async fn get_pairs_for_token(
+        &self,
+        token: H160,
+    ) -> Result<impl Stream<Item = Result<PairCreated>> + Send> {
+        let url = self
+            .base_url
+            .join("/api/eth/pairs/token/")?
+            .join(&format!("{:x}", token))?;
+        self.request(url).await
+    }
+
+    /// Get the uniswap v2 pair created event of the provided `pair`, joined with the symbols,
+    /// names and decimals of its tokens and its current reserves
+    pub async fn get_pair_info(&self, pair: H160) -> Result<Option<PairInfo>> {
+        let url = self
+            .base_url
+            .join("/api/eth/pair_info/")?
+            .join(&format!("{:x}", pair))?;
+        self.request(url).await?.next().await.transpose()
+    }
+
     /// Get the uniswap v2 prices for the provided `pair` within the specified `block_range`
     pub async fn get_prices_in_range(
         &self,
         pair: H160,
         block_range: std::ops::RangeInclusive<u64>,
     ) -> Result<impl Stream<Item = Result<Price>> + Send> {
-        self.get_prices(format!(
+        self.get_prices_in_range_with_options(pair, block_range, PriceOptions::default())
+            .await
+    }
+
+    /// Get the uniswap v2 prices like [`Client::get_prices_in_range`], additionally applying the
+    /// provided `options` gateway-side
+    pub async fn get_prices_in_range_with_options(
+        &self,
+        pair: H160,
+        block_range: std::ops::RangeInclusive<u64>,
+        options: PriceOptions,
+    ) -> Result<impl Stream<Item = Result<Price>> + Send> {
+        self.get_prices(
+            format!("{:x}/{}/{}", pair, block_range.start(), block_range.end()),
+            options,
+        )
+        .await
+    }
+
+    /// Get the uniswap v2 prices like [`Client::get_prices_in_range`], in batches of up to
+    /// `batch_size` prices
+    ///
+    /// The prices are decoded in place into each batch, which saves a lot of per-row overhead on
+    /// large backfills. Malformed rows are handled by the [`DecodePolicy`] of the client.
+    pub async fn get_prices_batched(
+        &self,
+        pair: H160,
+        block_range: std::ops::RangeInclusive<u64>,
+        batch_size: usize,
+    ) -> Result<impl Stream<Item = Result<Vec<Price>>> + Send> {
+        let url = self.base_url.join("/api/eth/prices/")?.join(&format!(
             "{:x}/{}/{}",
             pair,
             block_range.start(),
             block_range.end()
-        ))
-        .await
+        ))?;
+        let stream = self.request_batched(url, batch_size).await?;
+        Ok(stream.inspect_ok(|prices: &Vec<Price>| {
+            if let Some(price) = prices.last() {
+                telemetry::lag(Transport::Http, price.timestamp);
+            }
+        }))
     }
 
     /// Get the uniswap v2 prices for the provided `pair` `from_block` upwards following head
@@ -92,15 +280,52 @@ impl Client {
         pair: H160,
         from_block: u64,
     ) -> Result<impl Stream<Item = Result<Price>> + Send> {
-        self.get_prices(format!("{:x}/{}", pair, from_block)).await
+        self.get_prices_live_stream_with_options(pair, from_block, PriceOptions::default())
+            .await
+    }
+
+    /// Get the uniswap v2 prices like [`Client::get_prices_live_stream`], additionally applying
+    /// the provided `options` gateway-side
+    pub async fn get_prices_live_stream_with_options(
+        &self,
+        pair: H160,
+        from_block: u64,
+        options: PriceOptions,
+    ) -> Result<impl Stream<Item = Result<Price>> + Send> {
+        self.get_prices(format!("{:x}/{}", pair, from_block), options)
+            .await
+    }
+
+    /// Resume the uniswap v2 prices for the provided `pair` right after `cursor` following head
+    pub async fn get_prices_live_stream_after(
+        &self,
+        pair: H160,
+        cursor: Cursor,
+    ) -> Result<impl Stream<Item = Result<Price>> + Send> {
+        let stream = self.get_prices_live_stream(pair, cursor.block).await?;
+        Ok(stream.try_filter(move |price| futures::future::ready(cursor.precedes(price))))
+    }
+
+    /// Get the most recent uniswap v2 price quote of the provided `pair`
+    ///
+    /// Returns `None` if the pair was never traded.
+    pub async fn get_latest_price(&self, pair: H160) -> Result<Option<Price>> {
+        let url = self
+            .base_url
+            .join("/api/eth/latest_price/")?
+            .join(&format!("{:x}", pair))?;
+        self.request(url).await?.next().await.transpose()
     }
 
     async fn get_prices(
         &self,
         url_suffix: String,
+        options: PriceOptions,
     ) -> Result<impl Stream<Item = Result<Price>> + Send> {
-        let url = self.base_url.join("/api/eth/prices/")?.join(&url_suffix)?;
-        self.request(url).await
+        let mut url = self.base_url.join("/api/eth/prices/")?.join(&url_suffix)?;
+        options.append_query_pairs(&mut url);
+        let stream = self.request(url).await?;
+        Ok(stream.inspect_ok(|price: &Price| telemetry::lag(Transport::Http, price.timestamp)))
     }
 
     /// Get the uniswap v2 reserves for the provided `pair` within the specified `block_range`
@@ -109,12 +334,22 @@ impl Client {
         pair: H160,
         block_range: std::ops::RangeInclusive<u64>,
     ) -> Result<impl Stream<Item = Result<Reserves>> + Send> {
-        self.get_reserves(format!(
-            "{:x}/{}/{}",
-            pair,
-            block_range.start(),
-            block_range.end()
-        ))
+        self.get_reserves_in_range_with_options(pair, block_range, ReserveOptions::default())
+            .await
+    }
+
+    /// Get the uniswap v2 reserves like [`Client::get_reserves_in_range`], additionally applying
+    /// the provided `options` gateway-side
+    pub async fn get_reserves_in_range_with_options(
+        &self,
+        pair: H160,
+        block_range: std::ops::RangeInclusive<u64>,
+        options: ReserveOptions,
+    ) -> Result<impl Stream<Item = Result<Reserves>> + Send> {
+        self.get_reserves(
+            format!("{:x}/{}/{}", pair, block_range.start(), block_range.end()),
+            options,
+        )
         .await
     }
 
@@ -124,51 +359,957 @@ impl Client {
         pair: H160,
         from_block: u64,
     ) -> Result<impl Stream<Item = Result<Reserves>> + Send> {
-        self.get_reserves(format!("{:x}/{}", pair, from_block))
+        self.get_reserves_live_stream_with_options(pair, from_block, ReserveOptions::default())
+            .await
+    }
+
+    /// Resume the uniswap v2 reserves for the provided `pair` right after `cursor` following head
+    pub async fn get_reserves_live_stream_after(
+        &self,
+        pair: H160,
+        cursor: Cursor,
+    ) -> Result<impl Stream<Item = Result<Reserves>> + Send> {
+        let stream = self.get_reserves_live_stream(pair, cursor.block).await?;
+        Ok(stream.try_filter(move |reserves| futures::future::ready(cursor.precedes(reserves))))
+    }
+
+    /// Get the uniswap v2 reserves like [`Client::get_reserves_live_stream`], additionally
+    /// applying the provided `options` gateway-side
+    pub async fn get_reserves_live_stream_with_options(
+        &self,
+        pair: H160,
+        from_block: u64,
+        options: ReserveOptions,
+    ) -> Result<impl Stream<Item = Result<Reserves>> + Send> {
+        self.get_reserves(format!("{:x}/{}", pair, from_block), options)
             .await
     }
 
     async fn get_reserves(
         &self,
         url_suffix: String,
+        options: ReserveOptions,
     ) -> Result<impl Stream<Item = Result<Reserves>> + Send> {
-        let url = self
+        let mut url = self
             .base_url
             .join("/api/eth/reserves/")?
             .join(&url_suffix)?;
+        options.append_query_pairs(&mut url);
+        let stream = self.request(url).await?;
+        Ok(stream.inspect_ok(|reserves: &Reserves| {
+            if let Some(timestamp) = reserves.timestamp {
+                telemetry::lag(Transport::Http, timestamp);
+            }
+        }))
+    }
+
+    /// Get the stablecoin mint and burn events for the provided `token` within the specified
+    /// `block_range`
+    pub async fn get_stablecoin_supply_in_range(
+        &self,
+        token: H160,
+        block_range: std::ops::RangeInclusive<u64>,
+    ) -> Result<impl Stream<Item = Result<StablecoinSupply>> + Send> {
+        self.get_stablecoin_supply(format!(
+            "{:x}/{}/{}",
+            token,
+            block_range.start(),
+            block_range.end()
+        ))
+        .await
+    }
+
+    /// Get the stablecoin mint and burn events for the provided `token` `from_block` upwards
+    /// following head
+    pub async fn get_stablecoin_supply_live_stream(
+        &self,
+        token: H160,
+        from_block: u64,
+    ) -> Result<impl Stream<Item = Result<StablecoinSupply>> + Send> {
+        self.get_stablecoin_supply(format!("{:x}/{}", token, from_block))
+            .await
+    }
+
+    async fn get_stablecoin_supply(
+        &self,
+        url_suffix: String,
+    ) -> Result<impl Stream<Item = Result<StablecoinSupply>> + Send> {
+        let url = self
+            .base_url
+            .join("/api/eth/stablecoin_supply/")?
+            .join(&url_suffix)?;
+        self.request(url).await
+    }
+
+    /// Get the internal call traces made from or to the provided `address` within the specified
+    /// `block_range`
+    pub async fn get_traces_in_range(
+        &self,
+        address: H160,
+        block_range: std::ops::RangeInclusive<u64>,
+    ) -> Result<impl Stream<Item = Result<Trace>> + Send> {
+        self.get_traces(format!(
+            "{:x}/{}/{}",
+            address,
+            block_range.start(),
+            block_range.end()
+        ))
+        .await
+    }
+
+    /// Get the internal call traces made from or to the provided `address` `from_block` upwards
+    /// following head
+    pub async fn get_traces_live_stream(
+        &self,
+        address: H160,
+        from_block: u64,
+    ) -> Result<impl Stream<Item = Result<Trace>> + Send> {
+        self.get_traces(format!("{:x}/{}", address, from_block))
+            .await
+    }
+
+    async fn get_traces(
+        &self,
+        url_suffix: String,
+    ) -> Result<impl Stream<Item = Result<Trace>> + Send> {
+        let url = self.base_url.join("/api/eth/traces/")?.join(&url_suffix)?;
+        self.request(url).await
+    }
+
+    /// Get the USD denominated TVL for the provided `pair` within the specified `block_range`
+    pub async fn get_tvl_in_range(
+        &self,
+        pair: H160,
+        block_range: std::ops::RangeInclusive<u64>,
+    ) -> Result<impl Stream<Item = Result<Tvl>> + Send> {
+        self.get_tvl(format!(
+            "{:x}/{}/{}",
+            pair,
+            block_range.start(),
+            block_range.end()
+        ))
+        .await
+    }
+
+    /// Get the USD denominated TVL for the provided `pair` `from_block` upwards following head
+    pub async fn get_tvl_live_stream(
+        &self,
+        pair: H160,
+        from_block: u64,
+    ) -> Result<impl Stream<Item = Result<Tvl>> + Send> {
+        self.get_tvl(format!("{:x}/{}", pair, from_block)).await
+    }
+
+    async fn get_tvl(&self, url_suffix: String) -> Result<impl Stream<Item = Result<Tvl>> + Send> {
+        let url = self.base_url.join("/api/eth/tvl/")?.join(&url_suffix)?;
+        self.request(url).await
+    }
+
+    /// Get the LP token supply changes for the provided `pair` within the specified `block_range`
+    pub async fn get_lp_supply_in_range(
+        &self,
+        pair: H160,
+        block_range: std::ops::RangeInclusive<u64>,
+    ) -> Result<impl Stream<Item = Result<LpSupply>> + Send> {
+        self.get_lp_supply(format!(
+            "{:x}/{}/{}",
+            pair,
+            block_range.start(),
+            block_range.end()
+        ))
+        .await
+    }
+
+    /// Get the LP token supply changes for the provided `pair` `from_block` upwards following head
+    pub async fn get_lp_supply_live_stream(
+        &self,
+        pair: H160,
+        from_block: u64,
+    ) -> Result<impl Stream<Item = Result<LpSupply>> + Send> {
+        self.get_lp_supply(format!("{:x}/{}", pair, from_block))
+            .await
+    }
+
+    async fn get_lp_supply(
+        &self,
+        url_suffix: String,
+    ) -> Result<impl Stream<Item = Result<LpSupply>> + Send> {
+        let url = self
+            .base_url
+            .join("/api/eth/lp_supply/")?
+            .join(&url_suffix)?;
         self.request(url).await
     }
 
+    /// Get the Aave flash loans of the provided `asset` within the specified `block_range`
+    pub async fn get_flash_loans_in_range(
+        &self,
+        asset: H160,
+        block_range: std::ops::RangeInclusive<u64>,
+    ) -> Result<impl Stream<Item = Result<FlashLoan>> + Send> {
+        self.get_flash_loans(format!(
+            "{:x}/{}/{}",
+            asset,
+            block_range.start(),
+            block_range.end()
+        ))
+        .await
+    }
+
+    /// Get the Aave flash loans of the provided `asset` `from_block` upwards following head
+    pub async fn get_flash_loans_live_stream(
+        &self,
+        asset: H160,
+        from_block: u64,
+    ) -> Result<impl Stream<Item = Result<FlashLoan>> + Send> {
+        self.get_flash_loans(format!("{:x}/{}", asset, from_block))
+            .await
+    }
+
+    async fn get_flash_loans(
+        &self,
+        url_suffix: String,
+    ) -> Result<impl Stream<Item = Result<FlashLoan>> + Send> {
+        let url = self
+            .base_url
+            .join("/api/eth/flash_loans/")?
+            .join(&url_suffix)?;
+        self.request(url).await
+    }
+
+    /// Get the MEV events labeled by the gateway for the provided `pair` within the specified
+    /// `block_range`
+    pub async fn get_mev_events_in_range(
+        &self,
+        pair: H160,
+        block_range: std::ops::RangeInclusive<u64>,
+    ) -> Result<impl Stream<Item = Result<MevEvent>> + Send> {
+        self.get_mev_events(format!(
+            "{:x}/{}/{}",
+            pair,
+            block_range.start(),
+            block_range.end()
+        ))
+        .await
+    }
+
+    /// Get the MEV events labeled by the gateway for the provided `pair` `from_block` upwards
+    /// following head
+    pub async fn get_mev_events_live_stream(
+        &self,
+        pair: H160,
+        from_block: u64,
+    ) -> Result<impl Stream<Item = Result<MevEvent>> + Send> {
+        self.get_mev_events(format!("{:x}/{}", pair, from_block))
+            .await
+    }
+
+    async fn get_mev_events(
+        &self,
+        url_suffix: String,
+    ) -> Result<impl Stream<Item = Result<MevEvent>> + Send> {
+        let url = self.base_url.join("/api/eth/mev/")?.join(&url_suffix)?;
+        self.request(url).await
+    }
+
+    /// Get the cross-chain bridge deposits and withdrawals of the provided `token` within the
+    /// specified `block_range`
+    pub async fn get_bridge_transfers_in_range(
+        &self,
+        token: H160,
+        block_range: std::ops::RangeInclusive<u64>,
+    ) -> Result<impl Stream<Item = Result<BridgeTransfer>> + Send> {
+        self.get_bridge_transfers(format!(
+            "{:x}/{}/{}",
+            token,
+            block_range.start(),
+            block_range.end()
+        ))
+        .await
+    }
+
+    /// Get the cross-chain bridge deposits and withdrawals of the provided `token` `from_block`
+    /// upwards following head
+    pub async fn get_bridge_transfers_live_stream(
+        &self,
+        token: H160,
+        from_block: u64,
+    ) -> Result<impl Stream<Item = Result<BridgeTransfer>> + Send> {
+        self.get_bridge_transfers(format!("{:x}/{}", token, from_block))
+            .await
+    }
+
+    async fn get_bridge_transfers(
+        &self,
+        url_suffix: String,
+    ) -> Result<impl Stream<Item = Result<BridgeTransfer>> + Send> {
+        let url = self
+            .base_url
+            .join("/api/eth/bridge_transfers/")?
+            .join(&url_suffix)?;
+        self.request(url).await
+    }
+
+    /// Get the beacon chain staking deposits of the provided `validator_index` within the specified
+    /// `block_range`
+    pub async fn get_staking_deposits_in_range(
+        &self,
+        validator_index: u64,
+        block_range: std::ops::RangeInclusive<u64>,
+    ) -> Result<impl Stream<Item = Result<StakingDeposit>> + Send> {
+        self.get_staking_deposits(format!(
+            "{}/{}/{}",
+            validator_index,
+            block_range.start(),
+            block_range.end()
+        ))
+        .await
+    }
+
+    /// Get the beacon chain staking deposits of the provided `validator_index` `from_block` upwards
+    /// following head
+    pub async fn get_staking_deposits_live_stream(
+        &self,
+        validator_index: u64,
+        from_block: u64,
+    ) -> Result<impl Stream<Item = Result<StakingDeposit>> + Send> {
+        self.get_staking_deposits(format!("{}/{}", validator_index, from_block))
+            .await
+    }
+
+    async fn get_staking_deposits(
+        &self,
+        url_suffix: String,
+    ) -> Result<impl Stream<Item = Result<StakingDeposit>> + Send> {
+        let url = self
+            .base_url
+            .join("/api/eth/staking/deposits/")?
+            .join(&url_suffix)?;
+        self.request(url).await
+    }
+
+    /// Get the beacon chain staking withdrawals of the provided `validator_index` within the
+    /// specified `block_range`
+    pub async fn get_staking_withdrawals_in_range(
+        &self,
+        validator_index: u64,
+        block_range: std::ops::RangeInclusive<u64>,
+    ) -> Result<impl Stream<Item = Result<StakingWithdrawal>> + Send> {
+        self.get_staking_withdrawals(format!(
+            "{}/{}/{}",
+            validator_index,
+            block_range.start(),
+            block_range.end()
+        ))
+        .await
+    }
+
+    /// Get the beacon chain staking withdrawals of the provided `validator_index` `from_block`
+    /// upwards following head
+    pub async fn get_staking_withdrawals_live_stream(
+        &self,
+        validator_index: u64,
+        from_block: u64,
+    ) -> Result<impl Stream<Item = Result<StakingWithdrawal>> + Send> {
+        self.get_staking_withdrawals(format!("{}/{}", validator_index, from_block))
+            .await
+    }
+
+    async fn get_staking_withdrawals(
+        &self,
+        url_suffix: String,
+    ) -> Result<impl Stream<Item = Result<StakingWithdrawal>> + Send> {
+        let url = self
+            .base_url
+            .join("/api/eth/staking/withdrawals/")?
+            .join(&url_suffix)?;
+        self.request(url).await
+    }
+
+    /// Get the accrued swap fees per block for the provided `pair` within the specified
+    /// `block_range`
+    pub async fn get_fees_in_range(
+        &self,
+        pair: H160,
+        block_range: std::ops::RangeInclusive<u64>,
+    ) -> Result<impl Stream<Item = Result<Fees>> + Send> {
+        self.get_fees(format!(
+            "{:x}/{}/{}",
+            pair,
+            block_range.start(),
+            block_range.end()
+        ))
+        .await
+    }
+
+    /// Get the accrued swap fees per block for the provided `pair` `from_block` upwards following
+    /// head
+    pub async fn get_fees_live_stream(
+        &self,
+        pair: H160,
+        from_block: u64,
+    ) -> Result<impl Stream<Item = Result<Fees>> + Send> {
+        self.get_fees(format!("{:x}/{}", pair, from_block)).await
+    }
+
+    async fn get_fees(
+        &self,
+        url_suffix: String,
+    ) -> Result<impl Stream<Item = Result<Fees>> + Send> {
+        let url = self.base_url.join("/api/eth/fees/")?.join(&url_suffix)?;
+        self.request(url).await
+    }
+
+    /// Get the USD prices for the provided `token` within the specified `block_range`
+    pub async fn get_usd_prices_in_range(
+        &self,
+        token: H160,
+        block_range: std::ops::RangeInclusive<u64>,
+    ) -> Result<impl Stream<Item = Result<UsdPrice>> + Send> {
+        self.get_usd_prices(format!(
+            "{:x}/{}/{}",
+            token,
+            block_range.start(),
+            block_range.end()
+        ))
+        .await
+    }
+
+    /// Get the USD prices for the provided `token` `from_block` upwards following head
+    pub async fn get_usd_prices_live_stream(
+        &self,
+        token: H160,
+        from_block: u64,
+    ) -> Result<impl Stream<Item = Result<UsdPrice>> + Send> {
+        self.get_usd_prices(format!("{:x}/{}", token, from_block))
+            .await
+    }
+
+    async fn get_usd_prices(
+        &self,
+        url_suffix: String,
+    ) -> Result<impl Stream<Item = Result<UsdPrice>> + Send> {
+        let url = self
+            .base_url
+            .join("/api/eth/usd_prices/")?
+            .join(&url_suffix)?;
+        self.request(url).await
+    }
+
+    /// Get the synthetic exchange rates of `token_a` in `token_b` within the specified
+    /// `block_range`
+    pub async fn get_cross_rates_in_range(
+        &self,
+        token_a: H160,
+        token_b: H160,
+        block_range: std::ops::RangeInclusive<u64>,
+    ) -> Result<impl Stream<Item = Result<CrossRate>> + Send> {
+        self.get_cross_rates(format!(
+            "{:x}/{:x}/{}/{}",
+            token_a,
+            token_b,
+            block_range.start(),
+            block_range.end()
+        ))
+        .await
+    }
+
+    /// Get the synthetic exchange rates of `token_a` in `token_b` `from_block` upwards following
+    /// head
+    pub async fn get_cross_rates_live_stream(
+        &self,
+        token_a: H160,
+        token_b: H160,
+        from_block: u64,
+    ) -> Result<impl Stream<Item = Result<CrossRate>> + Send> {
+        self.get_cross_rates(format!("{:x}/{:x}/{}", token_a, token_b, from_block))
+            .await
+    }
+
+    async fn get_cross_rates(
+        &self,
+        url_suffix: String,
+    ) -> Result<impl Stream<Item = Result<CrossRate>> + Send> {
+        let url = self
+            .base_url
+            .join("/api/eth/cross_rates/")?
+            .join(&url_suffix)?;
+        self.request(url).await
+    }
+
+    /// Get all uniswap v2 pair created events, price quotes and reserves emitted by the
+    /// transaction with the provided `transaction_hash`
+    pub async fn get_events_for_tx(&self, transaction_hash: H256) -> Result<TransactionEvents> {
+        let (pairs_created, prices, reserves) = futures::try_join!(
+            self.get_transaction_events(transaction_hash, "pairs"),
+            self.get_transaction_events(transaction_hash, "prices"),
+            self.get_transaction_events(transaction_hash, "reserves"),
+        )?;
+
+        Ok(TransactionEvents {
+            pairs_created,
+            prices,
+            reserves,
+        })
+    }
+
+    async fn get_transaction_events<T>(
+        &self,
+        transaction_hash: H256,
+        events: &str,
+    ) -> Result<Vec<T>>
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        let url = self
+            .base_url
+            .join("/api/eth/tx/")?
+            .join(&format!("{:x}/{}", transaction_hash, events))?;
+        self.request(url).await?.try_collect().await
+    }
+
+    /// Get the traded volume per `interval` for the provided `pair` within the specified
+    /// `block_range`
+    pub async fn get_volume_in_range(
+        &self,
+        pair: H160,
+        interval: Interval,
+        block_range: std::ops::RangeInclusive<u64>,
+    ) -> Result<impl Stream<Item = Result<Volume>> + Send> {
+        self.get_volume(
+            format!("{:x}/{}/{}", pair, block_range.start(), block_range.end()),
+            interval,
+        )
+        .await
+    }
+
+    /// Get the traded volume per `interval` for the provided `pair` `from_block` upwards
+    /// following head
+    pub async fn get_volume_live_stream(
+        &self,
+        pair: H160,
+        interval: Interval,
+        from_block: u64,
+    ) -> Result<impl Stream<Item = Result<Volume>> + Send> {
+        self.get_volume(format!("{:x}/{}", pair, from_block), interval)
+            .await
+    }
+
+    async fn get_volume(
+        &self,
+        url_suffix: String,
+        interval: Interval,
+    ) -> Result<impl Stream<Item = Result<Volume>> + Send> {
+        let mut url = self.base_url.join("/api/eth/volume/")?.join(&url_suffix)?;
+        interval.append_query_pairs(&mut url);
+        self.request(url).await
+    }
+
+    /// Get the liquidity depth of the provided `pair` at the provided slippage `levels`, given in
+    /// basis points
+    ///
+    /// The depth is computed for both swap directions from the reserves as of `at_block`. An
+    /// `at_block` of `None` uses the latest reserves.
+    pub async fn get_depth(
+        &self,
+        pair: H160,
+        at_block: Option<u64>,
+        levels: impl IntoIterator<Item = u32>,
+    ) -> Result<Vec<DepthLevel>> {
+        let url_suffix = match at_block {
+            Some(block) => format!("{:x}/{}", pair, block),
+            None => format!("{:x}", pair),
+        };
+        let mut url = self.base_url.join("/api/eth/depth/")?.join(&url_suffix)?;
+        url.query_pairs_mut()
+            .extend_pairs(levels.into_iter().map(|level| ("level", level.to_string())));
+        self.request(url).await?.try_collect().await
+    }
+
+    /// Get the finality status of the provided `block`
+    ///
+    /// Returns `None` if the block is not indexed yet.
+    pub async fn get_finality(&self, block: u64) -> Result<Option<Finality>> {
+        let url = self
+            .base_url
+            .join("/api/eth/finality/")?
+            .join(&block.to_string())?;
+        self.request(url).await?.next().await.transpose()
+    }
+
+    /// Get the usage of the API key in the current billing period
+    pub async fn get_usage(&self) -> Result<Usage> {
+        let url = self.base_url.join("/api/usage")?;
+        self.request_json(url).await
+    }
+
+    /// The rate limit reported by the gateway with the latest response
+    ///
+    /// Returns `None` before the first response, or if the gateway didn't report a rate limit.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        *self.rate_limit.lock().expect("the lock is never poisoned")
+    }
+
     pub async fn get_height(&self) -> Result<u64> {
-        let height = self
-            .inner
-            .get("/api/eth/height")
-            .send()
-            .await?
-            .error_for_status()?
-            .json::<u64>()
-            .await?;
-        Ok(height)
+        let url = self.base_url.join("/api/eth/height")?;
+        self.request_json(url).await
+    }
+
+    /// Get the height of the latest finalized block
+    ///
+    /// Everything up to and including this block is settled and can't be reorged anymore.
+    pub async fn finalized_height(&self) -> Result<u64> {
+        let url = self.base_url.join("/api/eth/height/finalized")?;
+        self.request_json(url).await
+    }
+
+    /// Get the number of the latest block with a timestamp at or before the provided `timestamp`
+    ///
+    /// Resolved mappings of finalized blocks are cached, so repeated lookups don't hit the gateway.
+    pub async fn block_for_timestamp(&self, timestamp: i64) -> Result<u64> {
+        if let Some(block) = self.block_times.block(timestamp) {
+            return Ok(block);
+        }
+
+        let url = self
+            .base_url
+            .join("/api/eth/block_for_timestamp/")?
+            .join(&timestamp.to_string())?;
+        let block = self.request_json(url).await?;
+        // The answer is only settled once the following block is final, too
+        if self.is_finalized(block.saturating_add(1)).await {
+            self.block_times.insert_block(timestamp, block);
+        }
+        Ok(block)
+    }
+
+    /// Get the timestamp of the provided `block`
+    ///
+    /// Resolved mappings of finalized blocks are cached, so repeated lookups don't hit the gateway.
+    pub async fn timestamp_for_block(&self, block: u64) -> Result<i64> {
+        if let Some(timestamp) = self.block_times.timestamp(block) {
+            return Ok(timestamp);
+        }
+
+        let url = self
+            .base_url
+            .join("/api/eth/timestamp_for_block/")?
+            .join(&block.to_string())?;
+        let timestamp = self.request_json(url).await?;
+        if self.is_finalized(block).await {
+            self.block_times.insert_timestamp(block, timestamp);
+        }
+        Ok(timestamp)
+    }
+
+    /// Whether `block` is finalized, refreshing the known finalized height if necessary
+    async fn is_finalized(&self, block: u64) -> bool {
+        if self.block_times.is_finalized(block) {
+            return true;
+        }
+        match self.finalized_height().await {
+            Ok(height) => {
+                self.block_times.set_finalized(height);
+                block <= height
+            }
+            Err(_) => false,
+        }
+    }
+
+    async fn request_json<T>(&self, url: url::Url) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        telemetry::request(Transport::Http);
+        let context = ErrorContext::default().with_endpoint(url.as_str());
+        let mut request = self.inner.get(url).headers(self.headers.clone());
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        let value = async { Ok(self.send(request).await?.json::<T>().await?) }
+            .await
+            .map_err(|err: Error| error::observed(err.with_context(context)))?;
+        Ok(value)
     }
 
     async fn request<T>(&self, url: url::Url) -> Result<impl Stream<Item = Result<T>> + Send>
     where
-        T: serde::de::DeserializeOwned + 'static,
+        T: serde::de::DeserializeOwned + Send + 'static,
     {
-        let raw_data_stream = self
-            .inner
-            .get(url)
-            .headers(self.headers.clone())
-            .send()
-            .await?
-            .error_for_status()?
+        let context = ErrorContext::default().with_endpoint(url.as_str());
+        let body = self.body(url, self.sse, &context).await?;
+
+        let stream = if self.sse {
+            crate::sse::rows(body).left_stream().left_stream()
+        } else if self.fast_decode {
+            crate::decode::reusing_rows(body)
+                .right_stream()
+                .left_stream()
+        } else {
+            csv_async::AsyncDeserializer::from_reader(body)
+                .into_deserialize()
+                .map_err(Error::from)
+                .into_stream()
+                .right_stream()
+        };
+        let stream = stream
+            .map_err(move |err| error::observed(err.with_context(context.clone())))
+            .inspect(|row| telemetry::decoded(Transport::Http, row));
+        Ok(self.decode_policy.apply(stream))
+    }
+
+    /// Like [`Client::request`], but decoding the rows in batches of up to `batch_size` rows
+    ///
+    /// Batches are always decoded from CSV, also with [`Client::with_sse`].
+    async fn request_batched<T>(
+        &self,
+        url: url::Url,
+        batch_size: usize,
+    ) -> Result<impl Stream<Item = Result<Vec<T>>> + Send>
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        let context = ErrorContext::default().with_endpoint(url.as_str());
+        let body = self.body(url, false, &context).await?;
+        let policy = self.decode_policy.clone();
+        Ok(
+            crate::decode::reusing_batches(body, batch_size, Transport::Http, policy)
+                .map_err(move |err| error::observed(err.with_context(context.clone()))),
+        )
+    }
+
+    /// Send a `GET` request to `url` and read the body of the response, as CSV or with `sse` as
+    /// Server-Sent Events
+    async fn body(
+        &self,
+        url: url::Url,
+        sse: bool,
+        context: &ErrorContext,
+    ) -> Result<impl futures::AsyncBufRead + Unpin + Send> {
+        telemetry::request(Transport::Http);
+        let mut request = self.inner.get(url).headers(self.headers.clone());
+        if sse {
+            request = request.header(reqwest::header::ACCEPT, "text/event-stream");
+        }
+        let body = self
+            .send(request)
+            .await
+            .map_err(|err| error::observed(err.with_context(context.clone())))?
             .bytes_stream()
-            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err));
+            .inspect_ok(|bytes| telemetry::bytes(Transport::Http, bytes.len()))
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))
+            .into_async_read();
+        Ok(body)
+    }
+
+    /// Send `request`, retrying as configured, and fail on an error status
+    ///
+    /// A structured error in the body of an error response is returned as [`Error::Server`].
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let mut backoff = self.backoff;
+        let mut attempts = 0;
+        let mut refreshed = false;
+        loop {
+            let mut attempt = request
+                .try_clone()
+                .expect("requests without a body can be cloned");
+            let credentials = self
+                .credentials
+                .lock()
+                .expect("the lock is never poisoned")
+                .clone();
+            if let Some(credentials) = &credentials {
+                let authorization = credentials
+                    .authorization()
+                    .await?
+                    .parse()
+                    .map_err(|_| Error::Custom("invalid authorization value".to_owned()))?;
+                let mut headers = reqwest::header::HeaderMap::new();
+                headers.insert(reqwest::header::AUTHORIZATION, authorization);
+                // replaces the default header
+                attempt = attempt.headers(headers);
+            }
+            let started = std::time::Instant::now();
+            let response = attempt.send().await;
+            if let (Ok(response), Some(Credentials::Tokens(tokens))) = (&response, &credentials) {
+                if response.status() == reqwest::StatusCode::UNAUTHORIZED && !refreshed {
+                    if let Err(err) = response.error_for_status_ref() {
+                        error::observe(&err.into());
+                    }
+                    tokens.invalidate().await;
+                    refreshed = true;
+                    continue;
+                }
+            }
+
+            attempts += 1;
+            let retry_after = response.as_ref().ok().and_then(retry_after);
+            let retryable = match &response {
+                Ok(response) => {
+                    self.record_rate_limit(response);
+                    let status = response.status();
+                    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+                }
+                Err(err) => err.is_connect() || err.is_timeout(),
+            };
+            let response = response.map_err(|err| self.request_error(err, started.elapsed()));
+            if !retryable || attempts > self.retries {
+                let response = response?;
+                if let Err(err) = response.error_for_status_ref() {
+                    let status = response.status().as_u16();
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(match ServerError::parse(&body) {
+                        Some(server) => Error::from_server_error(ServerError {
+                            status: Some(status),
+                            ..server
+                        }),
+                        None => err.into(),
+                    });
+                }
+                return Ok(response);
+            }
+            if let Err(err) = response.and_then(|response| Ok(response.error_for_status()?)) {
+                error::observe(&err);
+            }
+            tokio::time::sleep(backoff.max(retry_after.unwrap_or_default())).await;
+            backoff *= 2;
+        }
+    }
+
+    /// The error of a request failing after `elapsed`, an [`Error::Timeout`] if it timed out
+    fn request_error(&self, err: reqwest::Error, elapsed: Duration) -> Error {
+        if !err.is_timeout() {
+            return err.into();
+        }
+        let phase = if err.is_connect() {
+            TimeoutPhase::Connect
+        } else {
+            TimeoutPhase::Total
+        };
+        Error::Timeout {
+            phase,
+            elapsed,
+            limit: self.timeout,
+        }
+    }
+
+    /// Remember the rate limit reported with `response`, if any
+    fn record_rate_limit(&self, response: &reqwest::Response) {
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok())
+        };
+        if let (Some(limit), Some(remaining), Some(reset)) = (
+            header("x-ratelimit-limit"),
+            header("x-ratelimit-remaining"),
+            header("x-ratelimit-reset"),
+        ) {
+            *self.rate_limit.lock().expect("the lock is never poisoned") = Some(RateLimit {
+                limit,
+                remaining,
+                reset: reset as i64,
+            });
+        }
+    }
+}
+
+/// The delay the gateway asked for before retrying, with a `Retry-After` header in seconds
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let value = response.headers().get(reqwest::header::RETRY_AFTER)?;
+    let seconds = value.to_str().ok()?.trim().parse().ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+
+    use super::*;
+    use crate::config::Config;
+
+    /// A server accepting connections, but never responding
+    fn silent_server() -> (std::net::TcpListener, reqwest::Url) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        (listener, url.parse().unwrap())
+    }
+
+    fn client(url: reqwest::Url) -> Client {
+        let config = Config::builder()
+            .with_username("user")
+            .with_password("password")
+            .with_http_url(url)
+            .with_timeout(Duration::from_millis(100))
+            .build()
+            .unwrap();
+        Client::from_config(&config).unwrap()
+    }
+
+    /// A server answering one request per connection with each of `responses`, in order
+    fn scripted_server(responses: Vec<&'static str>) -> reqwest::Url {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        url.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn waits_as_long_as_the_gateway_asks() {
+        let url = scripted_server(vec![
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 1\r\nContent-Length: 0\r\n\
+             Connection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\
+             Connection: close\r\n\r\n42",
+        ]);
+        let config = Config::builder()
+            .with_username("user")
+            .with_password("password")
+            .with_http_url(url)
+            .with_retries(1, Duration::from_millis(10))
+            .build()
+            .unwrap();
+        let client = Client::from_config(&config).unwrap();
+
+        let started = std::time::Instant::now();
+        assert_eq!(client.get_height().await.unwrap(), 42);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn times_out_point_queries() {
+        let (_listener, url) = silent_server();
+        let err = client(url).get_height().await.unwrap_err();
+        assert!(matches!(
+            err.inner(),
+            Error::Timeout {
+                phase: TimeoutPhase::Total,
+                ..
+            }
+        ));
+    }
 
-        let stream = csv_async::AsyncDeserializer::from_reader(raw_data_stream.into_async_read())
-            .into_deserialize()
-            .map_err(Error::from)
-            .into_stream();
-        Ok(stream)
+    #[tokio::test]
+    async fn keeps_streams_waiting() {
+        let (_listener, url) = silent_server();
+        let client = client(url);
+        let stream = client.get_prices_live_stream(H160::zero(), 0);
+        let waited = tokio::time::timeout(Duration::from_millis(300), stream).await;
+        assert!(
+            waited.is_err(),
+            "the stream gave up waiting for its response"
+        );
     }
 }
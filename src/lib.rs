@@ -10,40 +10,227 @@
 //! This crates allows you to easily use the Superchain API. Both the WebSocket endpoints and the
 //! HTTP endpoints.
 //!
+//! ### Features
+//! The [`HttpClient`] is gated behind the `http` feature and the [`WsClient`] behind the `ws`
+//! feature, both are enabled by default. Without them, only the types, adapters and config are
+//! compiled, which is all that is needed to process data fetched elsewhere.
+//!
+//! The `grpc` feature adds the `GrpcClient`, which queries the gRPC interface of the gateway with
+//! tonic and streams prices and reserves with server-streaming calls. Generating its client
+//! requires `protoc` at build time.
+//!
+//! The `cli` feature builds the `sc-cli` binary, which pulls pairs, prices, reserves and the
+//! height as CSV or JSON, e.g. `sc-cli prices --pair <pair> --from-block 15000000`.
+//!
+//! The `metrics` feature records requests, rows, bytes, decode errors, reconnects and the lag of
+//! streamed rows with the `metrics` crate, ready to be exported to e.g. Prometheus. See
+//! `describe_metrics` for all metric names. To capture errors, including the ones retried
+//! internally, install an observer with [`set_error_observer`].
+//!
+//! The `toml` and `yaml` features enable reading the `config::Config` from TOML and YAML files,
+//! JSON files are always supported. Instead of basic auth, the clients authenticate with
+//! refreshed bearer tokens of an [`auth::TokenProvider`], e.g. `auth::OAuth2`. The
+//! [`auth::Credentials`] are rotated at runtime with `set_credentials` of the clients. With the
+//! `keyring` feature, credentials are kept in the OS keyring instead of plaintext `.env` files.
+//!
 //! ### API overview
 //! There are two ways to interface with Superchain: HTTP and WebSocket
 //!
+//! For most use cases [`Client::connect`] is all you need. It hides the two transports, by
+//! streaming via WebSocket and running point queries via HTTP. With the `blocking` feature,
+//! `blocking::Client` offers the same without an async runtime.
+//!
 //! The WebSocket interface is a lot more flexible and powerful, while also being simpler, so use
-//! this one whenever you can.
+//! this one whenever you can. Rows, that fail to decode, are yielded as errors, unless a
+//! `DecodePolicy` set with `with_decode_policy` of either client skips them. For high-throughput
+//! streams, `with_fast_decode` decodes all rows into a single reused buffer, while
+//! `get_prices_batched` yields the prices in batches, which cuts the per-row overhead of backfills.
+//!
+//! Both clients implement the [`SuperchainProvider`] trait, so code can be written against either
+//! transport. The [`FailoverClient`] implements it as well, preferring WebSocket and falling back
+//! to HTTP during outages. To test code without network access, the [`MockClient`] serves
+//! canned data through the same trait. The [`cassette`] module records the responses of any
+//! provider to a file and serves them back. With the `cache` feature, `cache::CachedClient`
+//! keeps historical rows in a local SQLite database, so only missing ranges hit the gateway.
+//! With the `parquet` feature, `backfill::Backfill` bulk exports historical rows of many pairs
+//! to partitioned Parquet files.
+//! With the `server` feature, `server::ReplayServer` serves the data of any provider over the
+//! gateway protocols, so apps and examples run completely offline.
+//! Stream positions are persisted across restarts with a [`store::CheckpointStore`], and whole
+//! subscriptions are restored on startup with a [`subscriptions::SubscriptionManager`]. Several
+//! API keys are shared between tenants with a [`manager::ClientManager`], that tracks their
+//! quotas. Transforms applied to every stream of a provider are registered once with a
+//! [`middleware::Pipeline`].
+//! A [`shutdown::Shutdown`] ends all active streams cleanly, e.g. on pod termination.
+//! The [`sinks`] deliver streams to external systems, e.g. Kafka with the `kafka` feature,
+//! Postgres with the `postgres` feature, Redis with the `redis` feature or HTTP webhooks with the
+//! `webhook` feature.
+//!
+//! With the `wasm` feature, `WsClient::connect_browser` streams via the WebSocket API of the
+//! browser instead of tokio-tungstenite, for dashboards compiled to WebAssembly. Where WebSockets
+//! are blocked, [`HttpClient::with_sse`] streams via Server-Sent Events instead.
 //!
 //! #### HTTP
 //!
 //! - [`HttpClient::get_pair_created`]\: Get the PairCreated event for a pair from the entire eth history
 //! - [`HttpClient::get_pair_created_in_range`]\: Get the PairCreated event for a pair from the provided block range
 //! - [`HttpClient::get_pair_created_live_stream`]\: Get the PairCreated event for a pair from the provided block and keep streaming from head
+//! - [`HttpClient::get_pairs_for_token`]\: Get the PairCreated events of all pairs containing a token
+//! - [`HttpClient::get_pair_info`]\: Get the PairCreated event of a pair joined with its token metadata and current reserves
 //! - [`HttpClient::get_prices_in_range`]\: Get all price quotes for a pair from the provided block range
 //! - [`HttpClient::get_prices_live_stream`]\: Get all price quotes for a pair from the provided block range and keep streaming from head
+//! - [`HttpClient::get_prices_live_stream_after`]\: Resume all price quotes for a pair right after a cursor and keep streaming from head
+//! - [`HttpClient::get_latest_price`]\: Get the most recent price quote of a pair
+//! - [`HttpClient::get_reserves_live_stream_after`]\: Resume all reserves for a pair right after a cursor and keep streaming from head
+//! - [`HttpClient::get_stablecoin_supply_in_range`]\: Get all mint and burn events for a stablecoin from the provided block range
+//! - [`HttpClient::get_stablecoin_supply_live_stream`]\: Get all mint and burn events for a stablecoin from the provided block and keep streaming from head
+//! - [`HttpClient::get_traces_in_range`]\: Get all internal calls of an address from the provided block range
+//! - [`HttpClient::get_traces_live_stream`]\: Get all internal calls of an address from the provided block and keep streaming from head
+//! - [`HttpClient::get_tvl_in_range`]\: Get the USD TVL of a pair from the provided block range
+//! - [`HttpClient::get_tvl_live_stream`]\: Get the USD TVL of a pair from the provided block and keep streaming from head
+//! - [`HttpClient::get_lp_supply_in_range`]\: Get all LP token supply changes of a pair from the provided block range
+//! - [`HttpClient::get_lp_supply_live_stream`]\: Get all LP token supply changes of a pair from the provided block and keep streaming from head
+//! - [`HttpClient::get_flash_loans_in_range`]\: Get all Aave flash loans of an asset from the provided block range
+//! - [`HttpClient::get_flash_loans_live_stream`]\: Get all Aave flash loans of an asset from the provided block and keep streaming from head
+//! - [`HttpClient::get_mev_events_in_range`]\: Get all labeled MEV events of a pair from the provided block range
+//! - [`HttpClient::get_mev_events_live_stream`]\: Get all labeled MEV events of a pair from the provided block and keep streaming from head
+//! - [`HttpClient::get_bridge_transfers_in_range`]\: Get all bridge transfers of a token from the provided block range
+//! - [`HttpClient::get_bridge_transfers_live_stream`]\: Get all bridge transfers of a token from the provided block and keep streaming from head
+//! - [`HttpClient::get_staking_deposits_in_range`]\: Get all staking deposits of a validator from the provided block range
+//! - [`HttpClient::get_staking_deposits_live_stream`]\: Get all staking deposits of a validator from the provided block and keep streaming from head
+//! - [`HttpClient::get_staking_withdrawals_in_range`]\: Get all staking withdrawals of a validator from the provided block range
+//! - [`HttpClient::get_staking_withdrawals_live_stream`]\: Get all staking withdrawals of a validator from the provided block and keep streaming from head
+//! - [`HttpClient::get_fees_in_range`]\: Get the accrued swap fees of a pair per block from the provided block range
+//! - [`HttpClient::get_fees_live_stream`]\: Get the accrued swap fees of a pair per block from the provided block and keep streaming from head
+//! - [`HttpClient::get_usd_prices_in_range`]\: Get the USD prices of a token from the provided block range
+//! - [`HttpClient::get_usd_prices_live_stream`]\: Get the USD prices of a token from the provided block and keep streaming from head
+//! - [`HttpClient::get_cross_rates_in_range`]\: Get the synthetic exchange rates of a token pair from the provided block range
+//! - [`HttpClient::get_cross_rates_live_stream`]\: Get the synthetic exchange rates of a token pair from the provided block and keep streaming from head
+//! - [`HttpClient::get_events_for_tx`]\: Get all pair, price and reserve events emitted by a transaction
+//! - [`HttpClient::get_volume_in_range`]\: Get the traded volume of a pair per interval from the provided block range
+//! - [`HttpClient::get_volume_live_stream`]\: Get the traded volume of a pair per interval from the provided block and keep streaming from head
+//! - [`HttpClient::get_depth`]\: Get the liquidity depth of a pair at various slippage levels
+//! - [`HttpClient::block_for_timestamp`]\: Get the latest block at or before a timestamp
+//! - [`HttpClient::timestamp_for_block`]\: Get the timestamp of a block
+//! - [`HttpClient::get_finality`]\: Get the finality status of a block
+//! - [`HttpClient::get_usage`]\: Get the usage of the API key against its quotas
+//! - [`HttpClient::rate_limit`]\: Get the rate limit reported with the latest response
+//! - [`HttpClient::finalized_height`]\: Get the height of the latest finalized block
 //!
 //! #### WebSocket
 //!
 //! - [`WsClient::get_pairs_created`]\: Get the PairCreated event for a pair from the specified block range
+//! - [`WsClient::get_pairs_for_token`]\: Get the PairCreated events of all pairs containing a token
+//! - [`WsClient::get_pair_info`]\: Get the PairCreated event of a pair joined with its token metadata and current reserves
 //! - [`WsClient::get_prices`]\: Get all price quotes for a pair from the specified block range
+//! - [`WsClient::get_prices_after`]\: Resume all price quotes for a pair right after a cursor
+//! - [`WsClient::get_reserves_after`]\: Resume all reserves for a pair right after a cursor
+//! - [`WsClient::get_latest_price`]\: Get the most recent price quote of a pair
+//! - [`WsClient::get_stablecoin_supply`]\: Get all stablecoin mint and burn events from the specified block range
+//! - [`WsClient::get_traces`]\: Get all internal calls of a set of addresses from the specified block range
+//! - [`WsClient::get_tvl`]\: Get the USD TVL of a set of pairs from the specified block range
+//! - [`WsClient::get_lp_supply`]\: Get all LP token supply changes of a set of pairs from the specified block range
+//! - [`WsClient::get_flash_loans`]\: Get all Aave flash loans of a set of assets from the specified block range
+//! - [`WsClient::get_mev_events`]\: Get all labeled MEV events of a set of pairs from the specified block range
+//! - [`WsClient::get_bridge_transfers`]\: Get all bridge transfers of a set of tokens from the specified block range
+//! - [`WsClient::get_staking_deposits`]\: Get all staking deposits of a set of validators from the specified block range
+//! - [`WsClient::get_staking_withdrawals`]\: Get all staking withdrawals of a set of validators from the specified block range
+//! - [`WsClient::get_fees`]\: Get the accrued swap fees of a set of pairs per block from the specified block range
+//! - [`WsClient::get_usd_prices`]\: Get the USD prices of a set of tokens from the specified block range
+//! - [`WsClient::get_cross_rates`]\: Get the synthetic exchange rates of a token pair from the specified block range
+//! - [`WsClient::get_events_for_tx`]\: Get all pair, price and reserve events emitted by a transaction
+//! - [`WsClient::get_volume`]\: Get the traded volume of a set of pairs per interval from the specified block range
+//! - [`WsClient::get_depth`]\: Get the liquidity depth of a pair at various slippage levels
+//! - [`WsClient::subscribe_reorgs`]\: Get notified about all chain reorganizations from now on
+//! - [`WsClient::get_finality`]\: Get the finality status of a block
+//! - [`WsClient::finalized_height`]\: Get the height of the latest finalized block
+//! - [`WsClient::block_for_timestamp`]\: Get the latest block at or before a timestamp
+//! - [`WsClient::timestamp_for_block`]\: Get the timestamp of a block
 
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 #![deny(rust_2018_idioms, rustdoc::broken_intra_doc_links)]
 
-pub use ::{ethers, futures, reqwest, tokio, tokio_tungstenite, tungstenite, url};
+#[cfg(feature = "http")]
+pub use ::reqwest;
+#[cfg(feature = "grpc")]
+pub use ::tonic;
+pub use ::{ethers, futures, tokio, url};
+#[cfg(feature = "ws")]
+pub use ::{tokio_tungstenite, tungstenite};
 
+#[cfg(any(feature = "http", feature = "ws", feature = "wasm"))]
+pub use crate::decode::{DecodePolicy, DecodeWarning};
+#[cfg(feature = "grpc")]
+#[doc(inline)]
+pub use crate::grpc::Client as GrpcClient;
+#[cfg(feature = "http")]
+#[doc(inline)]
+pub use crate::http::Client as HttpClient;
+#[cfg(feature = "metrics")]
+pub use crate::telemetry::describe_metrics;
+#[cfg(any(feature = "ws", feature = "wasm"))]
+#[doc(inline)]
+pub use crate::ws::Client as WsClient;
+#[cfg(all(feature = "http", feature = "ws"))]
+#[doc(inline)]
+pub use crate::{client::Client, failover::FailoverClient};
 #[doc(inline)]
 pub use crate::{
-    error::{Error, Result},
-    http::Client as HttpClient,
-    types::{PairCreated, Price, Reserves, Side, Type},
-    ws::Client as WsClient,
+    config::{DEFAULT_HTTP_URL, DEFAULT_WS_URL},
+    error::{
+        clear_error_observer, set_error_observer, Error, ErrorContext, Result, ServerError,
+        TimeoutPhase,
+    },
+    mock::MockClient,
+    provider::SuperchainProvider,
+    types::{
+        BridgeDirection, BridgeTransfer, CallType, CrossRate, Cursor, DepthLevel, Fees, Finality,
+        FinalityStatus, FlashLoan, Interval, LpSupply, MevEvent, MevKind, PairCreated, PairInfo,
+        Position, Positioned, Price, PriceOptions, RateLimit, Reorg, ReserveOptions, Reserves,
+        Sampling, Side, StablecoinSupply, StakingDeposit, StakingWithdrawal, SupplyChange, Trace,
+        TransactionEvents, Tvl, Type, Usage, UsdPrice, Volume,
+    },
 };
 
+pub mod adapters;
+pub mod auth;
+#[cfg(feature = "parquet")]
+pub mod backfill;
+#[cfg(any(feature = "http", feature = "ws", feature = "wasm"))]
+mod block_time;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "cache")]
+pub mod cache;
+pub mod cassette;
+#[cfg(all(feature = "http", feature = "ws"))]
+mod client;
 pub mod config;
+#[cfg(any(feature = "http", feature = "ws", feature = "wasm"))]
+mod decode;
 mod error;
+#[cfg(all(feature = "http", feature = "ws"))]
+mod failover;
+#[cfg(feature = "grpc")]
+mod grpc;
+#[cfg(feature = "http")]
 mod http;
+pub mod manager;
+pub mod middleware;
+mod mock;
+mod provider;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod shutdown;
+pub mod sinks;
+#[cfg(all(feature = "http", feature = "ws"))]
+pub mod snapshot;
+#[cfg(feature = "http")]
+mod sse;
+pub mod store;
+pub mod subscriptions;
+#[cfg(any(feature = "http", feature = "ws", feature = "wasm"))]
+mod telemetry;
 mod types;
+#[cfg(any(feature = "ws", feature = "wasm"))]
 mod ws;
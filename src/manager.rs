@@ -0,0 +1,271 @@
+//! Share several API keys between tenants, tracking their usage against quotas
+//!
+//! A [`ClientManager`] owns one provider per API key, e.g. a `Client` connected with the
+//! credentials of that key. Requests are routed by tenant with [`ClientManager::tenant`], and
+//! served with the first key of the tenant, that has quota left.
+//! Once a key hits its quota or gets throttled by the gateway, requests rotate to the next key.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use ethers::types::H160;
+use futures::{future::BoxFuture, stream::BoxStream};
+
+use crate::{
+    types::{PairCreated, PairInfo, Price, Reserves},
+    Error, Result, SuperchainProvider,
+};
+
+/// The usage of an API key in the current quota period
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyUsage {
+    pub key: String,
+    /// The number of requests made, each stream counts as one request
+    pub used: u64,
+    pub quota: Option<u64>,
+    /// Whether the gateway throttled this key in the current period
+    pub throttled: bool,
+}
+
+struct Key<P> {
+    name: String,
+    provider: P,
+    quota: Option<u64>,
+    used: AtomicU64,
+    throttled: AtomicBool,
+}
+
+impl<P> Key<P> {
+    /// Count a request against the quota, if the key is still usable
+    fn try_use(&self) -> bool {
+        !self.throttled.load(Ordering::SeqCst)
+            && self
+                .used
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                    (!matches!(self.quota, Some(quota) if used >= quota)).then_some(used + 1)
+                })
+                .is_ok()
+    }
+}
+
+struct Tenant<P> {
+    keys: Vec<Key<P>>,
+    current: AtomicUsize,
+}
+
+/// Owns the providers of several API keys and routes requests by tenant
+///
+/// Usage is counted per quota period, which defaults to a day. Once it passes, all counters and
+/// throttled keys are reset.
+pub struct ClientManager<P> {
+    tenants: HashMap<String, Tenant<P>>,
+    period: Duration,
+    period_start: Mutex<Instant>,
+}
+
+impl<P> ClientManager<P>
+where
+    P: SuperchainProvider,
+{
+    /// Create a new [`ClientManager`] without any keys
+    pub fn new() -> Self {
+        Self {
+            tenants: HashMap::new(),
+            period: Duration::from_secs(24 * 60 * 60),
+            period_start: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Add the API `key` of `tenant`, served by `provider`, allowing up to `quota` requests per
+    /// period
+    ///
+    /// Keys of a tenant are used in the order they were added.
+    pub fn with_key(
+        mut self,
+        tenant: impl Into<String>,
+        key: impl Into<String>,
+        provider: P,
+        quota: Option<u64>,
+    ) -> Self {
+        let tenant = self.tenants.entry(tenant.into()).or_insert_with(|| Tenant {
+            keys: Vec::new(),
+            current: AtomicUsize::new(0),
+        });
+        tenant.keys.push(Key {
+            name: key.into(),
+            provider,
+            quota,
+            used: AtomicU64::new(0),
+            throttled: AtomicBool::new(false),
+        });
+        self
+    }
+
+    /// Set the period quotas apply to, defaults to a day
+    pub fn with_quota_period(mut self, period: Duration) -> Self {
+        self.period = period;
+        self
+    }
+
+    /// A provider serving the requests of `tenant` with its keys
+    pub fn tenant(&self, tenant: &str) -> Result<TenantClient<'_, P>> {
+        let keys = self
+            .tenants
+            .get(tenant)
+            .ok_or_else(|| Error::UnknownTenant(tenant.to_owned()))?;
+        Ok(TenantClient {
+            manager: self,
+            name: tenant.to_owned(),
+            tenant: keys,
+        })
+    }
+
+    /// The usage of the keys of `tenant` in the current period
+    pub fn usage(&self, tenant: &str) -> Result<Vec<KeyUsage>> {
+        self.roll_period();
+        let tenant = self
+            .tenants
+            .get(tenant)
+            .ok_or_else(|| Error::UnknownTenant(tenant.to_owned()))?;
+        Ok(tenant
+            .keys
+            .iter()
+            .map(|key| KeyUsage {
+                key: key.name.clone(),
+                used: key.used.load(Ordering::SeqCst),
+                quota: key.quota,
+                throttled: key.throttled.load(Ordering::SeqCst),
+            })
+            .collect())
+    }
+
+    /// Reset all counters, once the current period passed
+    fn roll_period(&self) {
+        let mut period_start = self
+            .period_start
+            .lock()
+            .expect("the lock is never poisoned");
+        if period_start.elapsed() < self.period {
+            return;
+        }
+        *period_start = Instant::now();
+        for key in self.tenants.values().flat_map(|tenant| &tenant.keys) {
+            key.used.store(0, Ordering::SeqCst);
+            key.throttled.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+impl<P> Default for ClientManager<P>
+where
+    P: SuperchainProvider,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The requests of a single tenant, as returned by [`ClientManager::tenant`]
+///
+/// Requests fail with [`Error::QuotaExhausted`] once all keys of the tenant hit their quota.
+pub struct TenantClient<'m, P> {
+    manager: &'m ClientManager<P>,
+    name: String,
+    tenant: &'m Tenant<P>,
+}
+
+impl<'m, P> TenantClient<'m, P>
+where
+    P: SuperchainProvider,
+{
+    /// The first key with quota left, starting with the most recently used one
+    fn acquire(&self) -> Result<&'m Key<P>> {
+        self.manager.roll_period();
+        let keys = &self.tenant.keys;
+        let start = self.tenant.current.load(Ordering::SeqCst);
+        for offset in 0..keys.len() {
+            let index = (start + offset) % keys.len();
+            if keys[index].try_use() {
+                self.tenant.current.store(index, Ordering::SeqCst);
+                return Ok(&keys[index]);
+            }
+        }
+        Err(Error::QuotaExhausted(self.name.clone()))
+    }
+
+    /// Run `query` with the current key, rotating to the next key when throttled
+    async fn query<T, F>(&self, query: F) -> Result<T>
+    where
+        F: Fn(&'m P) -> BoxFuture<'m, Result<T>>,
+    {
+        loop {
+            let key = self.acquire()?;
+            match query(&key.provider).await {
+                Err(err) if err.is_rate_limited() => key.throttled.store(true, Ordering::SeqCst),
+                result => return result,
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<'m, P> SuperchainProvider for TenantClient<'m, P>
+where
+    P: SuperchainProvider,
+{
+    async fn get_pair_created(&self, pair: H160) -> Result<Option<PairCreated>> {
+        self.query(|provider| provider.get_pair_created(pair)).await
+    }
+
+    async fn get_pair_info(&self, pair: H160) -> Result<Option<PairInfo>> {
+        self.query(|provider| provider.get_pair_info(pair)).await
+    }
+
+    async fn get_prices(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Price>>> {
+        self.query(|provider| provider.get_prices(pair, from_block, to_block_inc))
+            .await
+    }
+
+    async fn get_reserves(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Reserves>>> {
+        self.query(|provider| provider.get_reserves(pair, from_block, to_block_inc))
+            .await
+    }
+
+    async fn get_latest_price(&self, pair: H160) -> Result<Option<Price>> {
+        self.query(|provider| provider.get_latest_price(pair)).await
+    }
+
+    async fn get_height(&self) -> Result<u64> {
+        self.query(|provider| provider.get_height()).await
+    }
+
+    async fn finalized_height(&self) -> Result<u64> {
+        self.query(|provider| provider.finalized_height()).await
+    }
+
+    async fn block_for_timestamp(&self, timestamp: i64) -> Result<u64> {
+        self.query(|provider| provider.block_for_timestamp(timestamp))
+            .await
+    }
+
+    async fn timestamp_for_block(&self, block: u64) -> Result<i64> {
+        self.query(|provider| provider.timestamp_for_block(block))
+            .await
+    }
+}
@@ -0,0 +1,306 @@
+//! Transforms applied to every stream of a provider
+//!
+//! A [`Pipeline`] wraps any [`SuperchainProvider`] and applies its middleware, in the order they
+//! were added, to all price and reserve streams. This keeps cross-cutting concerns like
+//! filtering, enrichment, rate limiting and logging in one place, instead of at every call site.
+
+use std::{fmt::Debug, sync::Arc, time::Duration};
+
+use ethers::types::H160;
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
+use tokio::time::Instant;
+
+use crate::{
+    types::{PairCreated, PairInfo, Price, Reserves},
+    Error, Result, SuperchainProvider,
+};
+
+/// A transform of the streams of a [`Pipeline`]
+///
+/// Both methods pass the stream through unchanged by default, so a middleware only overrides
+/// the streams it transforms.
+pub trait Middleware: Send + Sync {
+    /// Transform a stream of price quotes
+    fn prices<'a>(&self, stream: BoxStream<'a, Result<Price>>) -> BoxStream<'a, Result<Price>> {
+        stream
+    }
+
+    /// Transform a stream of reserves
+    fn reserves<'a>(
+        &self,
+        stream: BoxStream<'a, Result<Reserves>>,
+    ) -> BoxStream<'a, Result<Reserves>> {
+        stream
+    }
+}
+
+/// A [`SuperchainProvider`] applying middleware to all streams of the wrapped provider
+pub struct Pipeline<P> {
+    inner: P,
+    middleware: Vec<Box<dyn Middleware>>,
+}
+
+impl<P: SuperchainProvider> Pipeline<P> {
+    /// Create a new [`Pipeline`] without any middleware
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            middleware: Vec::new(),
+        }
+    }
+
+    /// Add `middleware`, it is applied after all previously added ones
+    pub fn with(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Box::new(middleware));
+        self
+    }
+
+    /// The wrapped provider
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: SuperchainProvider> SuperchainProvider for Pipeline<P> {
+    async fn get_pair_created(&self, pair: H160) -> Result<Option<PairCreated>> {
+        self.inner.get_pair_created(pair).await
+    }
+
+    async fn get_pair_info(&self, pair: H160) -> Result<Option<PairInfo>> {
+        self.inner.get_pair_info(pair).await
+    }
+
+    async fn get_prices(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Price>>> {
+        let stream = self
+            .inner
+            .get_prices(pair, from_block, to_block_inc)
+            .await?;
+        Ok(self
+            .middleware
+            .iter()
+            .fold(stream, |stream, middleware| middleware.prices(stream)))
+    }
+
+    async fn get_reserves(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Reserves>>> {
+        let stream = self
+            .inner
+            .get_reserves(pair, from_block, to_block_inc)
+            .await?;
+        Ok(self
+            .middleware
+            .iter()
+            .fold(stream, |stream, middleware| middleware.reserves(stream)))
+    }
+
+    async fn get_latest_price(&self, pair: H160) -> Result<Option<Price>> {
+        self.inner.get_latest_price(pair).await
+    }
+
+    async fn get_height(&self) -> Result<u64> {
+        self.inner.get_height().await
+    }
+
+    async fn finalized_height(&self) -> Result<u64> {
+        self.inner.finalized_height().await
+    }
+
+    async fn block_for_timestamp(&self, timestamp: i64) -> Result<u64> {
+        self.inner.block_for_timestamp(timestamp).await
+    }
+
+    async fn timestamp_for_block(&self, block: u64) -> Result<i64> {
+        self.inner.timestamp_for_block(block).await
+    }
+}
+
+type Predicate<T> = Arc<dyn Fn(&T) -> bool + Send + Sync>;
+type InspectFn = Arc<dyn Fn(&dyn Debug) + Send + Sync>;
+
+/// A [`Middleware`] dropping all rows not matching its predicates
+#[derive(Default)]
+pub struct Filter {
+    prices: Option<Predicate<Price>>,
+    reserves: Option<Predicate<Reserves>>,
+}
+
+impl Filter {
+    /// Create a new [`Filter`] keeping all rows
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only keep the price quotes matching `predicate`
+    pub fn with_prices<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&Price) -> bool + Send + Sync + 'static,
+    {
+        self.prices = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Only keep the reserves matching `predicate`
+    pub fn with_reserves<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&Reserves) -> bool + Send + Sync + 'static,
+    {
+        self.reserves = Some(Arc::new(predicate));
+        self
+    }
+}
+
+fn filter<'a, T>(
+    stream: BoxStream<'a, Result<T>>,
+    predicate: &Option<Predicate<T>>,
+) -> BoxStream<'a, Result<T>>
+where
+    T: Send + 'a,
+{
+    match predicate.clone() {
+        Some(predicate) => stream
+            .try_filter(move |row| futures::future::ready(predicate(row)))
+            .boxed(),
+        None => stream,
+    }
+}
+
+impl Middleware for Filter {
+    fn prices<'a>(&self, stream: BoxStream<'a, Result<Price>>) -> BoxStream<'a, Result<Price>> {
+        filter(stream, &self.prices)
+    }
+
+    fn reserves<'a>(
+        &self,
+        stream: BoxStream<'a, Result<Reserves>>,
+    ) -> BoxStream<'a, Result<Reserves>> {
+        filter(stream, &self.reserves)
+    }
+}
+
+/// The longest delay between two rows of a [`RateLimit`]
+const MAX_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A [`Middleware`] limiting each stream to a maximum number of rows per second
+pub struct RateLimit {
+    period: Duration,
+}
+
+impl RateLimit {
+    /// Create a new [`RateLimit`] of `rows_per_second`
+    ///
+    /// The rate has to be positive and finite. Rates below one row per day are raised to it.
+    pub fn new(rows_per_second: f64) -> Result<Self> {
+        if !rows_per_second.is_finite() || rows_per_second <= 0.0 {
+            return Err(Error::InvalidConfig(format!(
+                "the rate limit has to be positive, got {rows_per_second} rows per second"
+            )));
+        }
+        let period = (1.0 / rows_per_second).min(MAX_PERIOD.as_secs_f64());
+        Ok(Self {
+            period: Duration::from_secs_f64(period),
+        })
+    }
+
+    fn limit<'a, T: Send + 'a>(
+        &self,
+        stream: BoxStream<'a, Result<T>>,
+    ) -> BoxStream<'a, Result<T>> {
+        let period = self.period;
+        let mut next = Instant::now();
+        stream
+            .then(move |row| {
+                let deadline = next.max(Instant::now());
+                next = deadline + period;
+                async move {
+                    tokio::time::sleep_until(deadline).await;
+                    row
+                }
+            })
+            .boxed()
+    }
+}
+
+impl Middleware for RateLimit {
+    fn prices<'a>(&self, stream: BoxStream<'a, Result<Price>>) -> BoxStream<'a, Result<Price>> {
+        self.limit(stream)
+    }
+
+    fn reserves<'a>(
+        &self,
+        stream: BoxStream<'a, Result<Reserves>>,
+    ) -> BoxStream<'a, Result<Reserves>> {
+        self.limit(stream)
+    }
+}
+
+/// A [`Middleware`] calling a function with every row, e.g. to log them
+pub struct Inspect {
+    inspect: InspectFn,
+}
+
+impl Inspect {
+    /// Create a new [`Inspect`] calling `inspect` with every row
+    pub fn new<F>(inspect: F) -> Self
+    where
+        F: Fn(&dyn Debug) + Send + Sync + 'static,
+    {
+        Self {
+            inspect: Arc::new(inspect),
+        }
+    }
+
+    fn inspect<'a, T>(&self, stream: BoxStream<'a, Result<T>>) -> BoxStream<'a, Result<T>>
+    where
+        T: Debug + Send + 'a,
+    {
+        let inspect = Arc::clone(&self.inspect);
+        stream.inspect_ok(move |row| inspect(row)).boxed()
+    }
+}
+
+impl Middleware for Inspect {
+    fn prices<'a>(&self, stream: BoxStream<'a, Result<Price>>) -> BoxStream<'a, Result<Price>> {
+        self.inspect(stream)
+    }
+
+    fn reserves<'a>(
+        &self,
+        stream: BoxStream<'a, Result<Reserves>>,
+    ) -> BoxStream<'a, Result<Reserves>> {
+        self.inspect(stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_invalid_rates() {
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(RateLimit::new(rate), Err(Error::InvalidConfig(_))));
+        }
+    }
+
+    #[test]
+    fn caps_the_period() {
+        assert_eq!(
+            RateLimit::new(4.0).unwrap().period,
+            Duration::from_millis(250)
+        );
+        assert_eq!(
+            RateLimit::new(f64::MIN_POSITIVE).unwrap().period,
+            MAX_PERIOD
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use std::{collections::VecDeque, path::Path, sync::Mutex, time::Duration};
+
+use ethers::types::H160;
+use futures::{stream::BoxStream, StreamExt};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    types::{PairCreated, PairInfo, Positioned, Price, Reserves},
+    Error, Result, SuperchainProvider,
+};
+
+type ErrorFn = Box<dyn Fn() -> Error + Send + Sync>;
+
+/// A [`SuperchainProvider`] serving canned data, to test code without network access
+///
+/// Items are served in chain order. Streams always end after the last matching item, since there
+/// is no head to follow. The height is the highest block of all items.
+///
+/// Reserves recorded from the gateway come without a pair and block, these are served for every
+/// requested pair and block range, since they can't be told apart.
+#[derive(Default)]
+pub struct MockClient {
+    pairs: Vec<PairCreated>,
+    pair_infos: Vec<PairInfo>,
+    prices: Vec<Price>,
+    reserves: Vec<Reserves>,
+    delay: Duration,
+    stream_error: Option<(usize, ErrorFn)>,
+    errors: Mutex<VecDeque<Error>>,
+}
+
+impl MockClient {
+    /// Create a new [`MockClient`] without any data
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve the provided `PairCreated` events
+    pub fn with_pairs(mut self, pairs: impl IntoIterator<Item = PairCreated>) -> Self {
+        self.pairs.extend(pairs);
+        self.pairs.sort_by_key(Positioned::position);
+        self
+    }
+
+    /// Serve the provided pair infos, their `PairCreated` events are served as well
+    pub fn with_pair_infos(mut self, pair_infos: impl IntoIterator<Item = PairInfo>) -> Self {
+        for info in pair_infos {
+            self.pairs.push(info.pair_created());
+            self.pair_infos.push(info);
+        }
+        self.pairs.sort_by_key(Positioned::position);
+        self
+    }
+
+    /// Serve the provided price quotes
+    pub fn with_prices(mut self, prices: impl IntoIterator<Item = Price>) -> Self {
+        self.prices.extend(prices);
+        self.prices.sort_by_key(Positioned::position);
+        self
+    }
+
+    /// Serve the provided reserves
+    pub fn with_reserves(mut self, reserves: impl IntoIterator<Item = Reserves>) -> Self {
+        self.reserves.extend(reserves);
+        self.reserves.sort_by_key(Positioned::position);
+        self
+    }
+
+    /// Serve the `PairCreated` events of a csv file, as served by the gateway
+    pub fn with_pairs_from_csv(self, path: impl AsRef<Path>) -> Result<Self> {
+        Ok(self.with_pairs(read_csv(path)?))
+    }
+
+    /// Serve the price quotes of a csv file, as served by the gateway
+    pub fn with_prices_from_csv(self, path: impl AsRef<Path>) -> Result<Self> {
+        Ok(self.with_prices(read_csv(path)?))
+    }
+
+    /// Serve the reserves of a csv file, as served by the gateway
+    pub fn with_reserves_from_csv(self, path: impl AsRef<Path>) -> Result<Self> {
+        Ok(self.with_reserves(read_csv(path)?))
+    }
+
+    /// Delay each response and each streamed item by `delay`, defaults to no delay
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Fail all streams with the error returned by `error`, after they yielded `after` items
+    pub fn with_stream_error<F>(mut self, after: usize, error: F) -> Self
+    where
+        F: Fn() -> Error + Send + Sync + 'static,
+    {
+        self.stream_error = Some((after, Box::new(error)));
+        self
+    }
+
+    /// Fail the next query with `err`
+    ///
+    /// Multiple errors are returned by the following queries in the order they were added.
+    pub fn fail_next(&self, err: Error) {
+        self.errors
+            .lock()
+            .expect("the lock is never poisoned")
+            .push_back(err);
+    }
+
+    async fn respond<T>(&self, response: Result<T>) -> Result<T> {
+        let err = self
+            .errors
+            .lock()
+            .expect("the lock is never poisoned")
+            .pop_front();
+        if let Some(err) = err {
+            return Err(err);
+        }
+
+        if !self.delay.is_zero() {
+            tokio::time::sleep(self.delay).await;
+        }
+        response
+    }
+
+    fn stream<T>(&self, items: Vec<T>) -> BoxStream<'_, Result<T>>
+    where
+        T: Send + 'static,
+    {
+        let delay = self.delay;
+        let items = futures::stream::iter(items.into_iter().map(Ok));
+        let stream = match &self.stream_error {
+            Some((after, error)) => items
+                .take(*after)
+                .chain(futures::stream::once(futures::future::ready(Err(error()))))
+                .boxed(),
+            None => items.boxed(),
+        };
+
+        stream
+            .then(move |item| async move {
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+                item
+            })
+            .boxed()
+    }
+
+    /// The block numbers and timestamps of all items, in chain order per kind
+    fn block_times(&self) -> impl Iterator<Item = (u64, i64)> + '_ {
+        let pairs = self
+            .pairs
+            .iter()
+            .map(|pair| (pair.block_number, pair.timestamp));
+        let prices = self
+            .prices
+            .iter()
+            .map(|price| (price.block_number, price.timestamp));
+        let reserves = self
+            .reserves
+            .iter()
+            .filter_map(|reserves| reserves.block_number.zip(reserves.timestamp));
+        pairs.chain(prices).chain(reserves)
+    }
+}
+
+fn read_csv<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<Vec<T>> {
+    csv::Reader::from_path(path)?
+        .deserialize()
+        .map(|row| row.map_err(Error::from))
+        .collect()
+}
+
+fn in_range(block_number: u64, from_block: u64, to_block_inc: Option<u64>) -> bool {
+    block_number >= from_block && !matches!(to_block_inc, Some(to) if block_number > to)
+}
+
+#[async_trait::async_trait]
+impl SuperchainProvider for MockClient {
+    async fn get_pair_created(&self, pair: H160) -> Result<Option<PairCreated>> {
+        let found = self.pairs.iter().find(|created| created.pair == pair);
+        self.respond(Ok(found.cloned())).await
+    }
+
+    async fn get_pair_info(&self, pair: H160) -> Result<Option<PairInfo>> {
+        let found = self.pair_infos.iter().find(|info| info.pair == pair);
+        self.respond(Ok(found.cloned())).await
+    }
+
+    async fn get_prices(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Price>>> {
+        let prices = self
+            .prices
+            .iter()
+            .filter(|price| price.pair == pair)
+            .filter(|price| in_range(price.block_number, from_block, to_block_inc))
+            .cloned()
+            .collect();
+        self.respond(Ok(self.stream(prices))).await
+    }
+
+    async fn get_reserves(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Reserves>>> {
+        let reserves = self
+            .reserves
+            .iter()
+            .filter(|reserves| reserves.pair.map_or(true, |known| known == pair))
+            .filter(|reserves| {
+                let block = reserves.block_number;
+                block.map_or(true, |block| in_range(block, from_block, to_block_inc))
+            })
+            .cloned()
+            .collect();
+        self.respond(Ok(self.stream(reserves))).await
+    }
+
+    async fn get_latest_price(&self, pair: H160) -> Result<Option<Price>> {
+        let found = self.prices.iter().rev().find(|price| price.pair == pair);
+        self.respond(Ok(found.cloned())).await
+    }
+
+    async fn get_height(&self) -> Result<u64> {
+        let height = self.block_times().map(|(block, _)| block).max();
+        self.respond(Ok(height.unwrap_or_default())).await
+    }
+
+    async fn finalized_height(&self) -> Result<u64> {
+        self.get_height().await
+    }
+
+    async fn block_for_timestamp(&self, timestamp: i64) -> Result<u64> {
+        let block = self
+            .block_times()
+            .filter(|(_, time)| *time <= timestamp)
+            .map(|(block, _)| block)
+            .max()
+            .ok_or_else(|| Error::ErrorMsg(format!("no block at or before {}", timestamp)));
+        self.respond(block).await
+    }
+
+    async fn timestamp_for_block(&self, block: u64) -> Result<i64> {
+        let timestamp = self
+            .block_times()
+            .find(|(number, _)| *number == block)
+            .map(|(_, timestamp)| timestamp)
+            .ok_or_else(|| Error::ErrorMsg(format!("unknown block {}", block)));
+        self.respond(timestamp).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+
+    use super::*;
+    use crate::types::fixtures::{gateway_reserves, reserves};
+
+    #[tokio::test]
+    async fn serves_gateway_reserves_for_any_pair() {
+        let mock =
+            MockClient::new().with_reserves([gateway_reserves(1, 2), gateway_reserves(3, 4)]);
+        let rows: Vec<_> = mock
+            .get_reserves(H160::from_low_u64_be(7), 10, Some(20))
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn filters_positioned_reserves() {
+        let mock = MockClient::new().with_reserves([
+            reserves(1, 10, 0, None),
+            reserves(1, 30, 0, None),
+            reserves(2, 10, 0, None),
+        ]);
+        let rows: Vec<_> = mock
+            .get_reserves(H160::from_low_u64_be(1), 10, Some(20))
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].block_number, Some(10));
+    }
+}
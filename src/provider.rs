@@ -0,0 +1,231 @@
+use ethers::types::H160;
+use futures::stream::BoxStream;
+#[cfg(any(feature = "grpc", feature = "http", feature = "ws", feature = "wasm"))]
+use futures::StreamExt;
+
+#[cfg(feature = "grpc")]
+use crate::GrpcClient;
+#[cfg(feature = "http")]
+use crate::HttpClient;
+#[cfg(any(feature = "ws", feature = "wasm"))]
+use crate::WsClient;
+use crate::{
+    types::{PairCreated, PairInfo, Price, Reserves},
+    Result,
+};
+
+/// The queries supported by both the [`HttpClient`] and the [`WsClient`]
+///
+/// Application code written against this trait works with either transport, and tests can
+/// implement it to provide canned data. Streams span from `from_block` to `to_block_inc`, a
+/// `to_block_inc` of `None` leads to a head following stream.
+#[async_trait::async_trait]
+pub trait SuperchainProvider: Send + Sync {
+    /// Get the uniswap v2 pair created event of the provided `pair`
+    async fn get_pair_created(&self, pair: H160) -> Result<Option<PairCreated>>;
+
+    /// Get the uniswap v2 pair created event of the provided `pair`, joined with the metadata of
+    /// its tokens and its current reserves
+    async fn get_pair_info(&self, pair: H160) -> Result<Option<PairInfo>>;
+
+    /// Get the uniswap v2 price quotes of the provided `pair`
+    async fn get_prices(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Price>>>;
+
+    /// Get the uniswap v2 reserves of the provided `pair`
+    async fn get_reserves(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Reserves>>>;
+
+    /// Get the most recent uniswap v2 price quote of the provided `pair`
+    async fn get_latest_price(&self, pair: H160) -> Result<Option<Price>>;
+
+    /// Get the current height of the indexed chain
+    async fn get_height(&self) -> Result<u64>;
+
+    /// Get the height of the latest finalized block
+    async fn finalized_height(&self) -> Result<u64>;
+
+    /// Get the latest block at or before the provided unix `timestamp`
+    async fn block_for_timestamp(&self, timestamp: i64) -> Result<u64>;
+
+    /// Get the unix timestamp of the provided `block`
+    async fn timestamp_for_block(&self, block: u64) -> Result<i64>;
+}
+
+#[cfg(feature = "http")]
+#[async_trait::async_trait]
+impl SuperchainProvider for HttpClient {
+    async fn get_pair_created(&self, pair: H160) -> Result<Option<PairCreated>> {
+        HttpClient::get_pair_created(self, pair).await
+    }
+
+    async fn get_pair_info(&self, pair: H160) -> Result<Option<PairInfo>> {
+        HttpClient::get_pair_info(self, pair).await
+    }
+
+    async fn get_prices(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Price>>> {
+        Ok(match to_block_inc {
+            Some(to_block) => self
+                .get_prices_in_range(pair, from_block..=to_block)
+                .await?
+                .boxed(),
+            None => self.get_prices_live_stream(pair, from_block).await?.boxed(),
+        })
+    }
+
+    async fn get_reserves(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Reserves>>> {
+        Ok(match to_block_inc {
+            Some(to_block) => self
+                .get_reserves_in_range(pair, from_block..=to_block)
+                .await?
+                .boxed(),
+            None => self
+                .get_reserves_live_stream(pair, from_block)
+                .await?
+                .boxed(),
+        })
+    }
+
+    async fn get_latest_price(&self, pair: H160) -> Result<Option<Price>> {
+        HttpClient::get_latest_price(self, pair).await
+    }
+
+    async fn get_height(&self) -> Result<u64> {
+        HttpClient::get_height(self).await
+    }
+
+    async fn finalized_height(&self) -> Result<u64> {
+        HttpClient::finalized_height(self).await
+    }
+
+    async fn block_for_timestamp(&self, timestamp: i64) -> Result<u64> {
+        HttpClient::block_for_timestamp(self, timestamp).await
+    }
+
+    async fn timestamp_for_block(&self, block: u64) -> Result<i64> {
+        HttpClient::timestamp_for_block(self, block).await
+    }
+}
+
+#[cfg(any(feature = "ws", feature = "wasm"))]
+#[async_trait::async_trait]
+impl SuperchainProvider for WsClient {
+    async fn get_pair_created(&self, pair: H160) -> Result<Option<PairCreated>> {
+        let info = WsClient::get_pair_info(self, pair).await?;
+        Ok(info.map(|info| info.pair_created()))
+    }
+
+    async fn get_pair_info(&self, pair: H160) -> Result<Option<PairInfo>> {
+        WsClient::get_pair_info(self, pair).await
+    }
+
+    async fn get_prices(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Price>>> {
+        let stream = WsClient::get_prices(self, [pair], Some(from_block), to_block_inc).await?;
+        Ok(stream.boxed())
+    }
+
+    async fn get_reserves(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Reserves>>> {
+        let stream = WsClient::get_reserves(self, [pair], Some(from_block), to_block_inc).await?;
+        Ok(stream.boxed())
+    }
+
+    async fn get_latest_price(&self, pair: H160) -> Result<Option<Price>> {
+        WsClient::get_latest_price(self, pair).await
+    }
+
+    async fn get_height(&self) -> Result<u64> {
+        WsClient::get_height(self).await
+    }
+
+    async fn finalized_height(&self) -> Result<u64> {
+        WsClient::finalized_height(self).await
+    }
+
+    async fn block_for_timestamp(&self, timestamp: i64) -> Result<u64> {
+        WsClient::block_for_timestamp(self, timestamp).await
+    }
+
+    async fn timestamp_for_block(&self, block: u64) -> Result<i64> {
+        WsClient::timestamp_for_block(self, block).await
+    }
+}
+
+#[cfg(feature = "grpc")]
+#[async_trait::async_trait]
+impl SuperchainProvider for GrpcClient {
+    async fn get_pair_created(&self, pair: H160) -> Result<Option<PairCreated>> {
+        GrpcClient::get_pair_created(self, pair).await
+    }
+
+    async fn get_pair_info(&self, pair: H160) -> Result<Option<PairInfo>> {
+        GrpcClient::get_pair_info(self, pair).await
+    }
+
+    async fn get_prices(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Price>>> {
+        let stream = GrpcClient::get_prices(self, pair, from_block, to_block_inc).await?;
+        Ok(stream.boxed())
+    }
+
+    async fn get_reserves(
+        &self,
+        pair: H160,
+        from_block: u64,
+        to_block_inc: Option<u64>,
+    ) -> Result<BoxStream<'_, Result<Reserves>>> {
+        let stream = GrpcClient::get_reserves(self, pair, from_block, to_block_inc).await?;
+        Ok(stream.boxed())
+    }
+
+    async fn get_latest_price(&self, pair: H160) -> Result<Option<Price>> {
+        GrpcClient::get_latest_price(self, pair).await
+    }
+
+    async fn get_height(&self) -> Result<u64> {
+        GrpcClient::get_height(self).await
+    }
+
+    async fn finalized_height(&self) -> Result<u64> {
+        GrpcClient::finalized_height(self).await
+    }
+
+    async fn block_for_timestamp(&self, timestamp: i64) -> Result<u64> {
+        GrpcClient::block_for_timestamp(self, timestamp).await
+    }
+
+    async fn timestamp_for_block(&self, block: u64) -> Result<i64> {
+        GrpcClient::timestamp_for_block(self, block).await
+    }
+}
@@ -0,0 +1,414 @@
+//! Serve recorded data over the HTTP and WebSocket protocol of the gateway, for offline
+//! development
+//!
+//! A [`ReplayServer`] answers the queries of the [`HttpClient`](crate::HttpClient) and the
+//! [`WsClient`](crate::WsClient) with the data of any [`SuperchainProvider`], e.g. a
+//! [`MockClient`](crate::MockClient) loaded from csv files or a
+//! [`cassette::Player`](crate::cassette::Player). Point the clients at
+//! `http://<addr>/` and `ws://<addr>/websocket` and run them without network access.
+
+use std::sync::Arc;
+
+use ethers::types::H160;
+use futures::{stream::BoxStream, SinkExt, StreamExt};
+use serde::Serialize;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+use tokio_tungstenite::WebSocketStream;
+use tungstenite::{handshake::derive_accept_key, protocol::Role, Message};
+
+use crate::{Error, Result, SuperchainProvider};
+
+/// Serves the data of a [`SuperchainProvider`] over the gateway protocols
+///
+/// Both protocols are served on the same port, WebSocket connections are accepted on any path.
+/// Streams end with the data of the provider and gateway-side options, like sampling or sender
+/// filters, are ignored. Queries outside of the [`SuperchainProvider`] trait are answered with
+/// an error. Authorization headers are accepted, but not checked.
+pub struct ReplayServer<P> {
+    provider: Arc<P>,
+}
+
+impl<P> ReplayServer<P>
+where
+    P: SuperchainProvider + 'static,
+{
+    /// Create a new [`ReplayServer`] serving the data of `provider`
+    pub fn new(provider: P) -> Self {
+        Self {
+            provider: Arc::new(provider),
+        }
+    }
+
+    /// Accept and serve connections on `listener` until accepting fails
+    ///
+    /// Each connection is served on its own task.
+    pub async fn serve(self, listener: TcpListener) -> Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let provider = Arc::clone(&self.provider);
+            tokio::spawn(async move {
+                let _ = handle_connection(provider, stream).await;
+            });
+        }
+    }
+}
+
+async fn handle_connection<P>(provider: Arc<P>, stream: TcpStream) -> Result<()>
+where
+    P: SuperchainProvider + 'static,
+{
+    let mut stream = BufReader::new(stream);
+    let mut request_line = String::new();
+    stream.read_line(&mut request_line).await?;
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or("/")
+        .to_owned();
+
+    let mut websocket_key = None;
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("sec-websocket-key") {
+                websocket_key = Some(value.trim().to_owned());
+            }
+        }
+    }
+
+    match websocket_key {
+        Some(key) => {
+            let accept = derive_accept_key(key.as_bytes());
+            let response = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\
+                 Sec-WebSocket-Accept: {accept}\r\n\r\n"
+            );
+            stream.write_all(response.as_bytes()).await?;
+            let websocket = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+            serve_ws(provider, websocket).await
+        }
+        None => serve_http(&*provider, &path, stream).await,
+    }
+}
+
+/// A response to a single query
+enum Response<'a> {
+    Rows(BoxStream<'a, Result<Vec<u8>>>),
+    U64(u64),
+}
+
+/// A stream of csv chunks, the first one including the header
+fn csv_rows<'a, T>(rows: BoxStream<'a, Result<T>>) -> BoxStream<'a, Result<Vec<u8>>>
+where
+    T: Serialize + Send + 'a,
+{
+    let mut first = true;
+    rows.map(move |row| {
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(first)
+            .from_writer(Vec::new());
+        writer.serialize(row?)?;
+        first = false;
+        Ok(writer.into_inner().expect("writing to a Vec never fails"))
+    })
+    .boxed()
+}
+
+fn csv_row<'a, T>(row: Option<T>) -> BoxStream<'a, Result<Vec<u8>>>
+where
+    T: Serialize + Send + 'a,
+{
+    csv_rows(futures::stream::iter(row.map(Ok)).boxed())
+}
+
+fn parse<T: std::str::FromStr>(segment: &str) -> Result<T> {
+    segment
+        .parse()
+        .map_err(|_| Error::ErrorMsg(format!("malformed path segment {segment}")))
+}
+
+async fn serve_http<P>(provider: &P, path: &str, mut stream: BufReader<TcpStream>) -> Result<()>
+where
+    P: SuperchainProvider,
+{
+    let path = path.split('?').next().unwrap_or_default();
+    let segments: Vec<_> = path
+        .trim_start_matches("/api/eth/")
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let response = http_response(provider, &segments).await;
+
+    let (status, content_type) = match &response {
+        Ok(Response::Rows(_)) => ("200 OK", "text/csv"),
+        Ok(Response::U64(_)) => ("200 OK", "application/json"),
+        Err(err) if matches!(err.inner(), Error::ErrorMsg(_)) => ("404 Not Found", "text/plain"),
+        Err(_) => ("500 Internal Server Error", "text/plain"),
+    };
+    let head =
+        format!("HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nConnection: close\r\n\r\n");
+    stream.write_all(head.as_bytes()).await?;
+
+    match response {
+        Ok(Response::Rows(mut rows)) => {
+            while let Some(chunk) = rows.next().await {
+                // The status was sent already, so a failing stream can only end the response
+                stream.write_all(&chunk?).await?;
+                stream.flush().await?;
+            }
+        }
+        Ok(Response::U64(value)) => stream.write_all(value.to_string().as_bytes()).await?,
+        Err(err) => stream.write_all(err.to_string().as_bytes()).await?,
+    }
+    stream.shutdown().await?;
+    Ok(())
+}
+
+async fn http_response<'a, P>(provider: &'a P, segments: &[&str]) -> Result<Response<'a>>
+where
+    P: SuperchainProvider,
+{
+    let to_block_inc = segments.get(3).copied().map(parse).transpose()?;
+    let response = match segments {
+        ["pair", pair, ..] => {
+            let from_block = segments.get(2).copied().map(parse).transpose()?;
+            let from_block = from_block.unwrap_or(0);
+            let pair_created =
+                provider
+                    .get_pair_created(parse(pair)?)
+                    .await?
+                    .filter(|pair_created| {
+                        pair_created.block_number >= from_block
+                            && !matches!(to_block_inc, Some(to) if pair_created.block_number > to)
+                    });
+            Response::Rows(csv_row(pair_created))
+        }
+        ["pair_info", pair] => Response::Rows(csv_row(provider.get_pair_info(parse(pair)?).await?)),
+        ["prices", pair, from_block, ..] => {
+            let prices = provider
+                .get_prices(parse(pair)?, parse(from_block)?, to_block_inc)
+                .await?;
+            Response::Rows(csv_rows(prices))
+        }
+        ["reserves", pair, from_block, ..] => {
+            let reserves = provider
+                .get_reserves(parse(pair)?, parse(from_block)?, to_block_inc)
+                .await?;
+            Response::Rows(csv_rows(reserves))
+        }
+        ["latest_price", pair] => {
+            Response::Rows(csv_row(provider.get_latest_price(parse(pair)?).await?))
+        }
+        ["height"] => Response::U64(provider.get_height().await?),
+        ["height", "finalized"] => Response::U64(provider.finalized_height().await?),
+        ["block_for_timestamp", timestamp] => {
+            Response::U64(provider.block_for_timestamp(parse(timestamp)?).await?)
+        }
+        ["timestamp_for_block", block] => {
+            Response::U64(provider.timestamp_for_block(parse(block)?).await? as u64)
+        }
+        _ => return Err(Error::ErrorMsg("unsupported query".to_owned())),
+    };
+    Ok(response)
+}
+
+/// A WebSocket request, only the queries of the [`SuperchainProvider`] trait are supported
+#[derive(serde::Deserialize)]
+struct Request {
+    id: u8,
+    #[serde(flatten)]
+    operation: Operation,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(tag = "operation", rename_all = "camelCase")]
+enum Operation {
+    GetPairs {
+        pairs: Vec<[u8; 20]>,
+        start: Option<u64>,
+        end: Option<u64>,
+    },
+    GetPairInfo {
+        pair: [u8; 20],
+    },
+    GetPrices {
+        pairs: Vec<[u8; 20]>,
+        start: Option<u64>,
+        end: Option<u64>,
+    },
+    GetLatestPrice {
+        pair: [u8; 20],
+    },
+    GetReserves {
+        pairs: Vec<[u8; 20]>,
+        start: Option<u64>,
+        end: Option<u64>,
+    },
+    GetHeight,
+    GetFinalizedHeight,
+    GetBlockForTimestamp {
+        timestamp: i64,
+    },
+    GetTimestampForBlock {
+        block: u64,
+    },
+    #[serde(other)]
+    Unsupported,
+}
+
+const START: u8 = 0b00000001;
+const CONTINUE: u8 = 0b00000010;
+const END: u8 = 0b00000100;
+const ERROR: u8 = 0b10000000;
+
+/// Append the message header, as expected by the [`WsClient`](crate::WsClient)
+fn frame(mut data: Vec<u8>, marker: u8, id: u8, counter: u32) -> Message {
+    data.push(marker);
+    data.push(id);
+    data.extend_from_slice(&counter.to_be_bytes());
+    Message::Binary(data)
+}
+
+async fn serve_ws<P>(
+    provider: Arc<P>,
+    websocket: WebSocketStream<BufReader<TcpStream>>,
+) -> Result<()>
+where
+    P: SuperchainProvider + 'static,
+{
+    let (mut sink, mut stream) = websocket.split();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let writer = tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            sink.send(msg).await?;
+        }
+        Ok::<_, Error>(())
+    });
+
+    while let Some(msg) = stream.next().await {
+        let data = match msg? {
+            Message::Binary(data) => data,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        let request: Request = match serde_cbor::from_slice(&data) {
+            Ok(request) => request,
+            Err(_) => continue,
+        };
+
+        let provider = Arc::clone(&provider);
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let id = request.id;
+            let _ = tx.send(frame(Vec::new(), START, id, 0));
+            let mut counter = 1;
+            let result = match ws_response(&*provider, request.operation).await {
+                Ok(Response::Rows(mut rows)) => loop {
+                    match rows.next().await {
+                        // The connection is gone, so stop pulling rows
+                        Some(Ok(chunk)) => {
+                            if tx.send(frame(chunk, CONTINUE, id, counter)).is_err() {
+                                return;
+                            }
+                            counter += 1;
+                        }
+                        Some(Err(err)) => break Err(err),
+                        None => break Ok(()),
+                    }
+                },
+                Ok(Response::U64(value)) => {
+                    let _ = tx.send(frame(value.to_ne_bytes().to_vec(), CONTINUE, id, counter));
+                    counter += 1;
+                    Ok(())
+                }
+                Err(err) => Err(err),
+            };
+            if let Err(err) = result {
+                let _ = tx.send(frame(err.to_string().into_bytes(), ERROR, id, counter));
+                counter += 1;
+            }
+            let _ = tx.send(frame(Vec::new(), END, id, counter));
+        });
+    }
+
+    // Pending streams stop once they fail to send to the aborted writer
+    writer.abort();
+    Ok(())
+}
+
+async fn ws_response<P>(provider: &P, operation: Operation) -> Result<Response<'_>>
+where
+    P: SuperchainProvider,
+{
+    let pairs_required = || Error::ErrorMsg("a pairs filter is required".to_owned());
+    let response = match operation {
+        Operation::GetPairs { pairs, start, end } => {
+            if pairs.is_empty() {
+                return Err(pairs_required());
+            }
+            let from_block = start.unwrap_or(0);
+            let rows = futures::stream::iter(pairs)
+                .then(move |pair| provider.get_pair_created(H160(pair)))
+                .filter_map(move |pair_created| {
+                    let pair_created = pair_created.transpose().filter(|pair_created| {
+                        !matches!(pair_created, Ok(pair_created)
+                            if pair_created.block_number < from_block
+                                || matches!(end, Some(to) if pair_created.block_number > to))
+                    });
+                    futures::future::ready(pair_created)
+                });
+            Response::Rows(csv_rows(rows.boxed()))
+        }
+        Operation::GetPairInfo { pair } => {
+            Response::Rows(csv_row(provider.get_pair_info(H160(pair)).await?))
+        }
+        Operation::GetPrices { pairs, start, end } => {
+            if pairs.is_empty() {
+                return Err(pairs_required());
+            }
+            let mut streams = Vec::with_capacity(pairs.len());
+            for pair in pairs {
+                streams.push(
+                    provider
+                        .get_prices(H160(pair), start.unwrap_or(0), end)
+                        .await?,
+                );
+            }
+            Response::Rows(csv_rows(futures::stream::select_all(streams).boxed()))
+        }
+        Operation::GetLatestPrice { pair } => {
+            Response::Rows(csv_row(provider.get_latest_price(H160(pair)).await?))
+        }
+        Operation::GetReserves { pairs, start, end } => {
+            if pairs.is_empty() {
+                return Err(pairs_required());
+            }
+            let mut streams = Vec::with_capacity(pairs.len());
+            for pair in pairs {
+                streams.push(
+                    provider
+                        .get_reserves(H160(pair), start.unwrap_or(0), end)
+                        .await?,
+                );
+            }
+            Response::Rows(csv_rows(futures::stream::select_all(streams).boxed()))
+        }
+        Operation::GetHeight => Response::U64(provider.get_height().await?),
+        Operation::GetFinalizedHeight => Response::U64(provider.finalized_height().await?),
+        Operation::GetBlockForTimestamp { timestamp } => {
+            Response::U64(provider.block_for_timestamp(timestamp).await?)
+        }
+        Operation::GetTimestampForBlock { block } => {
+            Response::U64(provider.timestamp_for_block(block).await? as u64)
+        }
+        Operation::Unsupported => return Err(Error::ErrorMsg("unsupported query".to_owned())),
+    };
+    Ok(response)
+}
@@ -0,0 +1,148 @@
+//! Coordinated graceful shutdown of all active streams
+//!
+//! A [`Shutdown`] ends every stream it guards once triggered, so downstream adapters like
+//! [`Checkpoints`](crate::adapters::Checkpoints) see a clean end and persist their last cursor.
+//! [`Shutdown::shutdown`] triggers it and resolves once all guarded streams and workers were
+//! dropped, e.g. to stop within the termination grace period of a Kubernetes pod. WebSocket
+//! connections send a close frame on shutdown, once hooked in with `WsClient::with_shutdown`.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use futures::{stream::BoxStream, Stream, StreamExt};
+use tokio::sync::{watch, Notify};
+
+use crate::{
+    middleware::Middleware,
+    types::{Price, Reserves},
+    Result,
+};
+
+struct Inner {
+    triggered: watch::Sender<bool>,
+    // Kept, so `triggered` never fails to send for a lack of receivers
+    trigger_rx: watch::Receiver<bool>,
+    active: AtomicUsize,
+    idle: Notify,
+}
+
+/// A shutdown coordinator, clones share the same state
+///
+/// Streams are guarded with [`Shutdown::guard`], or all streams of a
+/// [`Pipeline`](crate::middleware::Pipeline) by adding the [`Shutdown`] as middleware. Custom
+/// tasks register with [`Shutdown::worker`] and watch [`Shutdown::triggered`].
+#[derive(Clone)]
+pub struct Shutdown {
+    inner: Arc<Inner>,
+}
+
+/// Keeps [`Shutdown::stopped`] pending until dropped
+pub struct Worker {
+    inner: Arc<Inner>,
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        if self.inner.active.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.inner.idle.notify_waiters();
+        }
+    }
+}
+
+impl Shutdown {
+    /// Create a new, untriggered [`Shutdown`]
+    pub fn new() -> Self {
+        let (triggered, trigger_rx) = watch::channel(false);
+        Self {
+            inner: Arc::new(Inner {
+                triggered,
+                trigger_rx,
+                active: AtomicUsize::new(0),
+                idle: Notify::new(),
+            }),
+        }
+    }
+
+    /// End all guarded streams and signal all workers to stop
+    pub fn trigger(&self) {
+        let _ = self.inner.triggered.send(true);
+    }
+
+    /// Whether the shutdown was triggered
+    pub fn is_triggered(&self) -> bool {
+        *self.inner.trigger_rx.borrow()
+    }
+
+    /// Resolve once the shutdown is triggered
+    pub async fn triggered(&self) {
+        let mut trigger_rx = self.inner.trigger_rx.clone();
+        while !*trigger_rx.borrow() {
+            if trigger_rx.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Register a worker, the shutdown is not complete until the returned [`Worker`] is dropped
+    pub fn worker(&self) -> Worker {
+        self.inner.active.fetch_add(1, Ordering::SeqCst);
+        Worker {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+
+    /// End `stream` once the shutdown is triggered
+    ///
+    /// The stream counts as a worker until it is dropped.
+    pub fn guard<'a, S>(&self, stream: S) -> impl Stream<Item = S::Item> + Send + 'a
+    where
+        S: Stream + Send + 'a,
+    {
+        let worker = self.worker();
+        let shutdown = self.clone();
+        stream
+            .take_until(async move { shutdown.triggered().await })
+            .map(move |item| {
+                let _ = &worker;
+                item
+            })
+    }
+
+    /// Resolve once all workers and guarded streams were dropped
+    pub async fn stopped(&self) {
+        loop {
+            let idle = self.inner.idle.notified();
+            if self.inner.active.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+
+    /// Trigger the shutdown and resolve once all workers and guarded streams were dropped
+    pub async fn shutdown(&self) {
+        self.trigger();
+        self.stopped().await;
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for Shutdown {
+    fn prices<'a>(&self, stream: BoxStream<'a, Result<Price>>) -> BoxStream<'a, Result<Price>> {
+        self.guard(stream).boxed()
+    }
+
+    fn reserves<'a>(
+        &self,
+        stream: BoxStream<'a, Result<Reserves>>,
+    ) -> BoxStream<'a, Result<Reserves>> {
+        self.guard(stream).boxed()
+    }
+}
@@ -0,0 +1,116 @@
+use std::{sync::Arc, time::Duration};
+
+use futures::{Stream, StreamExt};
+use rdkafka::{
+    producer::{FutureProducer, FutureRecord},
+    ClientConfig,
+};
+use serde::Serialize;
+
+use crate::{Error, Result};
+
+type KeyFn<T> = Arc<dyn Fn(&T) -> String + Send + Sync>;
+
+/// Publishes the rows of a stream as JSON to a Kafka topic
+///
+/// The producer created by [`KafkaSink::new`] waits for all in-sync replicas to acknowledge each
+/// row and is idempotent, so retries of the producer neither duplicate nor reorder rows within a
+/// partition. Rows are still delivered at least once, since a restarted stream publishes the rows
+/// after its last persisted cursor again. Rows are batched by the producer, while up to
+/// `max_in_flight` rows await their acknowledgement.
+pub struct KafkaSink<T> {
+    producer: FutureProducer,
+    topic: Arc<str>,
+    key: Option<KeyFn<T>>,
+    max_in_flight: usize,
+    queue_timeout: Duration,
+}
+
+impl<T> KafkaSink<T>
+where
+    T: Serialize + Send,
+{
+    /// Create a new [`KafkaSink`] publishing to `topic` of the cluster at `brokers`
+    ///
+    /// `brokers` is a comma separated list of `host:port` pairs. Rows are batched for up to
+    /// `linger` before being sent.
+    pub fn new(brokers: &str, topic: impl Into<String>, linger: Duration) -> Result<Self> {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("acks", "all")
+            .set("enable.idempotence", "true")
+            .set("linger.ms", linger.as_millis().to_string())
+            .create()?;
+        Ok(Self::from_producer(producer, topic))
+    }
+
+    /// Create a new [`KafkaSink`] publishing to `topic` with a custom configured `producer`
+    pub fn from_producer(producer: FutureProducer, topic: impl Into<String>) -> Self {
+        Self {
+            producer,
+            topic: topic.into().into(),
+            key: None,
+            max_in_flight: 1024,
+            queue_timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Set the message key of each row, e.g. the pair, to keep related rows in one partition
+    pub fn with_key<F>(mut self, key: F) -> Self
+    where
+        F: Fn(&T) -> String + Send + Sync + 'static,
+    {
+        self.key = Some(Arc::new(key));
+        self
+    }
+
+    /// Set the maximum number of rows awaiting their acknowledgement, defaults to 1024
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = max_in_flight.max(1);
+        self
+    }
+
+    /// Set how long to wait for space in the full producer queue, defaults to 30s
+    pub fn with_queue_timeout(mut self, queue_timeout: Duration) -> Self {
+        self.queue_timeout = queue_timeout;
+        self
+    }
+
+    /// Publish the rows of `stream`, yielding each row once it was acknowledged
+    ///
+    /// A failed delivery is yielded as an error in place of the row.
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<T>>
+    where
+        S: Stream<Item = Result<T>>,
+    {
+        let Self {
+            producer,
+            topic,
+            key,
+            max_in_flight,
+            queue_timeout,
+        } = self;
+
+        stream
+            .map(move |row| {
+                let producer = producer.clone();
+                let topic = Arc::clone(&topic);
+                let key = key.clone();
+                async move {
+                    let row = row?;
+                    let payload = serde_json::to_vec(&row)?;
+                    let key = key.map(|key| key(&row));
+                    let mut record = FutureRecord::to(&topic).payload(&payload);
+                    if let Some(key) = &key {
+                        record = record.key(key);
+                    }
+                    producer
+                        .send(record, queue_timeout)
+                        .await
+                        .map_err(|(err, _)| Error::from(err))?;
+                    Ok(row)
+                }
+            })
+            .buffered(max_in_flight)
+    }
+}
@@ -0,0 +1,13 @@
+//! Sinks delivering the rows of a stream to external systems
+//!
+//! Each sink is gated behind the feature of the same name. Sinks pass the rows through once they
+//! were delivered, so a cursor persisted afterwards never covers undelivered rows.
+
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "redis")]
+pub mod redis;
+#[cfg(feature = "webhook")]
+pub mod webhook;
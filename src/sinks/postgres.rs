@@ -0,0 +1,374 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use ethers::types::{H160, H256, U256};
+use futures::{stream, SinkExt, Stream, StreamExt};
+use tokio_postgres::Client;
+
+use crate::{
+    types::{PairCreated, Price, Reserves, Side},
+    Result,
+};
+
+/// The schema migrations applied by [`migrate`], in order
+///
+/// Addresses and hashes are stored as `BYTEA`, 256 bit integers as `NUMERIC(78, 0)`.
+pub const MIGRATIONS: &[&str] = &["CREATE TABLE pairs_created (
+        block_number BIGINT NOT NULL,
+        factory BYTEA NOT NULL,
+        pair BYTEA NOT NULL,
+        token0 BYTEA NOT NULL,
+        token1 BYTEA NOT NULL,
+        pair_index NUMERIC(78, 0) NOT NULL,
+        timestamp BIGINT NOT NULL,
+        transaction_hash BYTEA NOT NULL,
+        transaction_index BIGINT NOT NULL
+    );
+    CREATE INDEX pairs_created_pair ON pairs_created (pair);
+    CREATE TABLE prices (
+        block_number BIGINT NOT NULL,
+        pair BYTEA NOT NULL,
+        sender BYTEA NOT NULL,
+        receiver BYTEA NOT NULL,
+        price DOUBLE PRECISION NOT NULL,
+        volume0 DOUBLE PRECISION NOT NULL,
+        volume1 DOUBLE PRECISION NOT NULL,
+        fixed0 NUMERIC(78, 0) NOT NULL,
+        fixed1 NUMERIC(78, 0) NOT NULL,
+        decimals0 SMALLINT NOT NULL,
+        decimals1 SMALLINT NOT NULL,
+        buy BOOLEAN NOT NULL,
+        timestamp BIGINT NOT NULL,
+        transaction_hash BYTEA NOT NULL,
+        transaction_index BIGINT NOT NULL,
+        wash_trade BOOLEAN NOT NULL,
+        log_index BIGINT
+    );
+    CREATE INDEX prices_pair_block ON prices (pair, block_number);
+    CREATE TABLE reserves (
+        event SMALLINT NOT NULL,
+        reserve0 NUMERIC(39, 0) NOT NULL,
+        reserve1 NUMERIC(39, 0) NOT NULL,
+        amount0 NUMERIC(78, 0) NOT NULL,
+        amount1 NUMERIC(78, 0) NOT NULL,
+        lp_amount NUMERIC(78, 0) NOT NULL,
+        protocol_fee NUMERIC(78, 0),
+        block_number BIGINT,
+        pair BYTEA,
+        timestamp BIGINT,
+        transaction_hash BYTEA,
+        transaction_index BIGINT,
+        log_index BIGINT
+    );
+    CREATE INDEX reserves_pair_block ON reserves (pair, block_number);"];
+
+/// Apply the pending [`MIGRATIONS`] to the database of `client`
+///
+/// Applied migrations are tracked in the `superchain_migrations` table, each migration is applied
+/// in its own transaction.
+pub async fn migrate(client: &mut Client) -> Result<()> {
+    client
+        .batch_execute(
+            "CREATE TABLE IF NOT EXISTS superchain_migrations (version INTEGER PRIMARY KEY)",
+        )
+        .await?;
+    let applied: i32 = client
+        .query_one(
+            "SELECT COALESCE(MAX(version), 0) FROM superchain_migrations",
+            &[],
+        )
+        .await?
+        .get(0);
+
+    for (version, migration) in (1..).zip(MIGRATIONS).skip(applied as usize) {
+        let transaction = client.transaction().await?;
+        transaction.batch_execute(migration).await?;
+        transaction
+            .execute(
+                "INSERT INTO superchain_migrations (version) VALUES ($1)",
+                &[&version],
+            )
+            .await?;
+        transaction.commit().await?;
+    }
+    Ok(())
+}
+
+/// A row type, that can be copied into a table created by [`migrate`]
+pub trait Table {
+    /// The name of the table
+    const NAME: &'static str;
+    /// The columns of the table, in the order of [`Table::record`]
+    const COLUMNS: &'static [&'static str];
+
+    /// The fields of this row in the CSV format of `COPY`, an empty field is `NULL`
+    fn record(&self) -> Vec<String>;
+}
+
+fn bytea(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(2 + 2 * bytes.len());
+    hex.push_str("\\x");
+    for byte in bytes {
+        hex.push_str(&format!("{byte:02x}"));
+    }
+    hex
+}
+
+fn address(address: &H160) -> String {
+    bytea(address.as_bytes())
+}
+
+fn hash(hash: &H256) -> String {
+    bytea(hash.as_bytes())
+}
+
+fn numeric(value: &U256) -> String {
+    value.to_string()
+}
+
+/// Postgres spells out infinity, unlike Rust
+fn double(value: f64) -> String {
+    if value == f64::INFINITY {
+        "Infinity".to_owned()
+    } else if value == f64::NEG_INFINITY {
+        "-Infinity".to_owned()
+    } else {
+        value.to_string()
+    }
+}
+
+fn optional(value: Option<impl ToString>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+impl Table for PairCreated {
+    const NAME: &'static str = "pairs_created";
+    const COLUMNS: &'static [&'static str] = &[
+        "block_number",
+        "factory",
+        "pair",
+        "token0",
+        "token1",
+        "pair_index",
+        "timestamp",
+        "transaction_hash",
+        "transaction_index",
+    ];
+
+    fn record(&self) -> Vec<String> {
+        vec![
+            self.block_number.to_string(),
+            address(&self.factory),
+            address(&self.pair),
+            address(&self.token0),
+            address(&self.token1),
+            numeric(&self.pair_index),
+            self.timestamp.to_string(),
+            hash(&self.transaction_hash),
+            self.transaction_index.to_string(),
+        ]
+    }
+}
+
+impl Table for Price {
+    const NAME: &'static str = "prices";
+    const COLUMNS: &'static [&'static str] = &[
+        "block_number",
+        "pair",
+        "sender",
+        "receiver",
+        "price",
+        "volume0",
+        "volume1",
+        "fixed0",
+        "fixed1",
+        "decimals0",
+        "decimals1",
+        "buy",
+        "timestamp",
+        "transaction_hash",
+        "transaction_index",
+        "wash_trade",
+        "log_index",
+    ];
+
+    fn record(&self) -> Vec<String> {
+        vec![
+            self.block_number.to_string(),
+            address(&self.pair),
+            address(&self.sender),
+            address(&self.receiver),
+            double(self.price),
+            double(self.volume0),
+            double(self.volume1),
+            numeric(&self.fixed0),
+            numeric(&self.fixed1),
+            self.decimals0.to_string(),
+            self.decimals1.to_string(),
+            matches!(self.side, Side::Buy).to_string(),
+            self.timestamp.to_string(),
+            hash(&self.transaction_hash),
+            self.transaction_index.to_string(),
+            self.wash_trade.to_string(),
+            optional(self.log_index),
+        ]
+    }
+}
+
+impl Table for Reserves {
+    const NAME: &'static str = "reserves";
+    const COLUMNS: &'static [&'static str] = &[
+        "event",
+        "reserve0",
+        "reserve1",
+        "amount0",
+        "amount1",
+        "lp_amount",
+        "protocol_fee",
+        "block_number",
+        "pair",
+        "timestamp",
+        "transaction_hash",
+        "transaction_index",
+        "log_index",
+    ];
+
+    fn record(&self) -> Vec<String> {
+        vec![
+            (self.event as u8).to_string(),
+            self.reserve0.to_string(),
+            self.reserve1.to_string(),
+            numeric(&self.amount0),
+            numeric(&self.amount1),
+            numeric(&self.lp_amount),
+            self.protocol_fee.as_ref().map(numeric).unwrap_or_default(),
+            optional(self.block_number),
+            self.pair.as_ref().map(address).unwrap_or_default(),
+            optional(self.timestamp),
+            self.transaction_hash.as_ref().map(hash).unwrap_or_default(),
+            optional(self.transaction_index),
+            optional(self.log_index),
+        ]
+    }
+}
+
+/// Bulk inserts the rows of a stream into Postgres with `COPY`
+///
+/// Rows are copied in batches of the rows ready at once, up to the batch size, so a live stream
+/// is written as it arrives, while a backfill is copied in large batches. The tables are created
+/// with [`migrate`].
+pub struct PostgresSink {
+    client: Arc<Client>,
+    batch_size: usize,
+}
+
+impl PostgresSink {
+    /// Create a new [`PostgresSink`] copying with `client`
+    ///
+    /// The connection returned by [`tokio_postgres::connect`] along with `client` has to be
+    /// spawned by the caller.
+    pub fn new(client: Arc<Client>) -> Self {
+        Self {
+            client,
+            batch_size: 1000,
+        }
+    }
+
+    /// Set the maximum number of rows copied at once, defaults to 1000
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Copy the rows of `stream`, yielding each row once its batch was committed
+    ///
+    /// A failed batch is yielded as a single error in place of its rows.
+    pub fn apply<T, S>(&self, stream: S) -> impl Stream<Item = Result<T>>
+    where
+        T: Table,
+        S: Stream<Item = Result<T>>,
+    {
+        let client = Arc::clone(&self.client);
+        stream
+            .ready_chunks(self.batch_size)
+            .then(move |chunk| {
+                let client = Arc::clone(&client);
+                async move {
+                    let mut out = Vec::with_capacity(chunk.len());
+                    let mut batch = Vec::new();
+                    for row in chunk {
+                        match row {
+                            Ok(row) => batch.push(row),
+                            Err(err) => {
+                                flush(&client, &mut batch, &mut out).await;
+                                out.push(Err(err));
+                            }
+                        }
+                    }
+                    flush(&client, &mut batch, &mut out).await;
+                    stream::iter(out)
+                }
+            })
+            .flatten()
+    }
+}
+
+/// Copy `batch` into its table, moving the rows or the error into `out`
+async fn flush<T: Table>(client: &Client, batch: &mut Vec<T>, out: &mut Vec<Result<T>>) {
+    if batch.is_empty() {
+        return;
+    }
+    match copy(client, batch).await {
+        Ok(()) => out.extend(batch.drain(..).map(Ok)),
+        Err(err) => {
+            batch.clear();
+            out.push(Err(err));
+        }
+    }
+}
+
+async fn copy<T: Table>(client: &Client, rows: &[T]) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer.write_record(row.record())?;
+    }
+    let data = writer.into_inner().expect("writing to a Vec never fails");
+
+    let statement = format!(
+        "COPY {} ({}) FROM STDIN (FORMAT csv)",
+        T::NAME,
+        T::COLUMNS.join(", ")
+    );
+    let sink = client.copy_in(&statement).await?;
+    futures::pin_mut!(sink);
+    sink.send(Bytes::from(data)).await?;
+    sink.finish().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::fixtures::{gateway_reserves, price};
+
+    #[test]
+    fn writes_non_finite_doubles_as_postgres_literals() {
+        let price = Price {
+            price: f64::INFINITY,
+            volume0: f64::NEG_INFINITY,
+            volume1: f64::NAN,
+            ..price(1, 10, 0, None)
+        };
+        let record = price.record();
+
+        assert_eq!(record[4..7], ["Infinity", "-Infinity", "NaN"]);
+        assert_eq!(record[16], "");
+    }
+
+    #[test]
+    fn writes_missing_reserve_positions_as_null() {
+        let record = gateway_reserves(1, 2).record();
+
+        assert_eq!(record[7..], ["", "", "", "", "", ""]);
+    }
+}
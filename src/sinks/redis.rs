@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use ::redis::aio::MultiplexedConnection;
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+
+use crate::Result;
+
+type KeyFn<T> = Arc<dyn Fn(&T) -> String + Send + Sync>;
+
+/// Publishes the rows of a stream as JSON to Redis
+///
+/// By default each row is published to a pub/sub channel, which only reaches the subscribers
+/// connected at that time. With [`RedisSink::with_stream`], rows are appended to a Redis stream
+/// instead, so consumers can catch up on missed rows. Commands are pipelined over a single
+/// multiplexed connection, while up to `max_in_flight` rows await their reply.
+pub struct RedisSink<T> {
+    connection: MultiplexedConnection,
+    key: Arc<str>,
+    key_fn: Option<KeyFn<T>>,
+    stream: bool,
+    max_len: Option<usize>,
+    max_in_flight: usize,
+}
+
+impl<T> RedisSink<T>
+where
+    T: Serialize + Send,
+{
+    /// Connect to the Redis server at `url`, e.g. `redis://127.0.0.1/`, and publish to `key`
+    pub async fn connect(url: &str, key: impl Into<String>) -> Result<Self> {
+        let client = ::redis::Client::open(url)?;
+        let connection = client.get_multiplexed_tokio_connection().await?;
+        Ok(Self::from_connection(connection, key))
+    }
+
+    /// Create a new [`RedisSink`] publishing to `key` over an established `connection`
+    pub fn from_connection(connection: MultiplexedConnection, key: impl Into<String>) -> Self {
+        Self {
+            connection,
+            key: key.into().into(),
+            key_fn: None,
+            stream: false,
+            max_len: None,
+            max_in_flight: 1024,
+        }
+    }
+
+    /// Set the channel or stream of each row, e.g. `prices:<pair>` to fan out by pair
+    pub fn with_key<F>(mut self, key: F) -> Self
+    where
+        F: Fn(&T) -> String + Send + Sync + 'static,
+    {
+        self.key_fn = Some(Arc::new(key));
+        self
+    }
+
+    /// Append rows to a Redis stream as the field `data`, instead of publishing them
+    ///
+    /// With a `max_len`, the stream is approximately trimmed to that many entries.
+    pub fn with_stream(mut self, max_len: Option<usize>) -> Self {
+        self.stream = true;
+        self.max_len = max_len;
+        self
+    }
+
+    /// Set the maximum number of rows awaiting their reply, defaults to 1024
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = max_in_flight.max(1);
+        self
+    }
+
+    /// Publish the rows of `stream`, yielding each row once Redis replied
+    ///
+    /// A failed command is yielded as an error in place of the row.
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<T>>
+    where
+        S: Stream<Item = Result<T>>,
+    {
+        let Self {
+            connection,
+            key,
+            key_fn,
+            stream: append,
+            max_len,
+            max_in_flight,
+        } = self;
+
+        stream
+            .map(move |row| {
+                let mut connection = connection.clone();
+                let key = match (&row, &key_fn) {
+                    (Ok(row), Some(key_fn)) => key_fn(row),
+                    _ => key.to_string(),
+                };
+                async move {
+                    let row = row?;
+                    let payload = serde_json::to_string(&row)?;
+                    let mut command = ::redis::cmd(if append { "XADD" } else { "PUBLISH" });
+                    command.arg(&key);
+                    if append {
+                        if let Some(max_len) = max_len {
+                            command.arg("MAXLEN").arg("~").arg(max_len);
+                        }
+                        command.arg("*").arg("data");
+                    }
+                    command
+                        .arg(payload)
+                        .query_async::<_, ()>(&mut connection)
+                        .await?;
+                    Ok(row)
+                }
+            })
+            .buffered(max_in_flight)
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use ethers::utils::hex;
+use futures::{Stream, StreamExt};
+use hmac::{Hmac, Mac};
+use reqwest::{header::CONTENT_TYPE, StatusCode};
+use serde::Serialize;
+use sha2::Sha256;
+use url::Url;
+
+use crate::Result;
+
+type FilterFn<T> = Arc<dyn Fn(&T) -> bool + Send + Sync>;
+type BodyFn<T> = Arc<dyn Fn(&T) -> serde_json::Value + Send + Sync>;
+type DeadLetterFn = Arc<dyn Fn(DeadLetter) + Send + Sync>;
+
+/// The header carrying the unix timestamp in seconds a delivery was signed at
+pub const TIMESTAMP_HEADER: &str = "X-Superchain-Timestamp";
+/// The header carrying the signature of a delivery, as `sha256=<hex>`
+pub const SIGNATURE_HEADER: &str = "X-Superchain-Signature";
+
+/// A delivery, that failed permanently
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct DeadLetter {
+    pub url: String,
+    pub body: String,
+    pub attempts: u32,
+    pub error: String,
+}
+
+/// Forwards the rows of a stream as JSON to an HTTP webhook
+///
+/// Rows are delivered in order, one at a time. Failed deliveries are retried with an exponential
+/// backoff on connection errors, `429` and `5xx` responses, any other response is final. With a
+/// secret, each delivery is signed with HMAC-SHA256 over `<timestamp>.<body>`, so the receiver
+/// can verify its origin and reject replays.
+pub struct WebhookSink<T> {
+    client: reqwest::Client,
+    url: Url,
+    secret: Option<Vec<u8>>,
+    filter: Option<FilterFn<T>>,
+    body: Option<BodyFn<T>>,
+    retries: u32,
+    backoff: Duration,
+    dead_letter: Option<DeadLetterFn>,
+}
+
+impl<T> WebhookSink<T>
+where
+    T: Serialize,
+{
+    /// Create a new [`WebhookSink`] posting to `url`
+    pub fn new(url: Url) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            secret: None,
+            filter: None,
+            body: None,
+            retries: 3,
+            backoff: Duration::from_secs(1),
+            dead_letter: None,
+        }
+    }
+
+    /// Use a custom configured `client`, e.g. with a request timeout
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Sign each delivery with `secret`
+    pub fn with_secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// Only deliver the rows matching `filter`, other rows are passed through
+    pub fn with_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Set the body delivered for a row, e.g. a Slack message, defaults to the row itself
+    pub fn with_body<F>(mut self, body: F) -> Self
+    where
+        F: Fn(&T) -> serde_json::Value + Send + Sync + 'static,
+    {
+        self.body = Some(Arc::new(body));
+        self
+    }
+
+    /// Set how often a failed delivery is retried, and the backoff before the first retry,
+    /// defaults to 3 retries after 1s
+    pub fn with_retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.retries = retries;
+        self.backoff = backoff;
+        self
+    }
+
+    /// Hand permanently failed deliveries to `dead_letter`, instead of yielding them as errors
+    pub fn with_dead_letter<F>(mut self, dead_letter: F) -> Self
+    where
+        F: Fn(DeadLetter) + Send + Sync + 'static,
+    {
+        self.dead_letter = Some(Arc::new(dead_letter));
+        self
+    }
+
+    /// Deliver the rows of `stream`, yielding each row once it was delivered
+    ///
+    /// Without a dead letter handler, a failed delivery is yielded as an error in place of the
+    /// row.
+    pub fn apply<S>(self, stream: S) -> impl Stream<Item = Result<T>>
+    where
+        S: Stream<Item = Result<T>>,
+    {
+        let sink = Arc::new(self);
+        stream.then(move |row| {
+            let sink = Arc::clone(&sink);
+            async move {
+                let row = row?;
+                if matches!(&sink.filter, Some(filter) if !filter(&row)) {
+                    return Ok(row);
+                }
+                let body = match &sink.body {
+                    Some(body) => serde_json::to_string(&body(&row))?,
+                    None => serde_json::to_string(&row)?,
+                };
+
+                match sink.deliver(&body).await {
+                    Ok(()) => Ok(row),
+                    Err((err, attempts)) => match &sink.dead_letter {
+                        Some(dead_letter) => {
+                            dead_letter(DeadLetter {
+                                url: sink.url.to_string(),
+                                body,
+                                attempts,
+                                error: err.to_string(),
+                            });
+                            Ok(row)
+                        }
+                        None => Err(err.into()),
+                    },
+                }
+            }
+        })
+    }
+
+    /// Post `body`, retrying as configured, returning the final error and the number of attempts
+    async fn deliver(&self, body: &str) -> std::result::Result<(), (reqwest::Error, u32)> {
+        let mut backoff = self.backoff;
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let err = match self.post(body).await {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+            let retryable = match err.status() {
+                Some(status) => status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error(),
+                None => true,
+            };
+            if !retryable || attempts > self.retries {
+                return Err((err, attempts));
+            }
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+
+    async fn post(&self, body: &str) -> reqwest::Result<()> {
+        let mut request = self
+            .client
+            .post(self.url.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_owned());
+        if let Some(secret) = &self.secret {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("any key size is valid");
+            mac.update(format!("{timestamp}.{body}").as_bytes());
+            let signature = hex::encode(mac.finalize().into_bytes());
+            request = request
+                .header(TIMESTAMP_HEADER, timestamp)
+                .header(SIGNATURE_HEADER, format!("sha256={signature}"));
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
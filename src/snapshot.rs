@@ -0,0 +1,53 @@
+//! Snapshot-then-deltas subscriptions, combining the HTTP and the WebSocket client
+//!
+//! The history of a pair up to the current height is fetched via HTTP and the WebSocket live
+//! stream is started right after that height, so the combined stream has no gap or overlap
+//! between the snapshot and the deltas.
+
+use ethers::types::H160;
+use futures::{future::OptionFuture, Stream, StreamExt};
+
+use crate::{
+    types::{Price, Reserves},
+    HttpClient, Result, WsClient,
+};
+
+/// Get the uniswap v2 price quotes of `pair` `from_block` upwards following head
+///
+/// The price quotes up to the current height are fetched with `http`, the later ones are
+/// streamed with `ws`.
+pub async fn prices(
+    http: &HttpClient,
+    ws: &WsClient,
+    pair: H160,
+    from_block: u64,
+) -> Result<impl Stream<Item = Result<Price>> + Send> {
+    let height = http.get_height().await?;
+    let snapshot =
+        (from_block <= height).then(|| http.get_prices_in_range(pair, from_block..=height));
+    let snapshot = OptionFuture::from(snapshot).await.transpose()?;
+    let deltas = ws
+        .get_prices([pair], Some(from_block.max(height + 1)), None)
+        .await?;
+    Ok(futures::stream::iter(snapshot).flatten().chain(deltas))
+}
+
+/// Get the uniswap v2 reserves of `pair` `from_block` upwards following head
+///
+/// The reserves up to the current height are fetched with `http`, the later ones are streamed
+/// with `ws`.
+pub async fn reserves(
+    http: &HttpClient,
+    ws: &WsClient,
+    pair: H160,
+    from_block: u64,
+) -> Result<impl Stream<Item = Result<Reserves>> + Send> {
+    let height = http.get_height().await?;
+    let snapshot =
+        (from_block <= height).then(|| http.get_reserves_in_range(pair, from_block..=height));
+    let snapshot = OptionFuture::from(snapshot).await.transpose()?;
+    let deltas = ws
+        .get_reserves([pair], Some(from_block.max(height + 1)), None)
+        .await?;
+    Ok(futures::stream::iter(snapshot).flatten().chain(deltas))
+}
@@ -0,0 +1,46 @@
+use futures::{AsyncBufRead, AsyncBufReadExt, Stream, TryStreamExt};
+
+use crate::{Error, Result};
+
+/// Parse a Server-Sent Events body into the JSON rows carried as `data` of its events
+///
+/// Events of type `error` are yielded as [`Error::Server`] or [`Error::ErrorMsg`], comments and
+/// other fields are ignored. The stream ends with the body.
+pub(crate) fn rows<R, T>(body: R) -> impl Stream<Item = Result<T>>
+where
+    R: AsyncBufRead + Unpin,
+    T: serde::de::DeserializeOwned,
+{
+    let mut event = None::<String>;
+    let mut data = String::new();
+    body.lines()
+        .map_err(Error::from)
+        .try_filter_map(move |line| {
+            if !line.is_empty() {
+                let (field, value) = line.split_once(':').unwrap_or((&line, ""));
+                let value = value.strip_prefix(' ').unwrap_or(value);
+                match field {
+                    "event" => event = Some(value.to_owned()),
+                    "data" => {
+                        if !data.is_empty() {
+                            data.push('\n');
+                        }
+                        data.push_str(value);
+                    }
+                    _ => {}
+                }
+                return futures::future::ready(Ok(None));
+            }
+            if data.is_empty() {
+                event = None;
+                return futures::future::ready(Ok(None));
+            }
+
+            let row = match event.take().as_deref() {
+                Some("error") => Err(Error::from_server_message(std::mem::take(&mut data))),
+                _ => serde_json::from_str(&data).map(Some).map_err(Error::from),
+            };
+            data.clear();
+            futures::future::ready(row)
+        })
+}
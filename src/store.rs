@@ -0,0 +1,123 @@
+//! Durable storage of stream [`Cursor`]s, to resume ingestion exactly where it left off
+//!
+//! Checkpointed streams persist their cursors with
+//! [`Checkpoints::with_store`](crate::adapters::Checkpoints::with_store). After a restart, the
+//! stored cursor is loaded and the stream is resumed right after it, e.g. with
+//! `WsClient::get_prices_after`.
+
+use std::{collections::HashMap, fs, path::PathBuf, sync::Mutex};
+
+use crate::{types::Cursor, Result};
+
+/// A durable key value store of stream cursors
+///
+/// The key identifies the stream, so a single store can hold the cursors of many streams.
+pub trait CheckpointStore: Send + Sync {
+    /// Load the last cursor stored for `key`
+    fn load(&self, key: &str) -> Result<Option<Cursor>>;
+
+    /// Store `cursor` for `key`, replacing the previous one
+    fn save(&self, key: &str, cursor: Cursor) -> Result<()>;
+}
+
+/// A [`CheckpointStore`] keeping all cursors in a single JSON file
+///
+/// The file is replaced atomically on every save, so a crash never leaves it half written.
+pub struct FileStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileStore {
+    /// Create a new [`FileStore`] at `path`, the file is created on the first save
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn read(&self) -> Result<HashMap<String, Cursor>> {
+        match fs::read(&self.path) {
+            Ok(data) => Ok(serde_json::from_slice(&data)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl CheckpointStore for FileStore {
+    fn load(&self, key: &str) -> Result<Option<Cursor>> {
+        let _lock = self.lock.lock().expect("the lock is never poisoned");
+        Ok(self.read()?.get(key).copied())
+    }
+
+    fn save(&self, key: &str, cursor: Cursor) -> Result<()> {
+        let _lock = self.lock.lock().expect("the lock is never poisoned");
+        let mut cursors = self.read()?;
+        cursors.insert(key.to_owned(), cursor);
+
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&cursors)?)?;
+        fs::rename(&tmp, &self.path)?;
+
+        Ok(())
+    }
+}
+
+/// A [`CheckpointStore`] keeping all cursors in a SQLite database
+#[cfg(feature = "sqlite")]
+pub struct SqliteStore {
+    db: Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStore {
+    /// Create a new [`SqliteStore`] in the SQLite database at `path`
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let db = rusqlite::Connection::open(path)?;
+        db.execute_batch(
+            "CREATE TABLE IF NOT EXISTS checkpoints (
+                key TEXT PRIMARY KEY,
+                block INTEGER NOT NULL,
+                tx_index INTEGER NOT NULL
+            )",
+        )?;
+
+        Ok(Self { db: Mutex::new(db) })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl CheckpointStore for SqliteStore {
+    fn load(&self, key: &str) -> Result<Option<Cursor>> {
+        use rusqlite::OptionalExtension;
+
+        let db = self.db.lock().expect("the lock is never poisoned");
+        let cursor = db
+            .query_row(
+                "SELECT block, tx_index FROM checkpoints WHERE key = ?1",
+                [key],
+                |row| {
+                    Ok(Cursor {
+                        block: row.get(0)?,
+                        tx_index: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(cursor)
+    }
+
+    fn save(&self, key: &str, cursor: Cursor) -> Result<()> {
+        let db = self.db.lock().expect("the lock is never poisoned");
+        db.execute(
+            "INSERT OR REPLACE INTO checkpoints (key, block, tx_index) VALUES (?1, ?2, ?3)",
+            rusqlite::params![key, cursor.block, cursor.tx_index],
+        )?;
+
+        Ok(())
+    }
+}
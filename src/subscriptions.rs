@@ -0,0 +1,205 @@
+//! Named subscriptions, that are restored after a restart
+//!
+//! A [`SubscriptionManager`] persists the [`Filter`] of every subscription by name, and the
+//! cursors of its stream in a [`CheckpointStore`] under the same name. After a restart,
+//! [`SubscriptionManager::restore`] re-creates all subscriptions right after their last cursor.
+//!
+//! Reserves the gateway sends without their transaction are resumed after their block. Rows
+//! without a known block don't advance the cursor, so they are replayed after a restart.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use ethers::types::H160;
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
+
+use crate::{
+    adapters::{Checkpointed, Checkpoints, Event},
+    store::CheckpointStore,
+    types::Cursor,
+    Error, ErrorContext, Result, SuperchainProvider,
+};
+
+/// The rows a subscription streams
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Filter {
+    /// The prices of `pair`, starting at `from_block`
+    Prices { pair: H160, from_block: u64 },
+    /// The reserves of `pair`, starting at `from_block`
+    Reserves { pair: H160, from_block: u64 },
+}
+
+/// A running subscription, as returned by [`SubscriptionManager`]
+///
+/// Its stream persists a cursor once the previous rows were yielded, so processing the rows one
+/// by one never skips a row after a restart.
+pub struct Subscription<'a> {
+    pub name: String,
+    pub filter: Filter,
+    pub stream: BoxStream<'a, Result<Checkpointed<Event>>>,
+}
+
+/// Registers head following streams under a name and restores them after a restart
+///
+/// The filters are kept in a single JSON file, which is replaced atomically on every change.
+pub struct SubscriptionManager<P> {
+    provider: P,
+    path: PathBuf,
+    store: Arc<dyn CheckpointStore>,
+    every: u64,
+    filters: Mutex<BTreeMap<String, Filter>>,
+}
+
+impl<P> SubscriptionManager<P>
+where
+    P: SuperchainProvider,
+{
+    /// Open the subscriptions persisted at `path`, with their cursors kept in `store`
+    ///
+    /// The file is created with the first subscription.
+    pub fn open(
+        provider: P,
+        path: impl Into<PathBuf>,
+        store: Arc<dyn CheckpointStore>,
+    ) -> Result<Self> {
+        let path = path.into();
+        let filters = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Self {
+            provider,
+            path,
+            store,
+            every: 1000,
+            filters: Mutex::new(filters),
+        })
+    }
+
+    /// Persist a cursor every `every` rows, defaults to 1000
+    pub fn with_checkpoint_every(mut self, every: u64) -> Self {
+        self.every = every;
+        self
+    }
+
+    /// The names and filters of all registered subscriptions
+    pub fn subscriptions(&self) -> Vec<(String, Filter)> {
+        let filters = self.filters.lock().expect("the lock is never poisoned");
+        filters
+            .iter()
+            .map(|(name, filter)| (name.clone(), *filter))
+            .collect()
+    }
+
+    /// Register the subscription `name` and start streaming it
+    ///
+    /// Subscribing to an already registered name with the same filter resumes it, a different
+    /// filter fails with [`Error::SubscriptionConflict`].
+    pub async fn subscribe(
+        &self,
+        name: impl Into<String>,
+        filter: Filter,
+    ) -> Result<Subscription<'_>> {
+        let name = name.into();
+        {
+            let mut filters = self.filters.lock().expect("the lock is never poisoned");
+            match filters.get(&name) {
+                Some(registered) if *registered != filter => {
+                    return Err(Error::SubscriptionConflict(name))
+                }
+                Some(_) => {}
+                None => {
+                    filters.insert(name.clone(), filter);
+                    self.persist(&filters)?;
+                }
+            }
+        }
+
+        self.start(name, filter).await
+    }
+
+    /// Remove the subscription `name`, returning whether it was registered
+    ///
+    /// A running stream of the subscription is not affected, but it is not restored anymore.
+    pub fn unsubscribe(&self, name: &str) -> Result<bool> {
+        let mut filters = self.filters.lock().expect("the lock is never poisoned");
+        if filters.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.persist(&filters)?;
+
+        Ok(true)
+    }
+
+    /// Re-create all registered subscriptions right after their last persisted cursor
+    pub async fn restore(&self) -> Result<Vec<Subscription<'_>>> {
+        let mut subscriptions = Vec::new();
+        for (name, filter) in self.subscriptions() {
+            subscriptions.push(self.start(name, filter).await?);
+        }
+
+        Ok(subscriptions)
+    }
+
+    async fn start(&self, name: String, filter: Filter) -> Result<Subscription<'_>> {
+        let cursor = self.store.load(&name)?;
+        let from_block = |from_block| cursor.map_or(from_block, |cursor: Cursor| cursor.block);
+        let after = move |row: &Event| match cursor {
+            Some(cursor) => cursor.precedes(row),
+            None => true,
+        };
+
+        let context = ErrorContext::default().with_subscription(&name);
+        let rows = match filter {
+            Filter::Prices {
+                pair,
+                from_block: from,
+            } => self
+                .provider
+                .get_prices(pair, from_block(from), None)
+                .await
+                .map_err(|err| err.with_context(context.clone()))?
+                .map_ok(Event::from)
+                .boxed(),
+            Filter::Reserves {
+                pair,
+                from_block: from,
+            } => self
+                .provider
+                .get_reserves(pair, from_block(from), None)
+                .await
+                .map_err(|err| err.with_context(context.clone()))?
+                .map_ok(Event::from)
+                .boxed(),
+        };
+        let rows = rows
+            .map_err(move |err| err.with_context(context.clone()))
+            .try_filter(move |row| futures::future::ready(after(row)));
+        let stream = Checkpoints::new(self.every)
+            .with_store(Arc::clone(&self.store), name.clone())
+            .apply(rows)
+            .boxed();
+
+        Ok(Subscription {
+            name,
+            filter,
+            stream,
+        })
+    }
+
+    fn persist(&self, filters: &BTreeMap<String, Filter>) -> Result<()> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(filters)?)?;
+        fs::rename(&tmp, &self.path)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+//! Metrics of both clients, recorded with the `metrics` crate
+//!
+//! Without the `metrics` feature all functions are no-ops. Install any `metrics` recorder, e.g.
+//! `metrics-exporter-prometheus`, to export them.
+
+/// The transport a metric was recorded for, used as the `transport` label
+#[derive(Clone, Copy)]
+pub(crate) enum Transport {
+    #[cfg(feature = "http")]
+    Http,
+    #[cfg(any(feature = "ws", feature = "wasm"))]
+    Ws,
+}
+
+#[cfg(feature = "metrics")]
+impl Transport {
+    fn label(self) -> &'static str {
+        match self {
+            #[cfg(feature = "http")]
+            Self::Http => "http",
+            #[cfg(any(feature = "ws", feature = "wasm"))]
+            Self::Ws => "ws",
+        }
+    }
+}
+
+/// Register the descriptions of all metrics with the installed recorder
+#[cfg(feature = "metrics")]
+pub fn describe_metrics() {
+    use metrics::{describe_counter, describe_histogram, Unit};
+
+    describe_counter!(
+        "superchain_requests_total",
+        "The requests sent to the gateway"
+    );
+    describe_counter!("superchain_rows_total", "The rows decoded from responses");
+    describe_counter!(
+        "superchain_bytes_total",
+        Unit::Bytes,
+        "The bytes received in responses"
+    );
+    describe_counter!(
+        "superchain_decode_errors_total",
+        "The rows of responses that failed to decode"
+    );
+    describe_counter!(
+        "superchain_reconnects_total",
+        "The reconnects of the failover client to the WebSocket transport"
+    );
+    describe_histogram!(
+        "superchain_stream_lag_seconds",
+        Unit::Seconds,
+        "The age of rows when they were decoded"
+    );
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn request(transport: Transport) {
+    #[cfg(feature = "metrics")]
+    metrics::increment_counter!("superchain_requests_total", "transport" => transport.label());
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn bytes(transport: Transport, bytes: usize) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("superchain_bytes_total", bytes as u64, "transport" => transport.label());
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn row(transport: Transport) {
+    #[cfg(feature = "metrics")]
+    metrics::increment_counter!("superchain_rows_total", "transport" => transport.label());
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn decode_error(transport: Transport) {
+    #[cfg(feature = "metrics")]
+    metrics::increment_counter!("superchain_decode_errors_total", "transport" => transport.label());
+}
+
+#[cfg(all(feature = "http", feature = "ws"))]
+pub(crate) fn reconnect() {
+    #[cfg(feature = "metrics")]
+    metrics::increment_counter!("superchain_reconnects_total");
+}
+
+/// Record the age of a row with the unix `timestamp`, rows without a timestamp are skipped
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn lag(transport: Transport, timestamp: i64) {
+    #[cfg(feature = "metrics")]
+    if timestamp > 0 {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0.0, |now| now.as_secs_f64());
+        metrics::histogram!(
+            "superchain_stream_lag_seconds",
+            now - timestamp as f64,
+            "transport" => transport.label()
+        );
+    }
+}
+
+/// Count the decoded rows and decode errors of a response
+#[cfg(any(feature = "http", feature = "ws", feature = "wasm"))]
+pub(crate) fn decoded<T>(transport: Transport, row: &crate::Result<T>) {
+    match row {
+        Ok(_) => self::row(transport),
+        Err(crate::Error::CsvAsync(err)) if !err.is_io_error() => decode_error(transport),
+        Err(_) => {}
+    }
+}
@@ -1,9 +1,11 @@
+use std::{num::NonZeroU32, ops::RangeInclusive};
+
 use ethers::types::{Address, H256, U256};
-use serde_repr::Deserialize_repr;
+use serde_repr::{Deserialize_repr, Serialize_repr};
 
 /// A uniswap v2 `PairCreated` event
 /// <https://docs.uniswap.org/protocol/V2/reference/smart-contracts/factory#paircreated>
-#[derive(Clone, Debug, serde::Deserialize)]
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub struct PairCreated {
     pub block_number: u64,
     pub factory: Address,
@@ -16,8 +18,184 @@ pub struct PairCreated {
     pub transaction_index: i64,
 }
 
+/// The position of an event within the chain, ordered chronologically
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub block_number: u64,
+    pub transaction_index: i64,
+    /// The index of the log within the block, `None` if unknown
+    pub log_index: Option<u64>,
+}
+
+impl Position {
+    /// The first position within `block`
+    pub(crate) fn block_start(block_number: u64) -> Self {
+        Self {
+            block_number,
+            transaction_index: i64::MIN,
+            log_index: None,
+        }
+    }
+
+    /// The last position within `block`, used to order rows only known to be in that block
+    pub(crate) fn block_end(block_number: u64) -> Self {
+        Self {
+            block_number,
+            transaction_index: i64::MAX,
+            log_index: Some(u64::MAX),
+        }
+    }
+}
+
+/// A resumable position within a stream, as yielded by
+/// [`Checkpoints`](crate::adapters::Checkpoints)
+///
+/// All rows up to and including the transaction `tx_index` of `block` were yielded before.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Deserialize, serde::Serialize,
+)]
+pub struct Cursor {
+    pub block: u64,
+    pub tx_index: i64,
+}
+
+impl Cursor {
+    /// A cursor covering all rows of `block`, e.g. for rows only known to be in the block
+    pub fn block_end(block: u64) -> Self {
+        Self {
+            block,
+            tx_index: i64::MAX,
+        }
+    }
+
+    /// Whether `row` comes after this cursor, i.e. was not yielded before
+    ///
+    /// Rows without a known position are assumed to come after the cursor, unless their block is
+    /// known to be covered by it, so resuming yields them at least once rather than not at all.
+    pub fn precedes(&self, row: &impl Positioned) -> bool {
+        match row.position() {
+            Some(position) => {
+                (position.block_number, position.transaction_index) > (self.block, self.tx_index)
+            }
+            None => row
+                .block_number()
+                .map_or(true, |block| Cursor::block_end(block) > *self),
+        }
+    }
+}
+
+/// Rows that are emitted at a specific [`Position`] within the chain
+///
+/// The position is `None` if the block or transaction of the row is unknown. Rows without a log
+/// index report a `log_index` of `None`, which orders before any known log index of the same
+/// transaction.
+pub trait Positioned {
+    fn position(&self) -> Option<Position>;
+
+    /// The block of the row, which may be known without its exact position
+    fn block_number(&self) -> Option<u64> {
+        self.position().map(|position| position.block_number)
+    }
+}
+
+impl Positioned for Price {
+    fn position(&self) -> Option<Position> {
+        Some(Position {
+            block_number: self.block_number,
+            transaction_index: self.transaction_index,
+            log_index: self.log_index,
+        })
+    }
+}
+
+impl Positioned for Reserves {
+    fn position(&self) -> Option<Position> {
+        Some(Position {
+            block_number: self.block_number?,
+            transaction_index: self.transaction_index?,
+            log_index: self.log_index,
+        })
+    }
+
+    fn block_number(&self) -> Option<u64> {
+        self.block_number
+    }
+}
+
+macro_rules! impl_positioned_without_log_index {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl Positioned for $ty {
+                fn position(&self) -> Option<Position> {
+                    Some(Position {
+                        block_number: self.block_number,
+                        transaction_index: self.transaction_index,
+                        log_index: None,
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_positioned_without_log_index!(
+    PairCreated,
+    StablecoinSupply,
+    Trace,
+    LpSupply,
+    FlashLoan,
+    BridgeTransfer,
+    UsdPrice,
+    CrossRate,
+    StakingDeposit,
+);
+
+/// A uniswap v2 pair, enriched with the metadata of its tokens and its current reserves
+///
+/// The first fields are the ones of the [`PairCreated`] event of the pair, see
+/// [`PairInfo::pair_created`]. `reserve0` and `reserve1` are the reserves as of
+/// `reserves_block_number`.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct PairInfo {
+    pub block_number: u64,
+    pub factory: Address,
+    pub pair: Address,
+    pub token0: Address,
+    pub token1: Address,
+    pub pair_index: U256,
+    pub timestamp: i64,
+    pub transaction_hash: H256,
+    pub transaction_index: i64,
+    pub symbol0: String,
+    pub symbol1: String,
+    pub name0: String,
+    pub name1: String,
+    pub decimals0: u8,
+    pub decimals1: u8,
+    pub reserve0: u128,
+    pub reserve1: u128,
+    pub reserves_block_number: u64,
+}
+
+impl PairInfo {
+    /// The `PairCreated` event of this pair
+    pub fn pair_created(&self) -> PairCreated {
+        PairCreated {
+            block_number: self.block_number,
+            factory: self.factory,
+            pair: self.pair,
+            token0: self.token0,
+            token1: self.token1,
+            pair_index: self.pair_index,
+            timestamp: self.timestamp,
+            transaction_hash: self.transaction_hash,
+            transaction_index: self.transaction_index,
+        }
+    }
+}
+
 /// A uniswap v2 price quote
-#[derive(Clone, Debug, serde::Deserialize)]
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub struct Price {
     pub block_number: u64,
     pub pair: Address,
@@ -34,10 +212,227 @@ pub struct Price {
     pub timestamp: i64,
     pub transaction_hash: H256,
     pub transaction_index: i64,
+    /// Whether the gateway classified this trade as a self-trade or wash trade
+    #[serde(default)]
+    pub wash_trade: bool,
+    /// The index of the swap log within the block, `None` if the gateway didn't provide it
+    pub log_index: Option<u64>,
+}
+
+/// Additional options for price quote requests
+///
+/// The default options yield all price quotes unfiltered.
+#[derive(Clone, Debug, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceOptions {
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    exclude_wash_trades: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    senders: Vec<[u8; 20]>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    receivers: Vec<[u8; 20]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    side: Option<Side>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sampling: Option<Sampling>,
+}
+
+impl PriceOptions {
+    /// Exclude self-trades and wash trades from the price quotes
+    ///
+    /// The excluded quotes are the ones, that would have [`Price::wash_trade`] set.
+    pub fn with_exclude_wash_trades(mut self, exclude: bool) -> Self {
+        self.exclude_wash_trades = exclude;
+        self
+    }
+
+    /// Only yield price quotes of swaps sent by one of the provided `senders`
+    ///
+    /// An empty list yields the swaps of all senders.
+    pub fn with_senders(mut self, senders: impl IntoIterator<Item = Address>) -> Self {
+        self.senders = senders.into_iter().map(|sender| sender.0).collect();
+        self
+    }
+
+    /// Only yield price quotes of swaps received by one of the provided `receivers`
+    ///
+    /// An empty list yields the swaps of all receivers.
+    pub fn with_receivers(mut self, receivers: impl IntoIterator<Item = Address>) -> Self {
+        self.receivers = receivers.into_iter().map(|receiver| receiver.0).collect();
+        self
+    }
+
+    /// Only yield price quotes of the provided `side`
+    ///
+    /// A `side` of `None` yields buys and sells.
+    pub fn with_side(mut self, side: Option<Side>) -> Self {
+        self.side = side;
+        self
+    }
+
+    /// Sample the price quotes gateway-side, see [`Sampling`]
+    ///
+    /// A `sampling` of `None` yields all price quotes.
+    pub fn with_sampling(mut self, sampling: Option<Sampling>) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    #[cfg(feature = "http")]
+    pub(crate) fn append_query_pairs(&self, url: &mut url::Url) {
+        let mut query = Vec::new();
+        if self.exclude_wash_trades {
+            query.push(("exclude_wash_trades", "true".to_owned()));
+        }
+        for sender in &self.senders {
+            query.push(("sender", format!("{:x}", Address::from(*sender))));
+        }
+        for receiver in &self.receivers {
+            query.push(("receiver", format!("{:x}", Address::from(*receiver))));
+        }
+        match self.side {
+            Some(Side::Buy) => query.push(("side", "true".to_owned())),
+            Some(Side::Sell) => query.push(("side", "false".to_owned())),
+            None => {}
+        }
+        if let Some(sampling) = self.sampling {
+            query.push(("sampling", sampling.to_string()));
+        }
+
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+    }
+}
+
+/// Additional options for reserves requests
+///
+/// The default options yield all reserves unfiltered.
+#[derive(Clone, Debug, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReserveOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sampling: Option<Sampling>,
+}
+
+impl ReserveOptions {
+    /// Sample the reserves gateway-side, see [`Sampling`]
+    ///
+    /// A `sampling` of `None` yields all reserves.
+    pub fn with_sampling(mut self, sampling: Option<Sampling>) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    #[cfg(feature = "http")]
+    pub(crate) fn append_query_pairs(&self, url: &mut url::Url) {
+        if let Some(sampling) = self.sampling {
+            url.query_pairs_mut()
+                .append_pair("sampling", &sampling.to_string());
+        }
+    }
+}
+
+/// Gateway-side sampling, for long histories at a reduced resolution
+///
+/// Both transports send it as the `sampling` parameter, either `block` or `every_<n>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sampling {
+    /// Only yield the last row of each block
+    PerBlock,
+    /// Only yield every nth row
+    EveryNth(NonZeroU32),
+}
+
+impl Sampling {
+    /// Only yield every `n`th row, `None` for an `n` of zero
+    pub fn every_nth(n: u32) -> Option<Self> {
+        NonZeroU32::new(n).map(Self::EveryNth)
+    }
+}
+
+impl std::fmt::Display for Sampling {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PerBlock => f.write_str("block"),
+            Self::EveryNth(n) => write!(f, "every_{n}"),
+        }
+    }
+}
+
+impl serde::Serialize for Sampling {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// The traded volume of a uniswap v2 pair within one aggregation interval
+///
+/// The interval spans the blocks `start_block..=end_block`, `swaps` is the number of swaps within.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct Volume {
+    pub pair: Address,
+    pub start_block: u64,
+    pub end_block: u64,
+    pub start_timestamp: i64,
+    pub end_timestamp: i64,
+    pub volume0: f64,
+    pub volume1: f64,
+    pub volume_usd: f64,
+    pub swaps: u64,
+}
+
+/// The size of the intervals the gateway aggregates rows into
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Interval {
+    /// Intervals spanning the given number of blocks
+    Blocks(u64),
+    /// Intervals spanning the given number of seconds
+    Seconds(u64),
+}
+
+impl Interval {
+    #[cfg(feature = "http")]
+    pub(crate) fn append_query_pairs(&self, url: &mut url::Url) {
+        match self {
+            Interval::Blocks(blocks) => url
+                .query_pairs_mut()
+                .append_pair("interval_blocks", &blocks.to_string()),
+            Interval::Seconds(seconds) => url
+                .query_pairs_mut()
+                .append_pair("interval_seconds", &seconds.to_string()),
+        };
+    }
+}
+
+/// The liquidity of a uniswap v2 pair at one slippage level, derived from its reserves
+///
+/// Swapping `amount_in` moves the price of the pair by `slippage_bps` basis points and yields
+/// `amount_out`. `side` is the direction of that swap, like in [`Price::side`].
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct DepthLevel {
+    pub block_number: u64,
+    pub pair: Address,
+    pub side: Side,
+    pub slippage_bps: u32,
+    pub amount_in: U256,
+    pub amount_out: U256,
+}
+
+/// All pair, price and reserve events emitted by a single transaction
+#[derive(Clone, Debug, Default)]
+pub struct TransactionEvents {
+    pub pairs_created: Vec<PairCreated>,
+    pub prices: Vec<Price>,
+    pub reserves: Vec<Reserves>,
 }
 
 /// The direction of transaction
-#[derive(Clone, Copy, Debug, serde::Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 pub enum Side {
     #[serde(rename = "true")]
     Buy,
@@ -45,7 +440,7 @@ pub enum Side {
     Sell,
 }
 
-#[derive(Clone, Debug, serde::Deserialize)]
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub struct Reserves {
     pub event: Type,
     pub reserve0: u128,
@@ -54,9 +449,17 @@ pub struct Reserves {
     pub amount1: U256,
     pub lp_amount: U256,
     pub protocol_fee: Option<U256>,
+    /// The position of the event, `None` if the gateway didn't provide it
+    pub block_number: Option<u64>,
+    pub pair: Option<Address>,
+    pub timestamp: Option<i64>,
+    pub transaction_hash: Option<H256>,
+    pub transaction_index: Option<i64>,
+    /// The index of the log within the block, `None` if the gateway didn't provide it
+    pub log_index: Option<u64>,
 }
 
-#[derive(Clone, Copy, Debug, Deserialize_repr)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize_repr, Serialize_repr)]
 #[repr(u8)]
 pub enum Type {
     Mint,
@@ -64,3 +467,410 @@ pub enum Type {
     Swap,
     Sync,
 }
+
+/// A stablecoin supply change, emitted whenever tokens are minted or burned
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct StablecoinSupply {
+    pub block_number: u64,
+    pub token: Address,
+    pub account: Address,
+    pub change: SupplyChange,
+    pub amount: U256,
+    pub total_supply: U256,
+    pub timestamp: i64,
+    pub transaction_hash: H256,
+    pub transaction_index: i64,
+}
+
+/// The direction of a supply change
+#[derive(Clone, Copy, Debug, Deserialize_repr)]
+#[repr(u8)]
+pub enum SupplyChange {
+    Mint,
+    Burn,
+}
+
+/// An internal call (trace) executed during a transaction
+///
+/// `trace_address` is the position of the call within the transaction's call tree, encoded as
+/// dash-separated indices (e.g. `0-2-1`). The top level call has an empty trace address.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct Trace {
+    pub block_number: u64,
+    pub transaction_hash: H256,
+    pub transaction_index: i64,
+    pub trace_address: String,
+    pub call_type: CallType,
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+    pub gas: U256,
+    pub gas_used: U256,
+    pub success: bool,
+    pub timestamp: i64,
+}
+
+/// The kind of an internal call
+#[derive(Clone, Copy, Debug, Deserialize_repr)]
+#[repr(u8)]
+pub enum CallType {
+    Call,
+    DelegateCall,
+    StaticCall,
+    CallCode,
+    Create,
+    Create2,
+    SelfDestruct,
+}
+
+/// A chain reorganization observed by the gateway
+///
+/// `old_head` and `new_head` are the hashes of the chain heads before and after the reorg, `depth`
+/// is the number of blocks that were orphaned.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct Reorg {
+    pub old_head: H256,
+    pub old_head_number: u64,
+    pub new_head: H256,
+    pub new_head_number: u64,
+    pub depth: u64,
+    pub timestamp: i64,
+}
+
+impl Reorg {
+    /// The block numbers of the orphaned blocks
+    ///
+    /// Any data ingested from these blocks before the reorg has to be considered invalid.
+    pub fn affected_blocks(&self) -> RangeInclusive<u64> {
+        (self.old_head_number + 1).saturating_sub(self.depth)..=self.old_head_number
+    }
+}
+
+/// The rate limit of the API key, as reported by the gateway with each HTTP response
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    /// The number of requests allowed per window
+    pub limit: u64,
+    pub remaining: u64,
+    /// The unix timestamp the current window resets at
+    pub reset: i64,
+}
+
+/// The usage of the API key in the current billing period
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct Usage {
+    pub requests: u64,
+    pub request_quota: Option<u64>,
+    pub rows: u64,
+    pub row_quota: Option<u64>,
+    /// The unix timestamp the current period ends at
+    pub period_end: i64,
+}
+
+impl Usage {
+    /// The number of requests left in the current period, `None` if unlimited
+    pub fn remaining_requests(&self) -> Option<u64> {
+        self.request_quota
+            .map(|quota| quota.saturating_sub(self.requests))
+    }
+
+    /// The number of rows left in the current period, `None` if unlimited
+    pub fn remaining_rows(&self) -> Option<u64> {
+        self.row_quota.map(|quota| quota.saturating_sub(self.rows))
+    }
+}
+
+/// The finality status of a block
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct Finality {
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub status: FinalityStatus,
+}
+
+/// How settled a block is, ordered from provisional to final
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize_repr)]
+#[repr(u8)]
+pub enum FinalityStatus {
+    /// The block is part of the canonical chain, but might still be reorged
+    Unsafe,
+    Safe,
+    Justified,
+    Finalized,
+}
+
+/// The USD denominated total value locked in a uniswap v2 pair
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct Tvl {
+    pub block_number: u64,
+    pub pair: Address,
+    pub reserve0_usd: f64,
+    pub reserve1_usd: f64,
+    pub tvl_usd: f64,
+    pub timestamp: i64,
+}
+
+/// A change of the total LP token supply of a uniswap v2 pair
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct LpSupply {
+    pub block_number: u64,
+    pub pair: Address,
+    pub total_supply: U256,
+    pub timestamp: i64,
+    pub transaction_hash: H256,
+    pub transaction_index: i64,
+}
+
+/// An Aave `FlashLoan` event
+/// <https://docs.aave.com/developers/core-contracts/pool#flashloan>
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct FlashLoan {
+    pub block_number: u64,
+    pub pool: Address,
+    pub target: Address,
+    pub initiator: Address,
+    pub asset: Address,
+    pub amount: U256,
+    pub premium: U256,
+    pub referral_code: u16,
+    pub timestamp: i64,
+    pub transaction_hash: H256,
+    pub transaction_index: i64,
+}
+
+/// A MEV event labeled by the gateway, linking the attacker and victim swaps of a block
+///
+/// For sandwiches, `front_run_transaction_hash` and `back_run_transaction_hash` are the attacker's
+/// swaps surrounding the victim's swap. For all other kinds only `front_run_transaction_hash` is
+/// set, containing the attacker's transaction.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct MevEvent {
+    pub block_number: u64,
+    pub kind: MevKind,
+    pub pair: Address,
+    pub attacker: Address,
+    pub victim: Option<Address>,
+    pub front_run_transaction_hash: H256,
+    pub victim_transaction_hash: Option<H256>,
+    pub back_run_transaction_hash: Option<H256>,
+    pub profit_usd: f64,
+    pub timestamp: i64,
+}
+
+impl MevEvent {
+    /// Whether the transaction with the provided `hash` is part of this MEV event, either as
+    /// attacker or as victim
+    pub fn involves(&self, hash: H256) -> bool {
+        self.front_run_transaction_hash == hash
+            || self.victim_transaction_hash == Some(hash)
+            || self.back_run_transaction_hash == Some(hash)
+    }
+}
+
+/// The kind of a MEV event
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize_repr)]
+#[repr(u8)]
+pub enum MevKind {
+    Sandwich,
+    Arbitrage,
+    Backrun,
+    Liquidation,
+}
+
+/// A token transfer through one of the major cross-chain bridges
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct BridgeTransfer {
+    pub block_number: u64,
+    pub bridge: Address,
+    pub direction: BridgeDirection,
+    pub token: Address,
+    pub sender: Address,
+    pub recipient: Address,
+    pub amount: U256,
+    pub source_chain_id: u64,
+    pub destination_chain_id: u64,
+    pub timestamp: i64,
+    pub transaction_hash: H256,
+    pub transaction_index: i64,
+}
+
+/// Whether tokens were deposited into or withdrawn from a bridge
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize_repr)]
+#[repr(u8)]
+pub enum BridgeDirection {
+    Deposit,
+    Withdrawal,
+}
+
+/// A beacon chain staking deposit made through the deposit contract
+///
+/// `pubkey` is the hex encoded BLS public key of the validator and `amount` is denominated in
+/// gwei. The `validator_index` is only known once the beacon chain processed the deposit.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct StakingDeposit {
+    pub block_number: u64,
+    pub validator_index: Option<u64>,
+    pub pubkey: String,
+    pub withdrawal_credentials: H256,
+    pub depositor: Address,
+    pub amount: u64,
+    pub timestamp: i64,
+    pub transaction_hash: H256,
+    pub transaction_index: i64,
+}
+
+/// A beacon chain withdrawal credited to the execution layer
+///
+/// `amount` is denominated in gwei.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct StakingWithdrawal {
+    pub block_number: u64,
+    pub withdrawal_index: u64,
+    pub validator_index: u64,
+    pub address: Address,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+/// The swap fees accrued by a uniswap v2 pair within a block
+///
+/// `fee0` and `fee1` are the raw token amounts paid to the liquidity providers, `fees_usd` is their
+/// combined USD value.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct Fees {
+    pub block_number: u64,
+    pub pair: Address,
+    pub fee0: U256,
+    pub fee1: U256,
+    pub fees_usd: f64,
+    pub swaps: u64,
+    pub timestamp: i64,
+}
+
+/// The USD price of a token, routed by the gateway through liquid stable pairs
+///
+/// `pair` is the last pair of the route, which is the one whose trade updated the price.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct UsdPrice {
+    pub block_number: u64,
+    pub token: Address,
+    pub pair: Address,
+    pub price_usd: f64,
+    pub timestamp: i64,
+    pub transaction_hash: H256,
+    pub transaction_index: i64,
+}
+
+/// A synthetic exchange rate between two tokens, derived by the gateway by routing through
+/// intermediate pairs
+///
+/// `rate` is the amount of `token_b` per `token_a` and `hops` is the number of pairs the route
+/// consists of.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct CrossRate {
+    pub block_number: u64,
+    pub token_a: Address,
+    pub token_b: Address,
+    pub rate: f64,
+    pub hops: u8,
+    pub timestamp: i64,
+    pub transaction_hash: H256,
+    pub transaction_index: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampling_uses_one_parameter() {
+        assert_eq!(Sampling::every_nth(0), None);
+
+        let options = PriceOptions::default().with_sampling(Sampling::every_nth(5));
+        let value = serde_json::to_value(options).unwrap();
+        assert_eq!(value, serde_json::json!({ "sampling": "every_5" }));
+
+        let options = ReserveOptions::default().with_sampling(Some(Sampling::PerBlock));
+        let value = serde_json::to_value(options).unwrap();
+        assert_eq!(value, serde_json::json!({ "sampling": "block" }));
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn sampling_is_a_single_query_pair() {
+        let mut url: url::Url = "https://example.com/".parse().unwrap();
+        PriceOptions::default()
+            .with_sampling(Sampling::every_nth(5))
+            .append_query_pairs(&mut url);
+        ReserveOptions::default()
+            .with_sampling(Some(Sampling::PerBlock))
+            .append_query_pairs(&mut url);
+
+        assert_eq!(url.query(), Some("sampling=every_5&sampling=block"));
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::*;
+
+    /// A swap of `pair` at the provided position, priced at 2.0
+    pub(crate) fn price(pair: u64, block: u64, tx: i64, log_index: Option<u64>) -> Price {
+        Price {
+            block_number: block,
+            pair: Address::from_low_u64_be(pair),
+            sender: Address::zero(),
+            receiver: Address::zero(),
+            price: 2.0,
+            volume0: 1.0,
+            volume1: 2.0,
+            fixed0: U256::zero(),
+            fixed1: U256::zero(),
+            decimals0: 18,
+            decimals1: 18,
+            side: Side::Buy,
+            timestamp: block as i64 * 12,
+            transaction_hash: H256::from_low_u64_be(block * 1000 + tx as u64),
+            transaction_index: tx,
+            wash_trade: false,
+            log_index,
+        }
+    }
+
+    /// A swap like the gateway sends it, without a log index
+    pub(crate) fn gateway_price(pair: u64, block: u64, tx: i64) -> Price {
+        price(pair, block, tx, None)
+    }
+
+    /// A `Sync` event of `pair` at the provided position
+    pub(crate) fn reserves(pair: u64, block: u64, tx: i64, log_index: Option<u64>) -> Reserves {
+        Reserves {
+            block_number: Some(block),
+            pair: Some(Address::from_low_u64_be(pair)),
+            timestamp: Some(block as i64 * 12),
+            transaction_hash: Some(H256::from_low_u64_be(block * 1000 + tx as u64)),
+            transaction_index: Some(tx),
+            log_index,
+            ..gateway_reserves(1, 2)
+        }
+    }
+
+    /// A `Sync` event like the gateway sends it, without a pair or position
+    pub(crate) fn gateway_reserves(reserve0: u128, reserve1: u128) -> Reserves {
+        Reserves {
+            event: Type::Sync,
+            reserve0,
+            reserve1,
+            amount0: U256::zero(),
+            amount1: U256::zero(),
+            lp_amount: U256::zero(),
+            protocol_fee: None,
+            block_number: None,
+            pair: None,
+            timestamp: None,
+            transaction_hash: None,
+            transaction_index: None,
+            log_index: None,
+        }
+    }
+}
@@ -1,15 +1,26 @@
-use ethers::types::H160;
+use ethers::types::{H160, H256};
 use futures::{SinkExt, Stream, StreamExt, TryStreamExt};
-use tokio::{
-    io::{AsyncRead, AsyncWrite},
-    sync::mpsc,
-};
+#[cfg(feature = "ws")]
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc;
+#[cfg(feature = "ws")]
 use tokio_tungstenite::WebSocketStream;
+#[cfg(feature = "ws")]
 use tungstenite::Message;
 
+#[cfg(feature = "ws")]
+use crate::shutdown::{self, Shutdown};
 use crate::{
-    types::{PairCreated, Price, Reserves},
-    Error, Result,
+    block_time::BlockTimeCache,
+    error,
+    telemetry::{self, Transport},
+    types::{
+        BridgeTransfer, CrossRate, Cursor, DepthLevel, Fees, Finality, FlashLoan, Interval,
+        LpSupply, MevEvent, PairCreated, PairInfo, Price, PriceOptions, Reorg, ReserveOptions,
+        Reserves, StablecoinSupply, StakingDeposit, StakingWithdrawal, Trace, TransactionEvents,
+        Tvl, UsdPrice, Volume,
+    },
+    DecodePolicy, Error, ErrorContext, Result,
 };
 
 type WsMsg = Result<Vec<u8>>;
@@ -18,18 +29,116 @@ type OperationMsg = (Operation, mpsc::UnboundedSender<WsMsg>);
 /// A Superchain WebSocket client
 pub struct Client {
     backend_tx: mpsc::Sender<OperationMsg>,
+    #[cfg(feature = "ws")]
+    shutdown_tx: mpsc::UnboundedSender<(Shutdown, shutdown::Worker)>,
+    block_times: BlockTimeCache,
+    decode_policy: DecodePolicy,
+    fast_decode: bool,
 }
 
 impl Client {
     /// Create a new [`Client`]
+    #[cfg(feature = "ws")]
     pub async fn new<S>(websocket: WebSocketStream<S>) -> Self
     where
         S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
     {
         let (tx, rx) = mpsc::channel(1024);
-        tokio::spawn(BackGroundWorker::new(websocket, rx).run());
+        let (shutdown_tx, shutdown_rx) = mpsc::unbounded_channel();
+        tokio::spawn(BackGroundWorker::new(websocket, rx, shutdown_rx).run());
+
+        Self {
+            backend_tx: tx,
+            shutdown_tx,
+            block_times: BlockTimeCache::default(),
+            decode_policy: DecodePolicy::default(),
+            fast_decode: false,
+        }
+    }
+
+    /// Connect to the WebSocket endpoint of `config`, authenticating with its credentials
+    #[cfg(feature = "ws")]
+    pub async fn connect_with_config(config: &crate::config::Config) -> Result<Self> {
+        Self::connect_with_credentials(&config.ws_url, &config.credentials()).await
+    }
 
-        Self { backend_tx: tx }
+    /// Connect to the WebSocket endpoint at `url`, authenticating with `credentials`
+    ///
+    /// The gateway checks the credentials once during the handshake, so rotating them only
+    /// takes effect with the next connection.
+    #[cfg(feature = "ws")]
+    pub async fn connect_with_credentials(
+        url: &url::Url,
+        credentials: &crate::auth::Credentials,
+    ) -> Result<Self> {
+        use tungstenite::client::IntoClientRequest;
+
+        let mut request = url.as_str().into_client_request()?;
+        request.headers_mut().append(
+            tungstenite::http::header::AUTHORIZATION,
+            credentials
+                .authorization()
+                .await?
+                .parse()
+                .map_err(|_| Error::Custom("invalid authorization value".to_owned()))?,
+        );
+        let (websocket, _) = tokio_tungstenite::connect_async(request).await?;
+        Ok(Self::new(websocket).await)
+    }
+
+    /// Connect to `url` with the WebSocket API of the browser, when compiled to WebAssembly
+    ///
+    /// Browsers don't allow setting headers on WebSocket requests, so credentials have to be
+    /// provided differently, e.g. as part of `url` or with a cookie of the gateway domain.
+    #[cfg(feature = "wasm")]
+    pub fn connect_browser(url: &str) -> Result<Self> {
+        let websocket = gloo_net::websocket::futures::WebSocket::open(url)
+            .map_err(|err| Error::Custom(err.to_string()))?;
+        let (tx, rx) = mpsc::channel(1024);
+        let worker = BrowserWorker {
+            websocket,
+            operation_rx: rx,
+            subscriptions: Subscriptions::new(),
+        };
+        wasm_bindgen_futures::spawn_local(async move {
+            let _ = worker.run().await;
+        });
+
+        Ok(Self {
+            backend_tx: tx,
+            // Browser connections aren't hooked into a shutdown, the sent worker is dropped at once
+            #[cfg(feature = "ws")]
+            shutdown_tx: mpsc::unbounded_channel().0,
+            block_times: BlockTimeCache::default(),
+            decode_policy: DecodePolicy::default(),
+            fast_decode: false,
+        })
+    }
+
+    /// Close the connection once `shutdown` is triggered
+    ///
+    /// The connection counts as a worker of `shutdown`, so [`Shutdown::stopped`] only resolves
+    /// after the close frame was sent. All streams of the client end with the connection. Browser
+    /// connections are not closed.
+    #[cfg(feature = "ws")]
+    pub fn with_shutdown(self, shutdown: &Shutdown) -> Self {
+        // Fails once the connection is gone, which drops the worker right away
+        let _ = self.shutdown_tx.send((shutdown.clone(), shutdown.worker()));
+        self
+    }
+
+    /// Set how rows, that fail to decode, are handled, defaults to [`DecodePolicy::FailFast`]
+    pub fn with_decode_policy(mut self, decode_policy: DecodePolicy) -> Self {
+        self.decode_policy = decode_policy;
+        self
+    }
+
+    /// Decode responses with a single reused row buffer, instead of allocating per row
+    ///
+    /// This cuts the CPU time of high-throughput streams. The rows are the same either way.
+    pub fn with_fast_decode(mut self) -> Self {
+        self.fast_decode = true;
+        self
     }
 
     /// Get the uniswap v2 pair created events for the provided `pairs_filter` within the specified
@@ -54,6 +163,30 @@ impl Client {
         .await
     }
 
+    /// Get the uniswap v2 pair created events of all pairs containing the provided `token`.
+    ///
+    /// This yields the pairs of all indexed factories, where `token` is either `token0` or
+    /// `token1`.
+    pub async fn get_pairs_for_token(
+        &self,
+        token: H160,
+    ) -> Result<impl Stream<Item = Result<PairCreated>> + Send> {
+        self.request(Operation::GetPairsForToken { token: token.0 })
+            .await
+    }
+
+    /// Get the uniswap v2 pair created event of the provided `pair`, joined with the symbols,
+    /// names and decimals of its tokens and its current reserves.
+    ///
+    /// Returns `None` if the pair is not indexed.
+    pub async fn get_pair_info(&self, pair: H160) -> Result<Option<PairInfo>> {
+        let stream = self
+            .request(Operation::GetPairInfo { pair: pair.0 })
+            .await?;
+        futures::pin_mut!(stream);
+        stream.next().await.transpose()
+    }
+
     /// Get the uniswap v2 price quotes for the provided `pairs_filter` within the specified
     /// block range.
     ///
@@ -68,14 +201,76 @@ impl Client {
         from_block: Option<u64>,
         to_block_inc: Option<u64>,
     ) -> Result<impl Stream<Item = Result<Price>> + Send> {
-        self.request(Operation::GetPrices {
-            pairs: pairs_filter.into_iter().map(|pair| pair.0).collect(),
-            start: from_block,
-            end: to_block_inc,
-        })
+        self.get_prices_with_options(
+            pairs_filter,
+            from_block,
+            to_block_inc,
+            PriceOptions::default(),
+        )
         .await
     }
 
+    /// Get the most recent uniswap v2 price quote of the provided `pair`.
+    ///
+    /// Returns `None` if the pair was never traded.
+    pub async fn get_latest_price(&self, pair: H160) -> Result<Option<Price>> {
+        let stream = self
+            .request(Operation::GetLatestPrice { pair: pair.0 })
+            .await?;
+        futures::pin_mut!(stream);
+        stream.next().await.transpose()
+    }
+
+    /// Get the uniswap v2 price quotes like [`Client::get_prices`], additionally applying the
+    /// provided `options` gateway-side.
+    pub async fn get_prices_with_options(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+        options: PriceOptions,
+    ) -> Result<impl Stream<Item = Result<Price>> + Send> {
+        let stream = self
+            .request(Operation::GetPrices {
+                pairs: pairs_filter.into_iter().map(|pair| pair.0).collect(),
+                start: from_block,
+                end: to_block_inc,
+                options,
+            })
+            .await?;
+        Ok(stream.inspect_ok(|price: &Price| telemetry::lag(Transport::Ws, price.timestamp)))
+    }
+
+    /// Get the uniswap v2 price quotes like [`Client::get_prices`], in batches of up to
+    /// `batch_size` quotes
+    ///
+    /// The quotes are decoded in place into each batch, which saves a lot of per-row overhead on
+    /// large backfills. Malformed rows are handled by the [`DecodePolicy`] of the client.
+    pub async fn get_prices_batched(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+        batch_size: usize,
+    ) -> Result<impl Stream<Item = Result<Vec<Price>>> + Send> {
+        let stream = self
+            .request_batched(
+                Operation::GetPrices {
+                    pairs: pairs_filter.into_iter().map(|pair| pair.0).collect(),
+                    start: from_block,
+                    end: to_block_inc,
+                    options: PriceOptions::default(),
+                },
+                batch_size,
+            )
+            .await?;
+        Ok(stream.inspect_ok(|prices: &Vec<Price>| {
+            if let Some(price) = prices.last() {
+                telemetry::lag(Transport::Ws, price.timestamp);
+            }
+        }))
+    }
+
     /// Get the reserves v2 price quotes for the provided `pairs_filter` within the specified
     /// block range.
     ///
@@ -90,7 +285,131 @@ impl Client {
         from_block: Option<u64>,
         to_block_inc: Option<u64>,
     ) -> Result<impl Stream<Item = Result<Reserves>> + Send> {
-        self.request(Operation::GetReserves {
+        self.get_reserves_with_options(
+            pairs_filter,
+            from_block,
+            to_block_inc,
+            ReserveOptions::default(),
+        )
+        .await
+    }
+
+    /// Get the uniswap v2 reserves like [`Client::get_reserves`], additionally applying the
+    /// provided `options` gateway-side.
+    pub async fn get_reserves_with_options(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+        options: ReserveOptions,
+    ) -> Result<impl Stream<Item = Result<Reserves>> + Send> {
+        let stream = self
+            .request(Operation::GetReserves {
+                pairs: pairs_filter.into_iter().map(|pair| pair.0).collect(),
+                start: from_block,
+                end: to_block_inc,
+                options,
+            })
+            .await?;
+        Ok(stream.inspect_ok(|reserves: &Reserves| {
+            if let Some(timestamp) = reserves.timestamp {
+                telemetry::lag(Transport::Ws, timestamp);
+            }
+        }))
+    }
+
+    /// Resume the uniswap v2 price quotes for the provided `pairs_filter` right after `cursor`.
+    ///
+    /// See [`Client::get_prices`] for the `pairs_filter` and `to_block_inc`.
+    pub async fn get_prices_after(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        cursor: Cursor,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<Price>> + Send> {
+        let stream = self
+            .get_prices(pairs_filter, Some(cursor.block), to_block_inc)
+            .await?;
+        Ok(stream.try_filter(move |price| futures::future::ready(cursor.precedes(price))))
+    }
+
+    /// Resume the uniswap v2 reserves for the provided `pairs_filter` right after `cursor`.
+    ///
+    /// See [`Client::get_reserves`] for the `pairs_filter` and `to_block_inc`.
+    pub async fn get_reserves_after(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        cursor: Cursor,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<Reserves>> + Send> {
+        let stream = self
+            .get_reserves(pairs_filter, Some(cursor.block), to_block_inc)
+            .await?;
+        Ok(stream.try_filter(move |reserves| futures::future::ready(cursor.precedes(reserves))))
+    }
+
+    /// Get the stablecoin mint and burn events for the provided `tokens_filter` within the
+    /// specified block range.
+    ///
+    /// A `tokens_filter` of `[]` or `None` will yield supply changes for all indexed stablecoins.
+    /// If one or more token addresses are specified, only supply changes of these tokens will be
+    /// returned (if present).
+    ///
+    /// A `from_block` of `None` will yield from the earliest indexed block (usually 0).
+    /// A `to_block_inc` of `None` will lead to a head following stream.
+    pub async fn get_stablecoin_supply(
+        &self,
+        tokens_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<StablecoinSupply>> + Send> {
+        self.request(Operation::GetStablecoinSupply {
+            tokens: tokens_filter.into_iter().map(|token| token.0).collect(),
+            start: from_block,
+            end: to_block_inc,
+        })
+        .await
+    }
+
+    /// Get the internal call traces touching the provided `addresses_filter` within the
+    /// specified block range.
+    ///
+    /// A `addresses_filter` of `[]` or `None` will yield all traces. If one or more addresses are
+    /// specified, only calls made from or to these addresses will be returned (if present).
+    ///
+    /// A `from_block` of `None` will yield from the earliest indexed block (usually 0).
+    /// A `to_block_inc` of `None` will lead to a head following stream.
+    pub async fn get_traces(
+        &self,
+        addresses_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<Trace>> + Send> {
+        self.request(Operation::GetTraces {
+            addresses: addresses_filter
+                .into_iter()
+                .map(|address| address.0)
+                .collect(),
+            start: from_block,
+            end: to_block_inc,
+        })
+        .await
+    }
+
+    /// Get the USD denominated TVL of the provided `pairs_filter` within the specified block range.
+    ///
+    /// A `pairs_filter` of `[]` or `None` will yield the TVL of all pairs. If one or more pair
+    /// hashes are specified, only the TVL of these pairs will be returned (if present).
+    ///
+    /// A `from_block` of `None` will yield from the earliest indexed block (usually 0).
+    /// A `to_block_inc` of `None` will lead to a head following stream.
+    pub async fn get_tvl(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<Tvl>> + Send> {
+        self.request(Operation::GetTvl {
             pairs: pairs_filter.into_iter().map(|pair| pair.0).collect(),
             start: from_block,
             end: to_block_inc,
@@ -98,31 +417,436 @@ impl Client {
         .await
     }
 
-    pub async fn get_height(&self) -> Result<u64> {
-        let stream = self.raw_request(Operation::GetHeight).await?;
+    /// Get the LP token supply changes for the provided `pairs_filter` within the specified block
+    /// range.
+    ///
+    /// A `pairs_filter` of `[]` or `None` will yield supply changes for all pairs. If one or more
+    /// pair hashes are specified, only supply changes of these pairs will be returned (if present).
+    ///
+    /// A `from_block` of `None` will yield from the earliest indexed block (usually 0).
+    /// A `to_block_inc` of `None` will lead to a head following stream.
+    pub async fn get_lp_supply(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<LpSupply>> + Send> {
+        self.request(Operation::GetLpSupply {
+            pairs: pairs_filter.into_iter().map(|pair| pair.0).collect(),
+            start: from_block,
+            end: to_block_inc,
+        })
+        .await
+    }
+
+    /// Get the Aave flash loans for the provided `assets_filter` within the specified block range.
+    ///
+    /// A `assets_filter` of `[]` or `None` will yield flash loans of all assets. If one or more
+    /// asset addresses are specified, only flash loans of these assets will be returned (if
+    /// present).
+    ///
+    /// A `from_block` of `None` will yield from the earliest indexed block (usually 0).
+    /// A `to_block_inc` of `None` will lead to a head following stream.
+    pub async fn get_flash_loans(
+        &self,
+        assets_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<FlashLoan>> + Send> {
+        self.request(Operation::GetFlashLoans {
+            assets: assets_filter.into_iter().map(|asset| asset.0).collect(),
+            start: from_block,
+            end: to_block_inc,
+        })
+        .await
+    }
+
+    /// Get the MEV events labeled by the gateway for the provided `pairs_filter` within the
+    /// specified block range.
+    ///
+    /// A `pairs_filter` of `[]` or `None` will yield MEV events of all pairs. If one or more pair
+    /// hashes are specified, only MEV events of these pairs will be returned (if present).
+    ///
+    /// A `from_block` of `None` will yield from the earliest indexed block (usually 0).
+    /// A `to_block_inc` of `None` will lead to a head following stream.
+    pub async fn get_mev_events(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<MevEvent>> + Send> {
+        self.request(Operation::GetMevEvents {
+            pairs: pairs_filter.into_iter().map(|pair| pair.0).collect(),
+            start: from_block,
+            end: to_block_inc,
+        })
+        .await
+    }
+
+    /// Get the cross-chain bridge deposits and withdrawals for the provided `tokens_filter` within
+    /// the specified block range.
+    ///
+    /// A `tokens_filter` of `[]` or `None` will yield transfers of all tokens. If one or more token
+    /// addresses are specified, only transfers of these tokens will be returned (if present).
+    ///
+    /// A `from_block` of `None` will yield from the earliest indexed block (usually 0).
+    /// A `to_block_inc` of `None` will lead to a head following stream.
+    pub async fn get_bridge_transfers(
+        &self,
+        tokens_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<BridgeTransfer>> + Send> {
+        self.request(Operation::GetBridgeTransfers {
+            tokens: tokens_filter.into_iter().map(|token| token.0).collect(),
+            start: from_block,
+            end: to_block_inc,
+        })
+        .await
+    }
+
+    /// Get the beacon chain staking deposits for the provided `validators_filter` within the
+    /// specified block range.
+    ///
+    /// A `validators_filter` of `[]` or `None` will yield the deposits of all validators. If one or
+    /// more validator indices are specified, only deposits of these validators will be returned (if
+    /// present).
+    ///
+    /// A `from_block` of `None` will yield from the earliest indexed block (usually 0).
+    /// A `to_block_inc` of `None` will lead to a head following stream.
+    pub async fn get_staking_deposits(
+        &self,
+        validators_filter: impl IntoIterator<Item = u64>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<StakingDeposit>> + Send> {
+        self.request(Operation::GetStakingDeposits {
+            validators: validators_filter.into_iter().collect(),
+            start: from_block,
+            end: to_block_inc,
+        })
+        .await
+    }
+
+    /// Get the beacon chain staking withdrawals for the provided `validators_filter` within the
+    /// specified block range.
+    ///
+    /// A `validators_filter` of `[]` or `None` will yield the withdrawals of all validators. If one
+    /// or more validator indices are specified, only withdrawals of these validators will be
+    /// returned (if present).
+    ///
+    /// A `from_block` of `None` will yield from the earliest indexed block (usually 0).
+    /// A `to_block_inc` of `None` will lead to a head following stream.
+    pub async fn get_staking_withdrawals(
+        &self,
+        validators_filter: impl IntoIterator<Item = u64>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<StakingWithdrawal>> + Send> {
+        self.request(Operation::GetStakingWithdrawals {
+            validators: validators_filter.into_iter().collect(),
+            start: from_block,
+            end: to_block_inc,
+        })
+        .await
+    }
+
+    /// Get the accrued swap fees per block for the provided `pairs_filter` within the specified
+    /// block range.
+    ///
+    /// A `pairs_filter` of `[]` or `None` will yield the fees of all pairs. If one or more pair
+    /// hashes are specified, only the fees of these pairs will be returned (if present).
+    ///
+    /// A `from_block` of `None` will yield from the earliest indexed block (usually 0).
+    /// A `to_block_inc` of `None` will lead to a head following stream.
+    pub async fn get_fees(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<Fees>> + Send> {
+        self.request(Operation::GetFees {
+            pairs: pairs_filter.into_iter().map(|pair| pair.0).collect(),
+            start: from_block,
+            end: to_block_inc,
+        })
+        .await
+    }
+
+    /// Get the USD prices for the provided `tokens_filter` within the specified block range.
+    ///
+    /// A `tokens_filter` of `[]` or `None` will yield the USD prices of all tokens. If one or more
+    /// token addresses are specified, only the USD prices of these tokens will be returned (if
+    /// present).
+    ///
+    /// A `from_block` of `None` will yield from the earliest indexed block (usually 0).
+    /// A `to_block_inc` of `None` will lead to a head following stream.
+    pub async fn get_usd_prices(
+        &self,
+        tokens_filter: impl IntoIterator<Item = H160>,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<UsdPrice>> + Send> {
+        self.request(Operation::GetUsdPrices {
+            tokens: tokens_filter.into_iter().map(|token| token.0).collect(),
+            start: from_block,
+            end: to_block_inc,
+        })
+        .await
+    }
+
+    /// Get the synthetic exchange rates of `token_a` in `token_b` within the specified block
+    /// range.
+    ///
+    /// The rates are derived gateway-side by routing through intermediate pairs, so this also
+    /// works for tokens without a direct pair.
+    ///
+    /// A `from_block` of `None` will yield from the earliest indexed block (usually 0).
+    /// A `to_block_inc` of `None` will lead to a head following stream.
+    pub async fn get_cross_rates(
+        &self,
+        token_a: H160,
+        token_b: H160,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<CrossRate>> + Send> {
+        self.request(Operation::GetCrossRates {
+            token_a: token_a.0,
+            token_b: token_b.0,
+            start: from_block,
+            end: to_block_inc,
+        })
+        .await
+    }
+
+    /// Get all uniswap v2 pair created events, price quotes and reserves emitted by the
+    /// transaction with the provided `transaction_hash`.
+    pub async fn get_events_for_tx(&self, transaction_hash: H256) -> Result<TransactionEvents> {
+        let (pairs_created, prices, reserves) = futures::try_join!(
+            self.get_transaction_events(transaction_hash, EventKind::Pairs),
+            self.get_transaction_events(transaction_hash, EventKind::Prices),
+            self.get_transaction_events(transaction_hash, EventKind::Reserves),
+        )?;
+
+        Ok(TransactionEvents {
+            pairs_created,
+            prices,
+            reserves,
+        })
+    }
+
+    async fn get_transaction_events<T>(
+        &self,
+        transaction_hash: H256,
+        events: EventKind,
+    ) -> Result<Vec<T>>
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        self.request(Operation::GetTransactionEvents {
+            transaction: transaction_hash.0,
+            events,
+        })
+        .await?
+        .try_collect()
+        .await
+    }
+
+    /// Get the traded volume per `interval` for the provided `pairs_filter` within the specified
+    /// block range.
+    ///
+    /// A `pairs_filter` of `[]` or `None` will yield the volume of all pairs. If one or more pair
+    /// hashes are specified, only the volume of these pairs will be returned (if present).
+    ///
+    /// A `from_block` of `None` will yield from the earliest indexed block (usually 0).
+    /// A `to_block_inc` of `None` will lead to a head following stream.
+    pub async fn get_volume(
+        &self,
+        pairs_filter: impl IntoIterator<Item = H160>,
+        interval: Interval,
+        from_block: Option<u64>,
+        to_block_inc: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<Volume>> + Send> {
+        self.request(Operation::GetVolume {
+            pairs: pairs_filter.into_iter().map(|pair| pair.0).collect(),
+            interval,
+            start: from_block,
+            end: to_block_inc,
+        })
+        .await
+    }
+
+    /// Get the liquidity depth of the provided `pair` at the provided slippage `levels`, given in
+    /// basis points.
+    ///
+    /// The depth is computed for both swap directions from the reserves as of `at_block`. An
+    /// `at_block` of `None` uses the latest reserves.
+    pub async fn get_depth(
+        &self,
+        pair: H160,
+        at_block: Option<u64>,
+        levels: impl IntoIterator<Item = u32>,
+    ) -> Result<Vec<DepthLevel>> {
+        self.request(Operation::GetDepth {
+            pair: pair.0,
+            block: at_block,
+            levels: levels.into_iter().collect(),
+        })
+        .await?
+        .try_collect()
+        .await
+    }
+
+    /// Subscribe to the chain reorganizations observed by the gateway.
+    ///
+    /// This is a head following stream, that yields a [`Reorg`] for every reorg from now on.
+    pub async fn subscribe_reorgs(&self) -> Result<impl Stream<Item = Result<Reorg>> + Send> {
+        self.request(Operation::SubscribeReorgs).await
+    }
+
+    /// Get the finality status of the provided `block`
+    ///
+    /// Returns `None` if the block is not indexed yet.
+    pub async fn get_finality(&self, block: u64) -> Result<Option<Finality>> {
+        let stream = self.request(Operation::GetFinality { block }).await?;
         futures::pin_mut!(stream);
-        let bytes = stream
-            .next()
+        stream.next().await.transpose()
+    }
+
+    pub async fn get_height(&self) -> Result<u64> {
+        self.request_u64(Operation::GetHeight).await
+    }
+
+    /// Get the height of the latest finalized block
+    ///
+    /// Everything up to and including this block is settled and can't be reorged anymore.
+    pub async fn finalized_height(&self) -> Result<u64> {
+        self.request_u64(Operation::GetFinalizedHeight).await
+    }
+
+    /// Get the number of the latest block with a timestamp at or before the provided `timestamp`
+    ///
+    /// Resolved mappings of finalized blocks are cached, so repeated lookups don't hit the gateway.
+    pub async fn block_for_timestamp(&self, timestamp: i64) -> Result<u64> {
+        if let Some(block) = self.block_times.block(timestamp) {
+            return Ok(block);
+        }
+
+        let block = self
+            .request_u64(Operation::GetBlockForTimestamp { timestamp })
+            .await?;
+        // The answer is only settled once the following block is final, too
+        if self.is_finalized(block.saturating_add(1)).await {
+            self.block_times.insert_block(timestamp, block);
+        }
+        Ok(block)
+    }
+
+    /// Get the timestamp of the provided `block`
+    ///
+    /// Resolved mappings of finalized blocks are cached, so repeated lookups don't hit the gateway.
+    pub async fn timestamp_for_block(&self, block: u64) -> Result<i64> {
+        if let Some(timestamp) = self.block_times.timestamp(block) {
+            return Ok(timestamp);
+        }
+
+        let timestamp = self
+            .request_u64(Operation::GetTimestampForBlock { block })
+            .await?;
+        let timestamp = i64::try_from(timestamp).map_err(|_| {
+            Error::Custom(format!(
+                "timestamp {timestamp} of block {block} is out of range"
+            ))
+        })?;
+        if self.is_finalized(block).await {
+            self.block_times.insert_timestamp(block, timestamp);
+        }
+        Ok(timestamp)
+    }
+
+    /// Whether `block` is finalized, refreshing the known finalized height if necessary
+    async fn is_finalized(&self, block: u64) -> bool {
+        if self.block_times.is_finalized(block) {
+            return true;
+        }
+        match self.finalized_height().await {
+            Ok(height) => {
+                self.block_times.set_finalized(height);
+                block <= height
+            }
+            Err(_) => false,
+        }
+    }
+
+    async fn request_u64(&self, operation: Operation) -> Result<u64> {
+        let context = operation.error_context();
+        let height = async {
+            let stream = self.raw_request(operation).await?;
+            futures::pin_mut!(stream);
+            let bytes = stream
+                .next()
+                .await
+                .transpose()?
+                .ok_or_else(|| Error::Custom("empty response from websocket".to_owned()))?;
+            let bytes: [u8; 8] = TryFrom::try_from(&*bytes).map_err(|_| {
+                Error::Custom("failed to collect bytes for height bytes".to_owned())
+            })?;
+            Ok(u64::from_ne_bytes(bytes))
+        };
+        height
             .await
-            .transpose()?
-            .ok_or_else(|| Error::Custom("empty response from websocket".to_owned()))?;
-        let bytes: [u8; 8] = TryFrom::try_from(&*bytes)
-            .map_err(|_| Error::Custom("failed to collect bytes for height bytes".to_owned()))?;
-        Ok(u64::from_ne_bytes(bytes))
+            .map_err(|err: Error| error::observed(err.with_context(context)))
     }
 
     async fn request<T>(&self, operation: Operation) -> Result<impl Stream<Item = Result<T>> + Send>
     where
-        T: serde::de::DeserializeOwned + 'static,
+        T: serde::de::DeserializeOwned + Send + 'static,
     {
-        let raw_data_stream = self.raw_request(operation).await?.boxed();
+        let context = operation.error_context();
+        let raw_data_stream = self
+            .raw_request(operation)
+            .await
+            .map_err(|err| error::observed(err.with_context(context.clone())))?
+            .boxed();
+
+        let reader = raw_data_stream.into_async_read();
+        let rows = if self.fast_decode {
+            crate::decode::reusing_rows(reader).left_stream()
+        } else {
+            csv_async::AsyncDeserializer::from_reader(reader)
+                .into_deserialize()
+                .map_err(Error::from)
+                .into_stream()
+                .right_stream()
+        };
+        let stream = rows
+            .map_err(move |err| error::observed(err.with_context(context.clone())))
+            .inspect(|row| telemetry::decoded(Transport::Ws, row));
 
-        let stream = csv_async::AsyncDeserializer::from_reader(raw_data_stream.into_async_read())
-            .into_deserialize()
-            .map_err(Error::from)
-            .into_stream();
+        Ok(self.decode_policy.apply(stream))
+    }
 
-        Ok(stream)
+    /// Like [`Client::request`], but decoding the rows in batches of up to `batch_size` rows
+    async fn request_batched<T>(
+        &self,
+        operation: Operation,
+        batch_size: usize,
+    ) -> Result<impl Stream<Item = Result<Vec<T>>> + Send>
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        let context = operation.error_context();
+        let reader = self
+            .raw_request(operation)
+            .await
+            .map_err(|err| error::observed(err.with_context(context.clone())))?
+            .boxed()
+            .into_async_read();
+        let policy = self.decode_policy.clone();
+        Ok(
+            crate::decode::reusing_batches(reader, batch_size, Transport::Ws, policy)
+                .map_err(move |err| error::observed(err.with_context(context.clone()))),
+        )
     }
 
     async fn raw_request(
@@ -130,6 +854,7 @@ impl Client {
         operation: Operation,
     ) -> Result<impl Stream<Item = Result<Vec<u8>, std::io::Error>> + Send> {
         let (tx, rx) = mpsc::unbounded_channel();
+        telemetry::request(Transport::Ws);
         self.backend_tx
             .send((operation, tx))
             .await
@@ -139,7 +864,10 @@ impl Client {
             let res = rx.recv().await?;
 
             match res {
-                Ok(data) => Some((Ok(data), rx)),
+                Ok(data) => {
+                    telemetry::bytes(Transport::Ws, data.len());
+                    Some((Ok(data), rx))
+                }
                 Err(err) => Some((Err(std::io::Error::new(std::io::ErrorKind::Other, err)), rx)),
             }
         });
@@ -148,33 +876,57 @@ impl Client {
     }
 }
 
+#[cfg(feature = "ws")]
 struct BackGroundWorker<S> {
     websocket: WebSocketStream<S>,
     operation_rx: mpsc::Receiver<OperationMsg>,
-    subscriptions: Vec<Option<mpsc::UnboundedSender<WsMsg>>>,
-    next_id: u8,
+    subscriptions: Subscriptions,
+    shutdown_rx: mpsc::UnboundedReceiver<(Shutdown, shutdown::Worker)>,
+    // The worker is dropped with the background worker, after the connection was closed
+    shutdown: Option<(Shutdown, shutdown::Worker)>,
 }
 
+#[cfg(feature = "ws")]
 impl<S> BackGroundWorker<S>
 where
     S: AsyncRead + AsyncWrite + Unpin + Send,
 {
-    fn new(websocket: WebSocketStream<S>, operation_rx: mpsc::Receiver<OperationMsg>) -> Self {
+    fn new(
+        websocket: WebSocketStream<S>,
+        operation_rx: mpsc::Receiver<OperationMsg>,
+        shutdown_rx: mpsc::UnboundedReceiver<(Shutdown, shutdown::Worker)>,
+    ) -> Self {
         Self {
             websocket,
             operation_rx,
-            subscriptions: vec![None; 256],
-            next_id: 0,
+            subscriptions: Subscriptions::new(),
+            shutdown_rx,
+            shutdown: None,
         }
     }
 
     async fn run(mut self) -> Result<()> {
+        let result = self.serve().await;
+        if result.is_err() {
+            self.subscriptions.close_all();
+        }
+        result
+    }
+
+    async fn serve(&mut self) -> Result<()> {
         use futures::future::Either;
 
         loop {
             let next_ws_msg = self.websocket.next();
             let next_operation = self.operation_rx.recv();
             let ping = tokio::time::sleep(std::time::Duration::from_secs(1));
+            let shutdown = self.shutdown.as_ref().map(|(shutdown, _)| shutdown.clone());
+            let triggered = async move {
+                match shutdown {
+                    Some(shutdown) => shutdown.triggered().await,
+                    None => futures::future::pending().await,
+                }
+            };
 
             let either = {
                 futures::pin_mut!(next_operation);
@@ -186,16 +938,28 @@ where
                         self.websocket.send(Message::Ping(Vec::new())).await?;
                         continue;
                     }
+                    Some(shutdown) = self.shutdown_rx.recv() => {
+                        self.shutdown = Some(shutdown);
+                        continue;
+                    }
+                    _ = triggered => {
+                        self.websocket.close(None).await?;
+                        break;
+                    }
                 }
             };
 
             match either {
                 Either::Left(Some(msg)) => self.handle_msg(msg?).await?,
-                Either::Left(None) => break,
+                Either::Left(None) => return Err(Error::ConnectionClosed),
                 Either::Right(Some((operation, sender))) => {
                     self.send_request(operation, sender).await?
                 }
-                Either::Right(None) => break,
+                // All clients were dropped, so close the connection cleanly
+                Either::Right(None) => {
+                    self.websocket.close(None).await?;
+                    break;
+                }
             }
         }
 
@@ -211,16 +975,137 @@ where
             _ => return Err(Error::UnexpectedMessage),
         };
 
+        self.subscriptions.handle_data(data)
+    }
+
+    async fn send_request(
+        &mut self,
+        operation: Operation,
+        sender: mpsc::UnboundedSender<WsMsg>,
+    ) -> Result<()> {
+        let (id, payload) = self.subscriptions.subscribe(operation, sender)?;
+        if let Err(err) = self.send_msg(Message::Binary(payload)).await {
+            self.subscriptions.remove(id);
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    async fn send_msg(&mut self, msg: Message) -> Result<()> {
+        self.websocket.send(msg).await?;
+        Ok(())
+    }
+}
+
+/// The background worker of a browser WebSocket connection
+///
+/// Browsers answer pings on their own, so there is no need to send any.
+#[cfg(feature = "wasm")]
+struct BrowserWorker {
+    websocket: gloo_net::websocket::futures::WebSocket,
+    operation_rx: mpsc::Receiver<OperationMsg>,
+    subscriptions: Subscriptions,
+}
+
+#[cfg(feature = "wasm")]
+impl BrowserWorker {
+    async fn run(mut self) -> Result<()> {
+        let result = self.serve().await;
+        if result.is_err() {
+            self.subscriptions.close_all();
+        }
+        result
+    }
+
+    async fn serve(&mut self) -> Result<()> {
+        use futures::future::Either;
+        use gloo_net::websocket::{Message, WebSocketError};
+
+        loop {
+            let either = tokio::select! {
+                val = self.websocket.next() => Either::Left(val),
+                val = self.operation_rx.recv() => Either::Right(val),
+            };
+
+            match either {
+                Either::Left(Some(Ok(Message::Bytes(data)))) => {
+                    self.subscriptions.handle_data(data)?
+                }
+                Either::Left(Some(Ok(Message::Text(_)))) => return Err(Error::UnexpectedMessage),
+                Either::Left(Some(Err(WebSocketError::ConnectionClose(_)))) => {
+                    return Err(Error::ConnectionClosed)
+                }
+                Either::Left(Some(Err(err))) => return Err(Error::Custom(err.to_string())),
+                Either::Left(None) => return Err(Error::ConnectionClosed),
+                Either::Right(Some((operation, sender))) => {
+                    let (id, payload) = self.subscriptions.subscribe(operation, sender)?;
+                    if let Err(err) = self.websocket.send(Message::Bytes(payload)).await {
+                        self.subscriptions.remove(id);
+                        return Err(Error::Custom(err.to_string()));
+                    }
+                }
+                Either::Right(None) => break,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The open requests of a connection, indexed by their request id
+struct Subscriptions {
+    senders: Vec<Option<mpsc::UnboundedSender<WsMsg>>>,
+    next_id: u8,
+}
+
+impl Subscriptions {
+    fn new() -> Self {
+        Self {
+            senders: vec![None; 256],
+            next_id: 0,
+        }
+    }
+
+    /// Register `sender` for the responses of `operation`, returning the request to send
+    fn subscribe(
+        &mut self,
+        operation: Operation,
+        sender: mpsc::UnboundedSender<WsMsg>,
+    ) -> Result<(u8, Vec<u8>)> {
+        let id = self.allocate_id()?;
+        let request = Request { id, operation };
+        let payload = serde_cbor::to_vec(&request)?;
+
+        self.senders[id as usize] = Some(sender);
+        Ok((id, payload))
+    }
+
+    fn remove(&mut self, id: u8) {
+        let _ = self.senders[id as usize].take();
+    }
+
+    /// Fail all open requests once the connection is lost
+    ///
+    /// Dropping the senders instead would end the streams as if they were complete.
+    fn close_all(&mut self) {
+        for sender in self.senders.iter_mut().filter_map(Option::take) {
+            let _ = sender.send(Err(Error::ConnectionClosed));
+        }
+    }
+
+    /// Forward a binary message of the server to the subscription it belongs to
+    fn handle_data(&mut self, data: Vec<u8>) -> Result<()> {
         let (header, data) = Header::try_from_data(data)?;
 
         let msg = if header.marker.contains(MsgMarker::END) {
-            let _ = self.subscriptions[header.id as usize].take();
+            self.remove(header.id);
             return Ok(());
         } else if header.marker.contains(MsgMarker::START) {
             return Ok(());
         } else if header.marker.contains(MsgMarker::ERROR) {
             match String::from_utf8(data) {
-                Ok(s) => Err(Error::ErrorMsg(s)),
+                Ok(s) => Err(Error::from_server_message(s)),
                 Err(_) => Err(Error::UnexpectedMessageFormat),
             }
         } else if header.marker.contains(MsgMarker::CONTINUE) {
@@ -232,7 +1117,7 @@ where
         // Even when the receiver is closed, we have to keep the subscription until the server
         // sends `END`. Otherwise we might reuse the id and get confusing responses.
         // We don't support unsubscribing for WebSocket yet :(
-        let _ = self.subscriptions[header.id as usize]
+        let _ = self.senders[header.id as usize]
             .as_ref()
             .ok_or(Error::UnknownResponseId)?
             .send(msg);
@@ -240,34 +1125,11 @@ where
         Ok(())
     }
 
-    async fn send_request(
-        &mut self,
-        operation: Operation,
-        sender: mpsc::UnboundedSender<WsMsg>,
-    ) -> Result<()> {
-        let id = self.allocate_id()?;
-        let request = Request { id, operation };
-        let payload = serde_cbor::to_vec(&request)?;
-
-        self.subscriptions[id as usize] = Some(sender);
-        if let Err(err) = self.send_msg(Message::Binary(payload)).await {
-            let _ = self.subscriptions[id as usize].take();
-            return Err(err);
-        }
-
-        Ok(())
-    }
-
-    async fn send_msg(&mut self, msg: Message) -> Result<()> {
-        self.websocket.send(msg).await?;
-        Ok(())
-    }
-
     fn allocate_id(&mut self) -> Result<u8> {
-        let id = match self.subscriptions[self.next_id as usize] {
+        let id = match self.senders[self.next_id as usize] {
             None => self.next_id,
             Some(_) => self
-                .subscriptions
+                .senders
                 .iter()
                 .enumerate()
                 .find(|(_, opt)| opt.is_none())
@@ -295,17 +1157,161 @@ enum Operation {
         start: Option<u64>,
         end: Option<u64>,
     },
+    GetPairsForToken {
+        token: [u8; 20],
+    },
+    GetPairInfo {
+        pair: [u8; 20],
+    },
     GetPrices {
         pairs: Vec<[u8; 20]>,
         start: Option<u64>,
         end: Option<u64>,
+        #[serde(flatten)]
+        options: PriceOptions,
+    },
+    GetLatestPrice {
+        pair: [u8; 20],
     },
     GetReserves {
         pairs: Vec<[u8; 20]>,
         start: Option<u64>,
         end: Option<u64>,
+        #[serde(flatten)]
+        options: ReserveOptions,
+    },
+    GetStablecoinSupply {
+        tokens: Vec<[u8; 20]>,
+        start: Option<u64>,
+        end: Option<u64>,
+    },
+    GetTraces {
+        addresses: Vec<[u8; 20]>,
+        start: Option<u64>,
+        end: Option<u64>,
+    },
+    GetTvl {
+        pairs: Vec<[u8; 20]>,
+        start: Option<u64>,
+        end: Option<u64>,
+    },
+    GetLpSupply {
+        pairs: Vec<[u8; 20]>,
+        start: Option<u64>,
+        end: Option<u64>,
+    },
+    GetFlashLoans {
+        assets: Vec<[u8; 20]>,
+        start: Option<u64>,
+        end: Option<u64>,
+    },
+    GetMevEvents {
+        pairs: Vec<[u8; 20]>,
+        start: Option<u64>,
+        end: Option<u64>,
+    },
+    GetBridgeTransfers {
+        tokens: Vec<[u8; 20]>,
+        start: Option<u64>,
+        end: Option<u64>,
+    },
+    GetStakingDeposits {
+        validators: Vec<u64>,
+        start: Option<u64>,
+        end: Option<u64>,
+    },
+    GetStakingWithdrawals {
+        validators: Vec<u64>,
+        start: Option<u64>,
+        end: Option<u64>,
+    },
+    GetFees {
+        pairs: Vec<[u8; 20]>,
+        start: Option<u64>,
+        end: Option<u64>,
+    },
+    GetUsdPrices {
+        tokens: Vec<[u8; 20]>,
+        start: Option<u64>,
+        end: Option<u64>,
+    },
+    #[serde(rename_all = "camelCase")]
+    GetCrossRates {
+        token_a: [u8; 20],
+        token_b: [u8; 20],
+        start: Option<u64>,
+        end: Option<u64>,
+    },
+    GetTransactionEvents {
+        transaction: [u8; 32],
+        events: EventKind,
+    },
+    GetVolume {
+        pairs: Vec<[u8; 20]>,
+        interval: Interval,
+        start: Option<u64>,
+        end: Option<u64>,
+    },
+    GetDepth {
+        pair: [u8; 20],
+        block: Option<u64>,
+        levels: Vec<u32>,
+    },
+    SubscribeReorgs,
+    GetFinality {
+        block: u64,
     },
     GetHeight,
+    GetFinalizedHeight,
+    GetBlockForTimestamp {
+        timestamp: i64,
+    },
+    GetTimestampForBlock {
+        block: u64,
+    },
+}
+
+impl Operation {
+    /// The context of errors of this operation, with its parameters as JSON
+    fn error_context(&self) -> ErrorContext {
+        let mut params = serde_json::to_value(self).unwrap_or_default();
+        let operation = params
+            .as_object_mut()
+            .and_then(|params| params.remove("operation"))
+            .and_then(|operation| operation.as_str().map(str::to_owned))
+            .unwrap_or_default();
+        hex_bytes(&mut params);
+        ErrorContext::default().with_operation(operation, params.to_string())
+    }
+}
+
+/// Replace the byte arrays of addresses and hashes in `value` with hex strings
+fn hex_bytes(value: &mut serde_json::Value) {
+    use serde_json::Value;
+
+    match value {
+        Value::Array(items)
+            if matches!(items.len(), 20 | 32) && items.iter().all(Value::is_u64) =>
+        {
+            let hex: String = items
+                .iter()
+                .filter_map(Value::as_u64)
+                .map(|byte| format!("{byte:02x}"))
+                .collect();
+            *value = Value::String(format!("0x{hex}"));
+        }
+        Value::Array(items) => items.iter_mut().for_each(hex_bytes),
+        Value::Object(fields) => fields.values_mut().for_each(hex_bytes),
+        _ => {}
+    }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+enum EventKind {
+    Pairs,
+    Prices,
+    Reserves,
 }
 
 struct Header {
@@ -349,3 +1355,33 @@ bitflags::bitflags! {
         const SUBSCRIPTION = 0b01000000;
     }
 }
+
+#[cfg(all(test, feature = "ws"))]
+mod tests {
+    use std::time::Duration;
+
+    use tungstenite::protocol::Role;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn sends_close_before_shutdown_completes() {
+        let (client, server) = tokio::io::duplex(1024);
+        let client = WebSocketStream::from_raw_socket(client, Role::Client, None).await;
+        let mut server = WebSocketStream::from_raw_socket(server, Role::Server, None).await;
+
+        let shutdown = Shutdown::new();
+        let _client = Client::new(client).await.with_shutdown(&shutdown);
+        tokio::time::timeout(Duration::from_secs(5), shutdown.shutdown())
+            .await
+            .expect("the shutdown completes");
+
+        loop {
+            match server.next().await {
+                Some(Ok(Message::Close(_))) => break,
+                Some(Ok(Message::Ping(_))) => continue,
+                other => panic!("expected a close frame, got {other:?}"),
+            }
+        }
+    }
+}